@@ -0,0 +1,652 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::db::{self, ConnectionStore, DbConnection, SavedConnection, SettingsStore, WatermarkStore};
+
+/// Exit code: no drift found / all statements applied
+pub const EXIT_OK: i32 = 0;
+/// Exit code: drift found
+pub const EXIT_DRIFT: i32 = 1;
+/// Exit code: invalid arguments, connection failure or failed statements
+pub const EXIT_ERROR: i32 = 2;
+
+const USAGE: &str = "Usage: syncforge-tui [COMMAND] [OPTIONS]
+
+Without a command the interactive UI is started.
+
+Commands:
+  schema-diff   Compare schemas of two saved connections
+  data-diff     Compare table data of two saved connections
+  apply         Apply schema (default) or data differences to the target
+  check-mapping List columns left unmatched by the table and column name mapping
+  copy          Copy all rows of tables into their empty counterparts in the target
+
+Options:
+  --source <NAME>    Saved connection to use as source
+  --target <NAME>    Saved connection to use as target
+  --table <TABLE>    Table to compare or sync (repeatable, default: all tables,
+                     parents before the tables referencing them)
+  --data             apply: sync table data instead of schema
+  --checksum         Compare data by server-side checksums of key ranges first
+                     (source and target must be the same database type)
+  --key <TABLE>:<COLUMNS>
+                     Columns identifying a row of TABLE, comma-separated (repeatable;
+                     default: primary key, then a unique index on non-null columns)
+  --where <TABLE>:<CONDITION>
+                     Only compare rows of TABLE matching the SQL condition (repeatable)
+  --target-where <TABLE>:<CONDITION>
+                     Condition for the target rows of TABLE, when --where names columns
+                     or tables differently there (repeatable; default: --where)
+  --include <TABLE>:<COLUMNS>
+                     Only compare these columns of TABLE; * and ? wildcards (repeatable)
+  --exclude <TABLE>:<COLUMNS>
+                     Ignore these columns of TABLE when comparing and updating (repeatable)
+  --ignore <PATTERNS>
+                     Column patterns ignored in every table, e.g. '*_at' (repeatable)
+  --target-table <TABLE>:<TARGET_TABLE>
+                     Name of source table TABLE in the target (repeatable)
+  --map <TABLE>:<SOURCE>=<TARGET>[,...]
+                     Target names of columns of TABLE named differently (repeatable)
+  --watermark <TABLE>:<COLUMN>
+                     Only compare rows of TABLE at or beyond the watermark of the last
+                     `apply --data`, an ever-growing column such as updated_at (repeatable)
+  --full             Compare all rows, ignoring saved watermarks
+  --sql-mode <MODE>  How row changes are written: row (default, one statement per row),
+                     multi (multi-row INSERTs) or upsert (multi-row upserts by key)
+  --rows-per-statement <N>
+                     Rows per multi-row INSERT or upsert (default: 100)
+  --batch-size <N>   copy: rows read and written per batch (default: 1000)
+  --concurrency <N>  Tables whose data is compared at the same time (default: 4)
+  --format <FORMAT>  Output format: text (default), json, sql (a migration script for schema-diff)
+  -h, --help         Print this help
+
+Exit codes:
+  0  no drift found / changes applied
+  1  drift found (check-mapping: unmapped columns found)
+  2  error";
+
+/// Output format for diff commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Sql,
+}
+
+/// Headless command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    SchemaDiff,
+    DataDiff,
+    Apply,
+    CheckMapping,
+    Copy,
+    Help,
+}
+
+/// Parsed command line
+#[derive(Debug, Clone)]
+pub struct CliArgs {
+    pub command: Command,
+    pub source: String,
+    pub target: String,
+    pub tables: Vec<String>,
+    pub data: bool,
+    pub checksum: bool,
+    /// Compare settings by table name, overriding the saved ones
+    pub options: HashMap<String, db::CompareOptions>,
+    /// Column patterns ignored in every table, in addition to the saved ones
+    pub ignored_columns: Vec<String>,
+    /// Ignore saved watermarks
+    pub full: bool,
+    /// How data changes are written for `--format sql` and `apply --data`
+    pub sql: db::SqlOptions,
+    /// Rows per batch of a table copy
+    pub batch_size: usize,
+    /// Tables whose data is compared at the same time
+    pub concurrency: usize,
+    pub format: OutputFormat,
+}
+
+/// Parse command line arguments (without the program name).
+/// Returns `None` when no command is given and the UI should start.
+pub fn parse_args(args: &[String]) -> Result<Option<CliArgs>> {
+    let mut iter = args.iter();
+    let command = match iter.next().map(|s| s.as_str()) {
+        None => return Ok(None),
+        Some("schema-diff") => Command::SchemaDiff,
+        Some("data-diff") => Command::DataDiff,
+        Some("apply") => Command::Apply,
+        Some("check-mapping") => Command::CheckMapping,
+        Some("copy") => Command::Copy,
+        Some("-h") | Some("--help") | Some("help") => Command::Help,
+        Some(other) => return Err(anyhow::anyhow!("Unknown command: {}", other)),
+    };
+
+    let mut cli = CliArgs {
+        command,
+        source: String::new(),
+        target: String::new(),
+        tables: Vec::new(),
+        data: false,
+        checksum: false,
+        options: HashMap::new(),
+        ignored_columns: Vec::new(),
+        full: false,
+        sql: db::SqlOptions::default(),
+        batch_size: db::DEFAULT_COPY_BATCH_SIZE,
+        concurrency: db::DEFAULT_TABLE_CONCURRENCY,
+        format: OutputFormat::default(),
+    };
+
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "--source" => cli.source = value("--source")?,
+            "--target" => cli.target = value("--target")?,
+            "--table" => cli.tables.push(value("--table")?),
+            "--data" => cli.data = true,
+            "--checksum" => cli.checksum = true,
+            "--key" => {
+                let spec = value("--key")?;
+                let (table, columns) = spec
+                    .split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("--key expects <TABLE>:<COLUMN>[,<COLUMN>...]"))?;
+                cli.options.entry(table.to_string()).or_default().key_columns =
+                    columns.split(',').map(|c| c.trim().to_string()).collect();
+            }
+            "--include" | "--exclude" => {
+                let spec = value(arg)?;
+                let (table, columns) = spec
+                    .split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("{} expects <TABLE>:<COLUMN>[,<COLUMN>...]", arg))?;
+                let options = cli.options.entry(table.to_string()).or_default();
+                let columns = columns.split(',').map(|c| c.trim().to_string());
+                if arg == "--include" {
+                    options.include_columns.extend(columns);
+                } else {
+                    options.exclude_columns.extend(columns);
+                }
+            }
+            "--ignore" => cli
+                .ignored_columns
+                .extend(value("--ignore")?.split(',').map(|c| c.trim().to_string())),
+            "--target-table" => {
+                let spec = value("--target-table")?;
+                let (table, target_table) = spec
+                    .split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("--target-table expects <TABLE>:<TARGET_TABLE>"))?;
+                cli.options.entry(table.to_string()).or_default().target_table = target_table.trim().to_string();
+            }
+            "--map" => {
+                let spec = value("--map")?;
+                let (table, pairs) = spec
+                    .split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("--map expects <TABLE>:<SOURCE>=<TARGET>[,...]"))?;
+                cli.options
+                    .entry(table.to_string())
+                    .or_default()
+                    .column_map
+                    .extend(db::parse_column_map(pairs)?);
+            }
+            "--where" => {
+                let spec = value("--where")?;
+                let (table, filter) = spec
+                    .split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("--where expects <TABLE>:<CONDITION>"))?;
+                cli.options.entry(table.to_string()).or_default().filter = filter.trim().to_string();
+            }
+            "--target-where" => {
+                let spec = value("--target-where")?;
+                let (table, filter) = spec
+                    .split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("--target-where expects <TABLE>:<CONDITION>"))?;
+                cli.options.entry(table.to_string()).or_default().target_filter = filter.trim().to_string();
+            }
+            "--watermark" => {
+                let spec = value("--watermark")?;
+                let (table, column) = spec
+                    .split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("--watermark expects <TABLE>:<COLUMN>"))?;
+                cli.options.entry(table.to_string()).or_default().watermark_column = column.trim().to_string();
+            }
+            "--full" => cli.full = true,
+            "--sql-mode" => {
+                cli.sql.mode = match value("--sql-mode")?.as_str() {
+                    "row" => db::SqlMode::PerRow,
+                    "multi" => db::SqlMode::MultiRow,
+                    "upsert" => db::SqlMode::Upsert,
+                    other => return Err(anyhow::anyhow!("Unknown SQL mode: {}", other)),
+                }
+            }
+            "--rows-per-statement" => {
+                cli.sql.rows_per_statement = value("--rows-per-statement")?
+                    .parse()
+                    .ok()
+                    .filter(|rows| *rows > 0)
+                    .ok_or_else(|| anyhow::anyhow!("--rows-per-statement expects a positive number"))?;
+            }
+            "--batch-size" => {
+                cli.batch_size = value("--batch-size")?
+                    .parse()
+                    .ok()
+                    .filter(|rows| *rows > 0)
+                    .ok_or_else(|| anyhow::anyhow!("--batch-size expects a positive number"))?;
+            }
+            "--concurrency" => {
+                cli.concurrency = value("--concurrency")?
+                    .parse()
+                    .ok()
+                    .filter(|tables| *tables > 0)
+                    .ok_or_else(|| anyhow::anyhow!("--concurrency expects a positive number"))?;
+            }
+            "--format" => {
+                cli.format = match value("--format")?.as_str() {
+                    "text" => OutputFormat::Text,
+                    "json" => OutputFormat::Json,
+                    "sql" => OutputFormat::Sql,
+                    other => return Err(anyhow::anyhow!("Unknown format: {}", other)),
+                }
+            }
+            "-h" | "--help" => cli.command = Command::Help,
+            other => return Err(anyhow::anyhow!("Unknown option: {}", other)),
+        }
+    }
+
+    if cli.command != Command::Help && (cli.source.is_empty() || cli.target.is_empty()) {
+        return Err(anyhow::anyhow!("--source and --target are required"));
+    }
+
+    Ok(Some(cli))
+}
+
+/// Print usage, prefixed with an error message if any
+pub fn print_usage(error: Option<&str>) {
+    if let Some(e) = error {
+        eprintln!("Error: {}\n", e);
+    }
+    eprintln!("{}", USAGE);
+}
+
+/// Run a headless command and return the process exit code
+pub async fn run(cli: CliArgs) -> i32 {
+    let result = match cli.command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(EXIT_OK)
+        }
+        Command::SchemaDiff => schema_diff(&cli).await,
+        Command::DataDiff => data_diff(&cli).await,
+        Command::Apply if cli.data => apply_data(&cli).await,
+        Command::CheckMapping => check_mapping(&cli).await,
+        Command::Copy => copy_tables(&cli).await,
+        Command::Apply => apply_schema(&cli).await,
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// Look up a saved connection by name
+fn find_connection(store: &ConnectionStore, name: &str) -> Result<SavedConnection> {
+    store
+        .get_all()
+        .iter()
+        .find(|c| c.name == name)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("No saved connection named '{}'", name))
+}
+
+/// Connect to the source and target connections
+async fn connect_pair(cli: &CliArgs) -> Result<(SavedConnection, DbConnection, SavedConnection, DbConnection)> {
+    let store = ConnectionStore::new()?;
+    let source = find_connection(&store, &cli.source)?;
+    let target = find_connection(&store, &cli.target)?;
+    let source_conn = DbConnection::connect(&source.config).await?;
+    let target_conn = DbConnection::connect(&target.config).await?;
+    Ok((source, source_conn, target, target_conn))
+}
+
+/// Compare settings of every table with saved or command line settings,
+/// the command line overriding the saved ones field by field
+fn compare_options(cli: &CliArgs, settings: &SettingsStore, source: &str, target: &str) -> HashMap<String, db::CompareOptions> {
+    let mut options = settings.table_options(source, target);
+    for (table, overrides) in &cli.options {
+        let options = options.entry(table.clone()).or_default();
+        if !overrides.key_columns.is_empty() {
+            options.key_columns = overrides.key_columns.clone();
+        }
+        if !overrides.filter.is_empty() {
+            options.filter = overrides.filter.clone();
+        }
+        if !overrides.target_filter.is_empty() {
+            options.target_filter = overrides.target_filter.clone();
+        }
+        if !overrides.include_columns.is_empty() {
+            options.include_columns = overrides.include_columns.clone();
+        }
+        if !overrides.exclude_columns.is_empty() {
+            options.exclude_columns = overrides.exclude_columns.clone();
+        }
+        if !overrides.target_table.is_empty() {
+            options.target_table = overrides.target_table.clone();
+        }
+        if !overrides.column_map.is_empty() {
+            options.column_map = overrides.column_map.clone();
+        }
+        if !overrides.watermark_column.is_empty() {
+            options.watermark_column = overrides.watermark_column.clone();
+        }
+    }
+    options
+}
+
+async fn compute_schema_diffs(cli: &CliArgs) -> Result<(DbConnection, db::DbType, Vec<db::DiffResult>)> {
+    let (source, source_conn, target, target_conn) = connect_pair(cli).await?;
+    let options = compare_options(cli, &SettingsStore::new()?, &source.name, &target.name);
+    let source_schema = source_conn.get_schema(&source.config.database).await?;
+    let target_schema = target_conn.get_schema(&target.config.database).await?;
+    // Compare under the target names of mapped tables and columns
+    let source_schema = db::map_schema(&source_schema, &options);
+    let mut diffs = db::compare_schemas(&source_schema, &target_schema, target.config.db_type);
+    if !cli.tables.is_empty() {
+        let default = db::CompareOptions::default();
+        let target_tables: Vec<&str> = cli
+            .tables
+            .iter()
+            .map(|t| options.get(t).unwrap_or(&default).target_table(t))
+            .collect();
+        diffs.retain(|d| target_tables.contains(&d.table_name.as_str()));
+    }
+    Ok((target_conn, target.config.db_type, diffs))
+}
+
+/// Data differences of the selected tables and the statements applying them
+struct DataDiffs {
+    target_conn: DbConnection,
+    summary: Vec<db::TableDataInfo>,
+    diffs: Vec<db::DataDiffResult>,
+    statements: Vec<db::DataStatement>,
+    /// Watermarks of the source tables read before comparing, reached once
+    /// the statements are applied
+    watermarks: Vec<db::SavedWatermark>,
+}
+
+async fn compute_data_diffs(cli: &CliArgs) -> Result<DataDiffs> {
+    let (source, source_conn, target, target_conn) = connect_pair(cli).await?;
+    let settings = SettingsStore::new()?;
+    let saved_watermarks = WatermarkStore::new()?.table_watermarks(&source.name, &target.name);
+    let mut table_options = compare_options(cli, &settings, &source.name, &target.name);
+    let tables = if cli.tables.is_empty() {
+        source_conn.get_tables_in_dependency_order().await?
+    } else {
+        cli.tables.clone()
+    };
+    let mut watermarks = Vec::new();
+    for table in &tables {
+        let options = table_options.entry(table.clone()).or_default();
+        options.exclude_columns.extend(settings.ignored_columns().iter().cloned());
+        options.exclude_columns.extend(cli.ignored_columns.iter().cloned());
+        if !cli.full {
+            db::apply_watermark(options, saved_watermarks.get(table));
+        }
+        if let Some(value) = db::read_watermark(&source_conn, table, &source.config.database, options).await? {
+            watermarks.push(db::SavedWatermark {
+                source: source.name.clone(),
+                target: target.name.clone(),
+                table: table.clone(),
+                column: options.watermark_column.clone(),
+                value,
+            });
+        }
+    }
+
+    let database = &source.config.database;
+    let results = db::compare_tables(
+        &source_conn,
+        &target_conn,
+        &tables,
+        database,
+        &table_options,
+        cli.checksum,
+        true,
+        cli.concurrency,
+        |_| {},
+    )
+    .await;
+
+    let mut summary = Vec::new();
+    let mut diffs = Vec::new();
+    let mut statements = Vec::new();
+    for (info, comparison) in results {
+        let Some(comparison) = comparison else {
+            return Err(anyhow::anyhow!("{}: {}", info.table_name, info.error.unwrap_or_default()));
+        };
+        let table_diffs: Vec<&db::DataDiffResult> = comparison.diffs.iter().collect();
+        statements.extend(db::data_statements(
+            &table_diffs,
+            &comparison.target_table,
+            target.config.db_type,
+            &cli.sql,
+        ));
+        summary.push(info);
+        diffs.extend(comparison.diffs);
+    }
+    Ok(DataDiffs { target_conn, summary, diffs, statements, watermarks })
+}
+
+async fn schema_diff(cli: &CliArgs) -> Result<i32> {
+    let (_, target_db_type, diffs) = compute_schema_diffs(cli).await?;
+
+    match cli.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diffs)?),
+        OutputFormat::Sql => print!("{}", db::migration_script(&diffs, target_db_type)),
+        OutputFormat::Text => {
+            for diff in &diffs {
+                let type_str = match diff.diff_type {
+                    db::DiffType::Added => "ADD",
+                    db::DiffType::Removed => "DROP",
+                    db::DiffType::Modified => "MODIFY",
+                };
+                println!("{:<7} {:<30} {}", type_str, diff.table_name, diff.detail);
+                for line in diff.body_diff.lines() {
+                    println!("        {}", line);
+                }
+            }
+            println!("{} differences", diffs.len());
+        }
+    }
+
+    Ok(if diffs.is_empty() { EXIT_OK } else { EXIT_DRIFT })
+}
+
+async fn data_diff(cli: &CliArgs) -> Result<i32> {
+    let DataDiffs { summary, diffs, statements, .. } = compute_data_diffs(cli).await?;
+
+    match cli.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diffs)?),
+        OutputFormat::Sql => {
+            for statement in &statements {
+                println!("{}", statement.sql);
+            }
+        }
+        OutputFormat::Text => {
+            for info in summary.iter().filter(|info| info.difference_count() > 0) {
+                println!(
+                    "TABLE   {:<30} {} inserts, {} updates, {} deletes",
+                    info.table_name, info.insert_count, info.update_count, info.delete_count
+                );
+            }
+            let count = |t: db::DataDiffType| diffs.iter().filter(|d| d.diff_type == t).count();
+            println!(
+                "{} differences ({} inserts, {} updates, {} deletes)",
+                diffs.len(),
+                count(db::DataDiffType::Insert),
+                count(db::DataDiffType::Update),
+                count(db::DataDiffType::Delete)
+            );
+        }
+    }
+
+    Ok(if diffs.is_empty() { EXIT_OK } else { EXIT_DRIFT })
+}
+
+async fn check_mapping(cli: &CliArgs) -> Result<i32> {
+    let (source, source_conn, target, target_conn) = connect_pair(cli).await?;
+    let table_options = compare_options(cli, &SettingsStore::new()?, &source.name, &target.name);
+    let tables = if cli.tables.is_empty() {
+        source_conn.get_tables().await?
+    } else {
+        cli.tables.clone()
+    };
+
+    let mut report = Vec::new();
+    for table in &tables {
+        let options = table_options.get(table).cloned().unwrap_or_default();
+        let unmapped = db::check_mapping(&source_conn, &target_conn, table, &options).await?;
+        report.push((table, options.target_table(table).to_string(), unmapped));
+    }
+    let unmapped_tables = report.iter().filter(|(_, _, unmapped)| !unmapped.is_empty()).count();
+
+    match cli.format {
+        OutputFormat::Json => {
+            let report: Vec<serde_json::Value> = report
+                .iter()
+                .map(|(table, target_table, unmapped)| {
+                    serde_json::json!({
+                        "table": table,
+                        "target_table": target_table,
+                        "unmapped_source_columns": unmapped.source,
+                        "unmapped_target_columns": unmapped.target,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        OutputFormat::Text | OutputFormat::Sql => {
+            for (table, target_table, unmapped) in &report {
+                for column in &unmapped.source {
+                    println!("SOURCE  {:<30} {} (no column in {})", table, column, target_table);
+                }
+                for column in &unmapped.target {
+                    println!("TARGET  {:<30} {} (no column in {})", target_table, column, table);
+                }
+            }
+            println!("{} of {} tables with unmapped columns", unmapped_tables, report.len());
+        }
+    }
+
+    Ok(if unmapped_tables == 0 { EXIT_OK } else { EXIT_DRIFT })
+}
+
+async fn copy_tables(cli: &CliArgs) -> Result<i32> {
+    let (source, source_conn, target, target_conn) = connect_pair(cli).await?;
+    let table_options = compare_options(cli, &SettingsStore::new()?, &source.name, &target.name);
+    // Parents first, so the foreign keys of copied rows find their parent rows
+    let tables = if cli.tables.is_empty() {
+        source_conn.get_tables_in_dependency_order().await?
+    } else {
+        cli.tables.clone()
+    };
+
+    // A failed table does not stop the others
+    let mut report = Vec::new();
+    for table in &tables {
+        let options = table_options.get(table).cloned().unwrap_or_default();
+        let result = db::copy_table(
+            &source_conn,
+            &target_conn,
+            table,
+            &source.config.database,
+            &options,
+            cli.batch_size,
+            |_| {},
+        )
+        .await;
+        report.push((table, result));
+    }
+    let failed = report.iter().filter(|(_, result)| result.is_err()).count();
+
+    match cli.format {
+        OutputFormat::Json => {
+            let report: Vec<serde_json::Value> = report
+                .iter()
+                .map(|(table, result)| match result {
+                    Ok(progress) => serde_json::json!({
+                        "table": table,
+                        "rows": progress.rows_copied,
+                        "seconds": progress.elapsed.as_secs_f64(),
+                        "rows_per_second": progress.rows_per_second(),
+                    }),
+                    Err(e) => serde_json::json!({ "table": table, "error": e.to_string() }),
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        OutputFormat::Text | OutputFormat::Sql => {
+            for (table, result) in &report {
+                match result {
+                    Ok(progress) => println!(
+                        "COPIED  {:<30} {} rows in {:.1}s ({:.0} rows/s)",
+                        table,
+                        progress.rows_copied,
+                        progress.elapsed.as_secs_f64(),
+                        progress.rows_per_second()
+                    ),
+                    Err(e) => eprintln!("FAILED  {:<30} {}", table, e),
+                }
+            }
+            println!("Copied {}/{} tables", report.len() - failed, report.len());
+        }
+    }
+
+    Ok(if failed == 0 { EXIT_OK } else { EXIT_ERROR })
+}
+
+async fn apply_schema(cli: &CliArgs) -> Result<i32> {
+    let (target_conn, _, diffs) = compute_schema_diffs(cli).await?;
+    let statements: Vec<String> = diffs.iter().map(|d| d.sql.clone()).collect();
+    let results = target_conn.execute_migration(&statements).await?;
+    Ok(report_results(&results))
+}
+
+async fn apply_data(cli: &CliArgs) -> Result<i32> {
+    let data = compute_data_diffs(cli).await?;
+    let batches = data.target_conn.execute_batches(&data.statements, db::DEFAULT_BATCH_SIZE).await?;
+    let code = report_results(&batches.results);
+    println!("Applied {}/{} rows", batches.rows_applied, batches.rows);
+
+    // Every difference up to the watermarks is applied
+    if code == EXIT_OK && !data.watermarks.is_empty() {
+        let mut store = WatermarkStore::new()?;
+        for watermark in data.watermarks {
+            println!("Synced {} up to {} = {}", watermark.table, watermark.column, watermark.value);
+            store.save(watermark)?;
+        }
+    }
+    Ok(code)
+}
+
+/// Print failed statements and a summary, returning the exit code
+fn report_results(results: &[db::StatementResult]) -> i32 {
+    for result in results {
+        if let db::StatementStatus::Failed(e) = &result.status {
+            eprintln!("Failed: {}\n  {}", result.sql, e);
+        }
+    }
+
+    let applied = results.iter().filter(|r| r.is_applied()).count();
+    println!("Applied {}/{} statements", applied, results.len());
+
+    if applied == results.len() { EXIT_OK } else { EXIT_ERROR }
+}
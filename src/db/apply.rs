@@ -0,0 +1,248 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::Executor;
+
+use super::{DataStatement, DbConnection, DbType, SqlServerClient};
+
+/// Default number of statements per transaction when applying data changes
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Outcome of executing a single statement
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatementStatus {
+    Applied,
+    Failed(String),
+    /// Executed successfully but undone when the transaction was rolled back
+    RolledBack,
+    /// Not executed because an earlier statement failed
+    Skipped,
+}
+
+/// Statement execution result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementResult {
+    pub sql: String,
+    pub status: StatementStatus,
+}
+
+impl StatementResult {
+    pub fn is_applied(&self) -> bool {
+        self.status == StatementStatus::Applied
+    }
+}
+
+/// Outcome of applying row changes in batches
+#[derive(Debug, Clone, Default)]
+pub struct BatchResult {
+    pub results: Vec<StatementResult>,
+    /// Rows written by all the statements
+    pub rows: usize,
+    /// Rows written by the statements that were applied
+    pub rows_applied: usize,
+}
+
+impl BatchResult {
+    pub fn is_complete(&self) -> bool {
+        self.results.iter().all(StatementResult::is_applied)
+    }
+}
+
+impl DbType {
+    /// Whether DDL statements can be rolled back inside a transaction.
+    /// MySQL implicitly commits on every DDL statement.
+    pub fn supports_transactional_ddl(&self) -> bool {
+        !matches!(self, DbType::MySQL)
+    }
+}
+
+/// Run statements on a sqlx pool.
+///
+/// A macro rather than a generic function: the higher-ranked `Executor` bounds
+/// a generic version needs make the resulting future non-`Send`.
+macro_rules! run_statements {
+    ($pool:expr, $statements:expr, $use_transaction:expr) => {{
+        let mut results: Vec<StatementResult> = Vec::with_capacity($statements.len());
+        let mut failed = false;
+
+        if $use_transaction {
+            let mut tx = $pool.begin().await?;
+            for sql in $statements {
+                let status = if failed {
+                    StatementStatus::Skipped
+                } else {
+                    match (&mut *tx).execute(sql.as_str()).await {
+                        Ok(_) => StatementStatus::Applied,
+                        Err(e) => {
+                            failed = true;
+                            StatementStatus::Failed(e.to_string())
+                        }
+                    }
+                };
+                results.push(StatementResult { sql: sql.clone(), status });
+            }
+
+            if failed {
+                tx.rollback().await?;
+                for result in results.iter_mut().filter(|r| r.is_applied()) {
+                    result.status = StatementStatus::RolledBack;
+                }
+            } else {
+                tx.commit().await?;
+            }
+        } else {
+            let mut conn = $pool.acquire().await?;
+            for sql in $statements {
+                let status = if failed {
+                    StatementStatus::Skipped
+                } else {
+                    match (&mut *conn).execute(sql.as_str()).await {
+                        Ok(_) => StatementStatus::Applied,
+                        Err(e) => {
+                            failed = true;
+                            StatementStatus::Failed(e.to_string())
+                        }
+                    }
+                };
+                results.push(StatementResult { sql: sql.clone(), status });
+            }
+        }
+
+        Ok(results)
+    }};
+}
+
+impl DbConnection {
+    /// Execute statements in order, stopping at the first failure.
+    ///
+    /// With `use_transaction` all statements run in a single transaction that
+    /// is rolled back if any of them fails.
+    pub async fn execute_statements(
+        &self,
+        statements: &[String],
+        use_transaction: bool,
+    ) -> Result<Vec<StatementResult>> {
+        match self {
+            DbConnection::MySQL(pool) => run_statements!(pool, statements, use_transaction),
+            DbConnection::PostgreSQL(pool) => run_statements!(pool, statements, use_transaction),
+            DbConnection::SQLite(pool) => run_statements!(pool, statements, use_transaction),
+            DbConnection::SQLServer(client) => {
+                let mut client = client.lock().await;
+                run_mssql_statements(&mut client, statements, use_transaction).await
+            }
+        }
+    }
+
+    /// Execute schema migration statements in order, in a transaction where
+    /// the database supports transactional DDL.
+    ///
+    /// PostgreSQL cannot use an enum label added in the same transaction, so
+    /// the statements up to the last `ALTER TYPE .. ADD VALUE` are committed
+    /// on their own first.
+    pub async fn execute_migration(&self, statements: &[String]) -> Result<Vec<StatementResult>> {
+        let use_transaction = self.db_type().supports_transactional_ddl();
+        let split = match self.db_type() {
+            DbType::PostgreSQL => statements.iter().rposition(|sql| adds_enum_label(sql)).map(|i| i + 1),
+            _ => None,
+        };
+        let Some(split) = split.filter(|&i| i < statements.len()) else {
+            return self.execute_statements(statements, use_transaction).await;
+        };
+
+        let mut results = self.execute_statements(&statements[..split], use_transaction).await?;
+        if results.iter().all(StatementResult::is_applied) {
+            results.extend(self.execute_statements(&statements[split..], use_transaction).await?);
+        } else {
+            results.extend(statements[split..].iter().map(|sql| StatementResult {
+                sql: sql.clone(),
+                status: StatementStatus::Skipped,
+            }));
+        }
+        Ok(results)
+    }
+
+    /// Execute statements in batches of `batch_size`, each in its own transaction.
+    ///
+    /// A failing batch is rolled back and the later batches are skipped, so
+    /// the target is left as of the last complete batch.
+    pub async fn execute_batches(&self, statements: &[DataStatement], batch_size: usize) -> Result<BatchResult> {
+        let mut results: Vec<StatementResult> = Vec::with_capacity(statements.len());
+        for batch in statements.chunks(batch_size.max(1)) {
+            if results.iter().all(StatementResult::is_applied) {
+                let batch: Vec<String> = batch.iter().map(|s| s.sql.clone()).collect();
+                results.extend(self.execute_statements(&batch, true).await?);
+            } else {
+                results.extend(batch.iter().map(|s| StatementResult {
+                    sql: s.sql.clone(),
+                    status: StatementStatus::Skipped,
+                }));
+            }
+        }
+        let rows_applied = statements
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_applied())
+            .map(|(statement, _)| statement.rows)
+            .sum();
+        Ok(BatchResult {
+            results,
+            rows: statements.iter().map(|s| s.rows).sum(),
+            rows_applied,
+        })
+    }
+}
+
+/// Whether a statement adds a label to a PostgreSQL enum type
+pub(super) fn adds_enum_label(sql: &str) -> bool {
+    let sql = sql.to_uppercase();
+    sql.contains("ALTER TYPE") && sql.contains(" ADD VALUE ")
+}
+
+/// Run statements on SQL Server, using explicit transaction statements
+async fn run_mssql_statements(
+    client: &mut SqlServerClient,
+    statements: &[String],
+    use_transaction: bool,
+) -> Result<Vec<StatementResult>> {
+    let mut results: Vec<StatementResult> = Vec::with_capacity(statements.len());
+    let mut failed = false;
+
+    if use_transaction {
+        client.simple_query("BEGIN TRANSACTION").await?.into_results().await?;
+    }
+
+    for sql in statements {
+        let status = if failed {
+            StatementStatus::Skipped
+        } else {
+            let outcome = match client.simple_query(sql.as_str()).await {
+                Ok(stream) => stream.into_results().await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            match outcome {
+                Ok(()) => StatementStatus::Applied,
+                Err(e) => {
+                    failed = true;
+                    StatementStatus::Failed(e.to_string())
+                }
+            }
+        };
+        results.push(StatementResult { sql: sql.clone(), status });
+    }
+
+    if use_transaction {
+        if failed {
+            client
+                .simple_query("IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION")
+                .await?
+                .into_results()
+                .await?;
+            for result in results.iter_mut().filter(|r| r.is_applied()) {
+                result.status = StatementStatus::RolledBack;
+            }
+        } else {
+            client.simple_query("COMMIT TRANSACTION").await?.into_results().await?;
+        }
+    }
+
+    Ok(results)
+}
@@ -0,0 +1,287 @@
+use anyhow::Result;
+use futures_util::TryStreamExt;
+
+use super::connection::mssql_query;
+use super::sync::{compare_keyless, merge_compare, range_filter, ChunkReader, TableComparison};
+use super::watermark::resolve_watermark_edges;
+use super::{CompareOptions, CompareProgress, DataDiffResult, DbConnection, DbType, KeyRange, KeyedTable, Value};
+
+/// Row count and aggregated row checksum of a key range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeChecksum {
+    pub count: u64,
+    pub checksum: String,
+}
+
+impl DbConnection {
+    /// Count and checksum the compared columns of the rows of `range`,
+    /// computed by the database.
+    ///
+    /// Checksums are only comparable between databases of the same type.
+    pub async fn get_range_checksum(&self, table: &KeyedTable, range: &KeyRange) -> Result<RangeChecksum> {
+        let db_type = self.db_type();
+        let cols: Vec<String> = table.compared_columns.iter().map(|c| table.quote_column(c, db_type)).collect();
+        let from = format!(
+            "{}{}",
+            db_type.quote_table_name(&table.name),
+            range_filter(table, range, db_type)
+        );
+
+        let (count, checksum) = match self {
+            DbConnection::MySQL(pool) => {
+                // Each field is 'n' for NULL, else 'v', its length and its text,
+                // so no two rows share a hash input
+                let fields: Vec<String> =
+                    cols.iter().map(|c| format!("COALESCE(CONCAT('v', LENGTH({}), ':', {}), 'n')", c, c)).collect();
+                // The two 64-bit halves of each row's MD5 are summed as DECIMAL, which cannot overflow
+                let half = |start: u32| format!("COALESCE(SUM(CAST(CONV(SUBSTRING(h, {}, 16), 16, 10) AS UNSIGNED)), 0)", start);
+                let query = format!(
+                    "SELECT COUNT(*), CAST(CONCAT({}, ':', {}) AS CHAR) FROM (SELECT MD5(CONCAT({})) AS h FROM {}) r",
+                    half(1),
+                    half(17),
+                    fields.join(", "),
+                    from
+                );
+                let row: (i64, String) = sqlx::query_as(&query).fetch_one(pool).await?;
+                (row.0, row.1)
+            }
+            DbConnection::PostgreSQL(pool) => {
+                // The row's text form quotes empty strings and leaves NULLs empty;
+                // two 60-bit pieces of its MD5 are summed as numeric
+                let half = |start: u32| format!("COALESCE(SUM(('x' || substr(h, {}, 15))::bit(60)::bigint), 0)", start);
+                let query = format!(
+                    "SELECT COUNT(*), {} || ':' || {} FROM (SELECT md5(ROW({})::text) AS h FROM {}) r",
+                    half(1),
+                    half(16),
+                    cols.join(", "),
+                    from
+                );
+                let row: (i64, String) = sqlx::query_as(&query).fetch_one(pool).await?;
+                (row.0, row.1)
+            }
+            DbConnection::SQLite(pool) => {
+                // SQLite has no hash function; the file is local, so hash the
+                // quoted row text in-process instead
+                let fields: Vec<String> = cols.iter().map(|c| format!("quote({})", c)).collect();
+                let query = format!("SELECT {} FROM {}", fields.join(" || char(31) || "), from);
+                let mut rows = sqlx::query_as::<_, (String,)>(&query).fetch(pool);
+                let mut count = 0i64;
+                let mut sum = 0u128;
+                while let Some((text,)) = rows.try_next().await? {
+                    count += 1;
+                    sum = sum.wrapping_add(fnv1a(text.as_bytes()));
+                }
+                (count, sum.to_string())
+            }
+            DbConnection::SQLServer(client) => {
+                // Each field is 0x00 for NULL, else 0x01, its length and its
+                // bytes. HASHBYTES takes more than 8000 bytes from SQL Server 2016.
+                let fields: Vec<String> = cols
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "COALESCE(0x01 + CAST(CAST(DATALENGTH({}) AS bigint) AS binary(8)) + CAST({} AS varbinary(max)), 0x00)",
+                            c, c
+                        )
+                    })
+                    .collect();
+                // The first two 64-bit pieces of each row's SHA-256 are summed as decimal(38, 0)
+                let half = |start: u32| {
+                    format!(
+                        "COALESCE(SUM(CAST(CAST(SUBSTRING(h, {}, 8) AS bigint) AS decimal(38, 0))), 0)",
+                        start
+                    )
+                };
+                let query = format!(
+                    "SELECT COUNT_BIG(*), CAST(CONCAT({}, ':', {}) AS NVARCHAR(100)) \
+                     FROM (SELECT HASHBYTES('SHA2_256', {}) AS h FROM {}) r",
+                    half(1),
+                    half(9),
+                    fields.join(" + "),
+                    from
+                );
+                let rows = mssql_query(client, &query, &[]).await?;
+                let row = rows.first();
+                (
+                    row.and_then(|r| r.get::<i64, _>(0)).unwrap_or(0),
+                    row.and_then(|r| r.get::<&str, _>(1)).unwrap_or_default().to_string(),
+                )
+            }
+        };
+
+        Ok(RangeChecksum {
+            count: count.max(0) as u64,
+            checksum,
+        })
+    }
+
+    /// Key of the row at `offset` within `range`, in key order
+    pub async fn get_key_at(&self, table: &KeyedTable, range: &KeyRange, offset: u64) -> Result<Option<Vec<Value>>> {
+        let db_type = self.db_type();
+        let keys: Vec<String> = table.key_columns.iter().map(|c| table.quote_column(c, db_type)).collect();
+        let order = table.key_exprs(db_type).join(", ");
+        let from = format!(
+            "{}{}",
+            db_type.quote_table_name(&table.name),
+            range_filter(table, range, db_type)
+        );
+
+        let query = match db_type {
+            DbType::SQLServer => format!(
+                "SELECT {} FROM {} ORDER BY {} OFFSET {} ROWS FETCH NEXT 1 ROWS ONLY",
+                keys.join(", "),
+                from,
+                order,
+                offset
+            ),
+            _ => format!("SELECT {} FROM {} ORDER BY {} LIMIT 1 OFFSET {}", keys.join(", "), from, order, offset),
+        };
+
+        let rows = self.fetch_rows(&query, &table.key_columns).await?;
+        Ok(rows.first().map(|row| table.key_of(row)))
+    }
+}
+
+/// 128-bit FNV-1a hash
+fn fnv1a(bytes: &[u8]) -> u128 {
+    bytes.iter().fold(0x6c62272e07bb014262b821756295c58d, |hash, b| {
+        (hash ^ *b as u128).wrapping_mul(0x0000000001000000000000000000013b)
+    })
+}
+
+/// Compare data between two databases of the same type by checksumming key
+/// ranges on the server. Ranges whose count and checksum match are skipped;
+/// others are split at the middle key until they hold at most `chunk_size`
+/// rows, and only those are fetched and compared row by row.
+pub async fn compare_table_checksums(
+    source: &DbConnection,
+    target: &DbConnection,
+    table_name: &str,
+    database: &str,
+    options: &CompareOptions,
+    chunk_size: usize,
+    on_progress: impl FnMut(&CompareProgress) + Send,
+) -> Result<Vec<DataDiffResult>> {
+    let comparison =
+        compare_table_pair_checksums(source, target, table_name, database, options, chunk_size, on_progress).await?;
+    Ok(comparison.diffs)
+}
+
+/// Compare data like `compare_table_checksums`, also returning the loaded
+/// tables and their row counts
+pub async fn compare_table_pair_checksums(
+    source: &DbConnection,
+    target: &DbConnection,
+    table_name: &str,
+    database: &str,
+    options: &CompareOptions,
+    chunk_size: usize,
+    mut on_progress: impl FnMut(&CompareProgress) + Send,
+) -> Result<TableComparison> {
+    if source.db_type() != target.db_type() {
+        return Err(anyhow::anyhow!(
+            "Checksum comparison needs source and target of the same database type"
+        ));
+    }
+
+    let (source_table, target_table) = KeyedTable::load_pair(source, target, table_name, database, options).await?;
+    let chunk_size = chunk_size.max(1) as u64;
+    let source_count = source.count_rows(&source_table).await?;
+    let target_count = target.count_rows(&target_table).await?;
+
+    let mut progress = CompareProgress {
+        table_name: table_name.to_string(),
+        total_rows: source_count + target_count,
+        ..Default::default()
+    };
+    on_progress(&progress);
+
+    // Key ranges need a key; keyless tables are compared in full
+    if !source_table.has_key() {
+        let diffs = compare_keyless(source, target, &source_table, &target_table, progress, on_progress).await?;
+        return Ok(TableComparison {
+            source_table,
+            target_table,
+            source_count,
+            target_count,
+            diffs,
+        });
+    }
+
+    let mut results = Vec::new();
+    // Depth-first, lower half on top, so differences come out in key order
+    let mut ranges = vec![KeyRange::default()];
+
+    while let Some(range) = ranges.pop() {
+        let source_sum = source.get_range_checksum(&source_table, &range).await?;
+        let target_sum = target.get_range_checksum(&target_table, &range).await?;
+
+        if source_sum == target_sum {
+            progress.rows_read += source_sum.count + target_sum.count;
+            on_progress(&progress);
+            continue;
+        }
+
+        let split = if source_sum.count.max(target_sum.count) > chunk_size {
+            // Split at the middle key of the larger side
+            let (conn, table, count) = if source_sum.count >= target_sum.count {
+                (source, &source_table, source_sum.count)
+            } else {
+                (target, &target_table, target_sum.count)
+            };
+            conn.get_key_at(table, &range, count / 2 - 1).await?
+        } else {
+            None
+        };
+
+        match split {
+            Some(middle) => {
+                ranges.push(KeyRange {
+                    after: Some(middle.clone()),
+                    until: range.until.clone(),
+                });
+                ranges.push(KeyRange {
+                    after: range.after,
+                    until: Some(middle),
+                });
+            }
+            None => {
+                merge_compare(
+                    ChunkReader::new(source, &source_table, range.clone(), chunk_size as usize),
+                    ChunkReader::new(target, &target_table, range, chunk_size as usize),
+                    target.db_type(),
+                    &mut results,
+                    &mut progress,
+                    &mut on_progress,
+                )
+                .await?;
+            }
+        }
+    }
+
+    if options.is_incremental() {
+        results = resolve_watermark_edges(source, target, &source_table, &target_table, results).await?;
+    }
+
+    progress.differences = results.len();
+    on_progress(&progress);
+
+    Ok(TableComparison {
+        source_table,
+        target_table,
+        source_count,
+        target_count,
+        diffs: results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(b""), 0x6c62272e07bb014262b821756295c58d);
+        assert_eq!(fnv1a(b"a"), 0xd228cb696f1a8caf78912b704e4a8964);
+    }
+}
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 
 /// Database type enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -29,6 +32,17 @@ impl DbType {
         let (open, close) = self.quote_char();
         format!("{}{}{}", open, name, close)
     }
+
+    /// Quote a table name. On PostgreSQL `schema.table` names are quoted
+    /// part by part, as tables outside the first schema are named that way.
+    pub fn quote_table_name(&self, name: &str) -> String {
+        match (self, name.split_once('.')) {
+            (DbType::PostgreSQL, Some((schema, table))) => {
+                format!("{}.{}", self.quote_identifier(schema), self.quote_identifier(table))
+            }
+            _ => self.quote_identifier(name),
+        }
+    }
 }
 
 /// Database connection configuration
@@ -43,6 +57,10 @@ pub struct ConnectionConfig {
     /// SQLite file path
     #[serde(default)]
     pub file_path: Option<PathBuf>,
+    /// PostgreSQL schemas to read tables from, in search order; empty means
+    /// `public`. Tables outside the first one are named `schema.table`.
+    #[serde(default)]
+    pub schemas: Vec<String>,
 }
 
 impl Default for ConnectionConfig {
@@ -55,6 +73,7 @@ impl Default for ConnectionConfig {
             password: String::new(),
             database: String::new(),
             file_path: None,
+            schemas: Vec::new(),
         }
     }
 }
@@ -69,14 +88,27 @@ impl ConnectionConfig {
             DbType::SQLServer => 1433,
         }
     }
+
+    /// PostgreSQL `search_path` covering the configured schemas
+    pub fn search_path(&self) -> String {
+        if self.schemas.is_empty() {
+            return DbType::PostgreSQL.quote_identifier("public");
+        }
+        let schemas: Vec<String> = self.schemas.iter().map(|s| DbType::PostgreSQL.quote_identifier(s)).collect();
+        schemas.join(",")
+    }
 }
 
+/// SQL Server client over a tokio TCP stream
+pub type SqlServerClient = tiberius::Client<tokio_util::compat::Compat<tokio::net::TcpStream>>;
+
 /// Database connection wrapper that can hold different connection types
 pub enum DbConnection {
     MySQL(sqlx::MySqlPool),
     PostgreSQL(sqlx::PgPool),
     SQLite(sqlx::SqlitePool),
-    SQLServer(tiberius::Client<tokio_util::compat::Compat<tokio::net::TcpStream>>),
+    /// The tiberius client needs `&mut` for every query, so it is shared behind a mutex
+    SQLServer(Arc<Mutex<SqlServerClient>>),
 }
 
 impl DbConnection {
@@ -96,7 +128,10 @@ impl DbConnection {
                     "postgres://{}:{}@{}:{}/{}",
                     config.user, config.password, config.host, config.port, config.database
                 );
-                let pool = sqlx::PgPool::connect(&url).await?;
+                // Unqualified names resolve through the search path, so set it on every connection
+                let options = sqlx::postgres::PgConnectOptions::from_str(&url)?
+                    .options([("search_path", config.search_path())]);
+                let pool = sqlx::PgPool::connect_with(options).await?;
                 Ok(DbConnection::PostgreSQL(pool))
             }
             DbType::SQLite => {
@@ -125,7 +160,7 @@ impl DbConnection {
                 let tcp = TcpStream::connect(config_builder.get_addr()).await?;
                 tcp.set_nodelay(true)?;
                 let client = tiberius::Client::connect(config_builder, tcp.compat_write()).await?;
-                Ok(DbConnection::SQLServer(client))
+                Ok(DbConnection::SQLServer(Arc::new(Mutex::new(client))))
             }
         }
     }
@@ -153,15 +188,25 @@ impl DbConnection {
                 sqlx::query("SELECT 1").execute(pool).await?;
             }
             DbConnection::SQLServer(client) => {
-                // SQL Server client is mutable, we can't test without mut ref
-                // Connection success is verified during connect
-                let _ = client;
+                mssql_query(client, "SELECT 1", &[]).await?;
             }
         }
         Ok(())
     }
 }
 
+/// Run a query on SQL Server and return the rows of the first result set
+pub(super) async fn mssql_query(
+    client: &Mutex<SqlServerClient>,
+    sql: &str,
+    params: &[&dyn tiberius::ToSql],
+) -> Result<Vec<tiberius::Row>> {
+    let mut client = client.lock().await;
+    let rows = client.query(sql, params).await?.into_first_result().await?;
+    Ok(rows)
+}
+
+
 /// Saved connection with name
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SavedConnection {
@@ -169,6 +214,16 @@ pub struct SavedConnection {
     pub config: ConnectionConfig,
 }
 
+/// Directory holding the saved configuration, created if missing
+pub(super) fn config_dir() -> Result<PathBuf> {
+    let config_dir = dirs::home_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
+        .join(".syncforge");
+
+    std::fs::create_dir_all(&config_dir)?;
+    Ok(config_dir)
+}
+
 /// Connection store for managing saved connections
 pub struct ConnectionStore {
     connections: Vec<SavedConnection>,
@@ -178,13 +233,7 @@ pub struct ConnectionStore {
 impl ConnectionStore {
     /// Create new connection store
     pub fn new() -> Result<Self> {
-        let config_dir = dirs::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
-            .join(".syncforge");
-
-        std::fs::create_dir_all(&config_dir)?;
-
-        let file_path = config_dir.join("connections.json");
+        let file_path = config_dir()?.join("connections.json");
         let connections = if file_path.exists() {
             let content = std::fs::read_to_string(&file_path)?;
             serde_json::from_str(&content).unwrap_or_default()
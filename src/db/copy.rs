@@ -0,0 +1,287 @@
+use anyhow::Result;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgConnection, PgCopyIn};
+use sqlx::query::Query;
+use sqlx::sqlite::SqliteArguments;
+use sqlx::{Postgres, Sqlite, Transaction};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::statements::{multi_row_insert_sql, MSSQL_MAX_ROWS};
+use super::sync::range_filter;
+use super::{CompareOptions, DbConnection, DbType, KeyRange, KeyedTable, StatementStatus, Value};
+
+/// Default number of rows read and written per batch when copying a table
+pub const DEFAULT_COPY_BATCH_SIZE: usize = 1000;
+
+/// Progress of a table copy
+#[derive(Debug, Clone, Default)]
+pub struct CopyProgress {
+    pub table_name: String,
+    pub rows_copied: u64,
+    /// Rows to copy, counted before the copy starts
+    pub total_rows: u64,
+    pub elapsed: Duration,
+}
+
+impl CopyProgress {
+    /// Fraction of rows copied, between 0.0 and 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.total_rows == 0 {
+            1.0
+        } else {
+            (self.rows_copied as f64 / self.total_rows as f64).min(1.0)
+        }
+    }
+
+    /// Average copy rate so far
+    pub fn rows_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.rows_copied as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Writes copied rows to the target table the fastest way its database offers
+enum BatchWriter<'c> {
+    /// A single `COPY ... FROM STDIN` in text format
+    Postgres(PgCopyIn<&'c mut PgConnection>),
+    /// One prepared INSERT per row, all in a single transaction
+    SQLite(Transaction<'static, Sqlite>, String),
+    /// Multi-row INSERTs, a transaction per batch
+    Statements,
+}
+
+impl<'c> BatchWriter<'c> {
+    /// Start writing to `table`. PostgreSQL targets copy over `pg_conn`.
+    async fn begin(
+        conn: &DbConnection,
+        table: &KeyedTable,
+        pg_conn: Option<&'c mut PoolConnection<Postgres>>,
+    ) -> Result<Self> {
+        let db_type = conn.db_type();
+        let name = db_type.quote_table_name(&table.name);
+        let cols: Vec<String> = table.columns.iter().map(|c| table.quote_column(c, db_type)).collect();
+
+        Ok(match (conn, pg_conn) {
+            (DbConnection::PostgreSQL(_), Some(pg_conn)) => {
+                let copy = pg_conn
+                    .copy_in_raw(&format!("COPY {} ({}) FROM STDIN", name, cols.join(", ")))
+                    .await?;
+                BatchWriter::Postgres(copy)
+            }
+            (DbConnection::SQLite(pool), _) => {
+                let placeholders = vec!["?"; cols.len()];
+                let sql = format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    name,
+                    cols.join(", "),
+                    placeholders.join(", ")
+                );
+                BatchWriter::SQLite(pool.begin().await?, sql)
+            }
+            _ => BatchWriter::Statements,
+        })
+    }
+
+    async fn write(&mut self, conn: &DbConnection, table: &KeyedTable, rows: &[HashMap<String, Value>]) -> Result<()> {
+        let value_of = |row: &'_ HashMap<String, Value>, column: &str| row.get(column).cloned().unwrap_or(Value::Null);
+
+        match self {
+            BatchWriter::Postgres(copy) => {
+                let mut data = String::new();
+                for row in rows {
+                    let fields: Vec<String> = table.columns.iter().map(|c| value_of(row, c).to_copy_text()).collect();
+                    data.push_str(&fields.join("\t"));
+                    data.push('\n');
+                }
+                copy.send(data.into_bytes()).await?;
+            }
+            BatchWriter::SQLite(tx, sql) => {
+                // The statement is prepared once and cached by the connection
+                for row in rows {
+                    let mut query = sqlx::query(sql.as_str());
+                    for column in &table.columns {
+                        query = bind_sqlite(query, value_of(row, column));
+                    }
+                    query.execute(&mut **tx).await?;
+                }
+            }
+            BatchWriter::Statements => {
+                let db_type = conn.db_type();
+                let rows: Vec<&HashMap<String, Value>> = rows.iter().collect();
+                let chunk_size = if db_type == DbType::SQLServer { MSSQL_MAX_ROWS } else { rows.len().max(1) };
+                let statements: Vec<String> = rows
+                    .chunks(chunk_size)
+                    .map(|chunk| multi_row_insert_sql(db_type, table, chunk))
+                    .collect();
+                for result in conn.execute_statements(&statements, true).await? {
+                    if let StatementStatus::Failed(e) = result.status {
+                        return Err(anyhow::anyhow!(e));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn finish(self) -> Result<()> {
+        match self {
+            BatchWriter::Postgres(copy) => {
+                copy.finish().await?;
+            }
+            BatchWriter::SQLite(tx, _) => tx.commit().await?,
+            BatchWriter::Statements => {}
+        }
+        Ok(())
+    }
+
+    /// Undo what can be undone; batches already written as statements stay
+    async fn abort(self, error: &anyhow::Error) {
+        match self {
+            BatchWriter::Postgres(copy) => {
+                let _ = copy.abort(error.to_string()).await;
+            }
+            BatchWriter::SQLite(tx, _) => {
+                let _ = tx.rollback().await;
+            }
+            BatchWriter::Statements => {}
+        }
+    }
+}
+
+fn bind_sqlite<'q>(query: Query<'q, Sqlite, SqliteArguments<'q>>, value: Value) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(v) => query.bind(v),
+        Value::Int(v) => query.bind(v),
+        Value::UInt(v) => match i64::try_from(v) {
+            Ok(v) => query.bind(v),
+            Err(_) => query.bind(v.to_string()),
+        },
+        Value::Float(v) => query.bind(v),
+        Value::Bytes(v) => query.bind(v),
+        other => query.bind(other.to_string()),
+    }
+}
+
+/// Move the sequence of a PostgreSQL serial or identity column in `table`
+/// to the largest value in the column, so the next generated id is free
+fn advance_sequence_sql(table: &KeyedTable, db_type: DbType) -> Option<String> {
+    let column = table.identity_column.as_ref().filter(|_| db_type == DbType::PostgreSQL)?;
+    let column_name = table.column_names.get(column).unwrap_or(column);
+    let quoted_table = db_type.quote_table_name(&table.name);
+    let quoted_column = table.quote_column(column, db_type);
+    Some(format!(
+        "SELECT setval(pg_get_serial_sequence('{}', '{}'), max({})) FROM {} HAVING max({}) IS NOT NULL;",
+        quoted_table.replace('\'', "''"),
+        column_name.replace('\'', "''"),
+        quoted_column,
+        quoted_table,
+        quoted_column
+    ))
+}
+
+/// Copy every row of source table `table_name` matching its filter into its
+/// empty counterpart in the target, `batch_size` rows at a time.
+/// `on_progress` is called after every batch.
+///
+/// PostgreSQL targets are written with `COPY FROM STDIN` and SQLite targets
+/// in a single transaction, so a failed copy leaves them empty. Other
+/// targets keep the batches written before the failure.
+pub async fn copy_table(
+    source: &DbConnection,
+    target: &DbConnection,
+    table_name: &str,
+    database: &str,
+    options: &CompareOptions,
+    batch_size: usize,
+    mut on_progress: impl FnMut(&CopyProgress) + Send,
+) -> Result<CopyProgress> {
+    // An empty target has nothing synced yet, whatever the watermark says
+    let options = CompareOptions { watermark: None, ..options.clone() };
+    let (source_table, target_table) = KeyedTable::load_pair(source, target, table_name, database, &options).await?;
+    if target.get_row_count(&target_table.name).await? > 0 {
+        return Err(anyhow::anyhow!(
+            "Target table {} is not empty; only empty tables can be copied into",
+            target_table.name
+        ));
+    }
+
+    let started = Instant::now();
+    let mut progress = CopyProgress {
+        table_name: table_name.to_string(),
+        total_rows: source.count_rows(&source_table).await?,
+        ..Default::default()
+    };
+    on_progress(&progress);
+
+    let batch_size = batch_size.max(1);
+    // COPY holds a connection of its own until it finishes
+    let mut pg_conn = match target {
+        DbConnection::PostgreSQL(pool) => Some(pool.acquire().await?),
+        _ => None,
+    };
+    let mut writer = BatchWriter::begin(target, &target_table, pg_conn.as_mut()).await?;
+    let result: Result<()> = async {
+        if source_table.has_key() {
+            let mut range = KeyRange::default();
+            loop {
+                let rows = source.get_table_chunk(&source_table, &range, batch_size).await?;
+                if let Some(last) = rows.last() {
+                    range.after = Some(source_table.key_of(last));
+                }
+                writer.write(target, &target_table, &rows).await?;
+                progress.rows_copied += rows.len() as u64;
+                progress.elapsed = started.elapsed();
+                on_progress(&progress);
+                if rows.len() < batch_size {
+                    break;
+                }
+            }
+        } else {
+            // Without a key there is no order to page by, so the rows are read at once
+            let db_type = source.db_type();
+            let cols: Vec<String> = source_table.columns.iter().map(|c| source_table.quote_column(c, db_type)).collect();
+            let query = format!(
+                "SELECT {} FROM {}{}",
+                cols.join(", "),
+                db_type.quote_table_name(&source_table.name),
+                range_filter(&source_table, &KeyRange::default(), db_type)
+            );
+            let rows = source.fetch_rows(&query, &source_table.columns).await?;
+            for batch in rows.chunks(batch_size) {
+                writer.write(target, &target_table, batch).await?;
+                progress.rows_copied += batch.len() as u64;
+                progress.elapsed = started.elapsed();
+                on_progress(&progress);
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => writer.finish().await?,
+        Err(e) => {
+            writer.abort(&e).await;
+            return Err(e);
+        }
+    }
+
+    // Copied ids do not advance the sequence behind a serial or identity column
+    if let Some(sql) = advance_sequence_sql(&target_table, target.db_type()) {
+        for result in target.execute_statements(&[sql], false).await? {
+            if let StatementStatus::Failed(e) = result.status {
+                return Err(anyhow::anyhow!("Rows copied, but advancing the sequence failed: {}", e));
+            }
+        }
+    }
+
+    progress.elapsed = started.elapsed();
+    on_progress(&progress);
+    Ok(progress)
+}
@@ -0,0 +1,796 @@
+use super::{ColumnInfo, ConstraintInfo, DbType, ForeignKeyInfo, IndexInfo, RoutineInfo, TableInfo, TriggerInfo};
+
+/// Column type split into its parts, e.g. `int(11) unsigned` -> ("int", "11", "unsigned")
+struct ParsedType {
+    base: String,
+    args: Option<String>,
+    suffix: String,
+}
+
+fn parse_type(data_type: &str) -> ParsedType {
+    let lower = data_type.trim().to_lowercase();
+    match (lower.find('('), lower.rfind(')')) {
+        (Some(open), Some(close)) if close > open => ParsedType {
+            base: lower[..open].trim().to_string(),
+            args: Some(lower[open + 1..close].trim().to_string()),
+            suffix: lower[close + 1..].trim().to_string(),
+        },
+        _ => {
+            // MySQL 8 reports integer types without a display width, e.g. `int unsigned`
+            let (base, suffix) = match lower.find(" unsigned").or_else(|| lower.find(" zerofill")) {
+                Some(at) => (lower[..at].to_string(), lower[at + 1..].to_string()),
+                None => (lower, String::new()),
+            };
+            ParsedType {
+                base,
+                args: None,
+                suffix,
+            }
+        }
+    }
+}
+
+/// Map a column type from one dialect to another.
+/// Types of the same dialect and unknown types are returned unchanged.
+pub fn map_type(data_type: &str, from: DbType, to: DbType) -> String {
+    if from == to {
+        return data_type.to_string();
+    }
+
+    let parsed = parse_type(data_type);
+    let args = parsed.args.as_deref();
+    let with_args = |name: &str| match args {
+        Some(a) => format!("{}({})", name, a),
+        None => name.to_string(),
+    };
+    // Length argument, ignoring SQL Server's "max"
+    let length = args.filter(|a| *a != "max");
+    // MySQL unsigned integers need the next wider type elsewhere
+    let unsigned = from == DbType::MySQL && parsed.suffix.contains("unsigned");
+
+    match parsed.base.as_str() {
+        // MySQL's conventional boolean
+        "tinyint" if from == DbType::MySQL && args == Some("1") => boolean_type(to),
+        "bool" | "boolean" | "bit" if args.is_none() || args == Some("1") => boolean_type(to),
+        "tinyint" => match to {
+            DbType::MySQL => "tinyint".to_string(),
+            // SQL Server's tinyint is unsigned, 0 to 255
+            DbType::SQLServer if from != DbType::MySQL || unsigned => "tinyint".to_string(),
+            DbType::PostgreSQL | DbType::SQLServer => "smallint".to_string(),
+            DbType::SQLite => "INTEGER".to_string(),
+        },
+        "smallint" | "int2" | "year" => match to {
+            DbType::SQLite => "INTEGER".to_string(),
+            DbType::PostgreSQL if unsigned => "integer".to_string(),
+            DbType::SQLServer if unsigned => "int".to_string(),
+            _ => "smallint".to_string(),
+        },
+        "int" | "integer" if unsigned => match to {
+            DbType::SQLite => "INTEGER".to_string(),
+            _ => "bigint".to_string(),
+        },
+        "mediumint" | "int" | "integer" | "int4" | "serial" | "serial4" => match to {
+            DbType::MySQL => if parsed.suffix.contains("unsigned") { "int unsigned" } else { "int" }.to_string(),
+            DbType::PostgreSQL => "integer".to_string(),
+            DbType::SQLite => "INTEGER".to_string(),
+            DbType::SQLServer => "int".to_string(),
+        },
+        "bigint" if unsigned => match to {
+            DbType::PostgreSQL => "numeric(20,0)".to_string(),
+            DbType::SQLite => "NUMERIC".to_string(),
+            _ => "decimal(20,0)".to_string(),
+        },
+        "bigint" | "int8" | "bigserial" | "serial8" => match to {
+            DbType::SQLite => "INTEGER".to_string(),
+            DbType::MySQL if parsed.suffix.contains("unsigned") => "bigint unsigned".to_string(),
+            _ => "bigint".to_string(),
+        },
+        "decimal" | "numeric" | "dec" => match to {
+            DbType::SQLite => "NUMERIC".to_string(),
+            _ => with_args(if to == DbType::PostgreSQL { "numeric" } else { "decimal" }),
+        },
+        "money" | "smallmoney" => match to {
+            DbType::SQLServer => parsed.base.clone(),
+            DbType::SQLite => "NUMERIC".to_string(),
+            _ => "decimal(19,4)".to_string(),
+        },
+        "real" | "float4" => match to {
+            DbType::MySQL => "float".to_string(),
+            DbType::SQLite => "REAL".to_string(),
+            _ => "real".to_string(),
+        },
+        "float" | "double" | "double precision" | "float8" => match to {
+            DbType::MySQL => "double".to_string(),
+            DbType::PostgreSQL => "double precision".to_string(),
+            DbType::SQLite => "REAL".to_string(),
+            DbType::SQLServer => "float".to_string(),
+        },
+        "char" | "character" | "nchar" | "bpchar" => match (to, length) {
+            (DbType::SQLite, _) => "TEXT".to_string(),
+            (DbType::SQLServer, Some(n)) => format!("nchar({})", n),
+            (_, Some(n)) => format!("char({})", n),
+            (_, None) => "char(1)".to_string(),
+        },
+        "varchar" | "character varying" | "nvarchar" | "varchar2" => match (to, length) {
+            (DbType::SQLite, _) => "TEXT".to_string(),
+            (DbType::SQLServer, Some(n)) => format!("nvarchar({})", n),
+            (DbType::SQLServer, None) => "nvarchar(max)".to_string(),
+            (DbType::PostgreSQL, Some(n)) => format!("varchar({})", n),
+            (DbType::PostgreSQL, None) => "text".to_string(),
+            (DbType::MySQL, Some(n)) => format!("varchar({})", n),
+            (DbType::MySQL, None) => "longtext".to_string(),
+        },
+        "text" | "tinytext" | "mediumtext" | "longtext" | "ntext" | "clob" | "xml" => text_type(to),
+        "blob" | "tinyblob" | "mediumblob" | "longblob" | "bytea" | "binary" | "varbinary" | "image" => {
+            match to {
+                DbType::MySQL => "longblob".to_string(),
+                DbType::PostgreSQL => "bytea".to_string(),
+                DbType::SQLite => "BLOB".to_string(),
+                DbType::SQLServer => "varbinary(max)".to_string(),
+            }
+        }
+        "date" => match to {
+            DbType::SQLite => "TEXT".to_string(),
+            _ => "date".to_string(),
+        },
+        "time" | "time without time zone" => match to {
+            DbType::SQLite => "TEXT".to_string(),
+            _ => "time".to_string(),
+        },
+        "datetime" | "datetime2" | "smalldatetime" | "timestamp" | "timestamp without time zone" => match to {
+            DbType::MySQL => "datetime".to_string(),
+            DbType::PostgreSQL => "timestamp".to_string(),
+            DbType::SQLite => "TEXT".to_string(),
+            DbType::SQLServer => "datetime2".to_string(),
+        },
+        "timestamptz" | "timestamp with time zone" | "datetimeoffset" => match to {
+            DbType::MySQL => "timestamp".to_string(),
+            DbType::PostgreSQL => "timestamptz".to_string(),
+            DbType::SQLite => "TEXT".to_string(),
+            DbType::SQLServer => "datetimeoffset".to_string(),
+        },
+        "json" | "jsonb" => match to {
+            DbType::MySQL => "json".to_string(),
+            DbType::PostgreSQL => "jsonb".to_string(),
+            DbType::SQLite => "TEXT".to_string(),
+            DbType::SQLServer => "nvarchar(max)".to_string(),
+        },
+        "uuid" | "uniqueidentifier" => match to {
+            DbType::MySQL => "char(36)".to_string(),
+            DbType::PostgreSQL => "uuid".to_string(),
+            DbType::SQLite => "TEXT".to_string(),
+            DbType::SQLServer => "uniqueidentifier".to_string(),
+        },
+        "enum" | "set" => match to {
+            DbType::SQLite => "TEXT".to_string(),
+            DbType::SQLServer => "nvarchar(255)".to_string(),
+            _ => "varchar(255)".to_string(),
+        },
+        _ => data_type.to_string(),
+    }
+}
+
+/// Canonical spelling of a PostgreSQL type, so the names `format_type` reports
+/// (`character varying(255)`, `timestamp(3) without time zone`) compare equal
+/// to the short names `map_type` produces (`varchar(255)`, `timestamp(3)`)
+pub fn normalize_pg_type(data_type: &str) -> String {
+    let lower = data_type.trim().to_lowercase();
+    let (base, with_tz) = if let Some(base) = lower.strip_suffix(" without time zone") {
+        (base, false)
+    } else if let Some(base) = lower.strip_suffix(" with time zone") {
+        (base, true)
+    } else {
+        (lower.as_str(), false)
+    };
+    let (name, args) = match base.find('(') {
+        Some(open) => (base[..open].trim(), base[open..].replace(' ', "")),
+        None => (base, String::new()),
+    };
+    let name = match name {
+        "character varying" => "varchar",
+        "character" | "bpchar" => "char",
+        "timestamp" if with_tz => "timestamptz",
+        "time" if with_tz => "timetz",
+        "int" | "int4" => "integer",
+        "int2" => "smallint",
+        "int8" => "bigint",
+        "bool" => "boolean",
+        "float4" => "real",
+        "float8" => "double precision",
+        "decimal" => "numeric",
+        other => other,
+    };
+    format!("{}{}", name, args)
+}
+
+/// Whether two type names of the `db_type` dialect name the same type
+pub fn types_equivalent(a: &str, b: &str, db_type: DbType) -> bool {
+    match db_type {
+        DbType::PostgreSQL => normalize_pg_type(a) == normalize_pg_type(b),
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+fn boolean_type(to: DbType) -> String {
+    match to {
+        DbType::MySQL => "tinyint(1)",
+        DbType::PostgreSQL => "boolean",
+        DbType::SQLite => "INTEGER",
+        DbType::SQLServer => "bit",
+    }
+    .to_string()
+}
+
+fn text_type(to: DbType) -> String {
+    match to {
+        DbType::MySQL => "longtext",
+        DbType::PostgreSQL => "text",
+        DbType::SQLite => "TEXT",
+        DbType::SQLServer => "nvarchar(max)",
+    }
+    .to_string()
+}
+
+/// Check if a column is auto-incrementing in its own dialect
+pub fn is_auto_increment(col: &ColumnInfo, db_type: DbType) -> bool {
+    match db_type {
+        DbType::MySQL => col.extra.to_lowercase().contains("auto_increment"),
+        DbType::PostgreSQL => {
+            col.default.as_deref().map(|d| d.starts_with("nextval(")).unwrap_or(false)
+                || col.extra.to_lowercase().contains("identity")
+        }
+        DbType::SQLite => col.is_primary_key && col.data_type.eq_ignore_ascii_case("integer"),
+        DbType::SQLServer => col.extra.to_lowercase().contains("identity"),
+    }
+}
+
+/// Render a column default as a SQL expression for the target dialect.
+///
+/// MySQL reports string defaults unquoted, the other engines report SQL
+/// expressions (PostgreSQL with `::type` casts, SQL Server wrapped in parentheses).
+pub fn render_default(default: &str, from: DbType, to: DbType) -> String {
+    let mut expr = match from {
+        DbType::MySQL => {
+            if is_numeric_or_special(default) {
+                default.to_string()
+            } else {
+                format!("'{}'", default.replace('\'', "''"))
+            }
+        }
+        DbType::SQLServer => strip_outer_parens(default).to_string(),
+        _ => default.to_string(),
+    };
+
+    if from == to {
+        return expr;
+    }
+
+    if from == DbType::PostgreSQL {
+        expr = strip_pg_cast(&expr).to_string();
+    }
+
+    let upper = expr.to_uppercase();
+    match upper.as_str() {
+        "NOW()" | "CURRENT_TIMESTAMP()" | "GETDATE()" | "SYSDATETIME()" | "LOCALTIMESTAMP"
+        | "TRANSACTION_TIMESTAMP()" => "CURRENT_TIMESTAMP".to_string(),
+        "TRUE" | "FALSE" if matches!(to, DbType::SQLServer | DbType::SQLite) => {
+            if upper == "TRUE" { "1" } else { "0" }.to_string()
+        }
+        _ => expr,
+    }
+}
+
+/// Remove redundant outer parentheses, e.g. SQL Server's `((0))`
+pub fn strip_outer_parens(expr: &str) -> &str {
+    let mut s = expr.trim();
+    while s.starts_with('(') && s.ends_with(')') && parens_enclose(s) {
+        s = s[1..s.len() - 1].trim();
+    }
+    s
+}
+
+/// Check that the first '(' of `s` closes at its last character
+fn parens_enclose(s: &str) -> bool {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i == s.len() - 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Remove a trailing PostgreSQL cast, e.g. `'abc'::character varying`
+fn strip_pg_cast(expr: &str) -> &str {
+    if let Some(rest) = expr.strip_prefix('\'') {
+        if let Some(end) = rest.rfind('\'') {
+            let literal_end = end + 2;
+            if expr[literal_end..].starts_with("::") {
+                return &expr[..literal_end];
+            }
+        }
+    }
+    expr
+}
+
+/// Check if value is numeric or special (doesn't need quotes)
+pub fn is_numeric_or_special(val: &str) -> bool {
+    let upper = val.to_uppercase();
+    let specials = ["NULL", "CURRENT_TIMESTAMP", "CURRENT_DATE", "CURRENT_TIME", "NOW()", "TRUE", "FALSE"];
+
+    if specials.contains(&upper.as_str()) {
+        return true;
+    }
+    if upper.ends_with("()") || upper.starts_with('(') {
+        return true;
+    }
+    // Check if numeric
+    val.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '-')
+}
+
+/// Build a column definition (everything after the column name) in the target dialect
+pub fn column_definition(col: &ColumnInfo, from: DbType, to: DbType) -> String {
+    let auto_increment = is_auto_increment(col, from);
+    let mut def = map_type(&col.data_type, from, to);
+
+    if auto_increment && from != to {
+        match to {
+            DbType::MySQL => def.push_str(" NOT NULL AUTO_INCREMENT"),
+            DbType::PostgreSQL => def.push_str(" GENERATED BY DEFAULT AS IDENTITY"),
+            DbType::SQLServer => def.push_str(" IDENTITY(1,1) NOT NULL"),
+            // SQLite only auto-increments an INTEGER PRIMARY KEY, see `build_create_table`
+            DbType::SQLite => def.push_str(" NOT NULL"),
+        }
+        return def;
+    }
+
+    if !col.nullable {
+        def.push_str(" NOT NULL");
+    }
+    if let Some(default) = column_default(col, from, to) {
+        def.push_str(&format!(" DEFAULT {}", default));
+    }
+    if from == to {
+        let extra = col.extra.replace("DEFAULT_GENERATED", "");
+        let extra = extra.trim();
+        if !extra.is_empty() {
+            def.push(' ');
+            def.push_str(extra);
+        }
+    }
+    def
+}
+
+/// Column default rendered for the target dialect, adapting 0/1 for PostgreSQL booleans
+pub fn column_default(col: &ColumnInfo, from: DbType, to: DbType) -> Option<String> {
+    let default = render_default(col.default.as_deref()?, from, to);
+    if from != to && to == DbType::PostgreSQL && map_type(&col.data_type, from, to) == "boolean" {
+        let literal = default.trim_matches('\'');
+        if literal == "0" {
+            return Some("false".to_string());
+        } else if literal == "1" {
+            return Some("true".to_string());
+        }
+    }
+    Some(default)
+}
+
+/// Check if a source column matches a target column once mapped to the target dialect
+pub fn columns_equivalent(source: &ColumnInfo, from: DbType, target: &ColumnInfo, to: DbType) -> bool {
+    let source_default = column_default(source, from, to);
+    let target_default = column_default(target, to, to);
+    let extra_equal = if from == to {
+        source.extra == target.extra
+    } else {
+        is_auto_increment(source, from) == is_auto_increment(target, to)
+    };
+    // Auto-increment defaults (e.g. PostgreSQL's nextval) are covered by the extra check
+    let defaults_equal = source_default == target_default
+        || (from != to && is_auto_increment(source, from) && is_auto_increment(target, to));
+
+    types_equivalent(&map_type(&source.data_type, from, to), &target.data_type, to)
+        && source.nullable == target.nullable
+        && defaults_equal
+        && extra_equal
+}
+
+/// Build a CREATE TABLE statement for `table` in the target dialect
+pub fn build_create_table(table: &TableInfo, from: DbType, to: DbType) -> String {
+    let pk_cols: Vec<&ColumnInfo> = table.columns.iter().filter(|c| c.is_primary_key).collect();
+    // SQLite needs the auto-increment primary key inline
+    let sqlite_rowid = to == DbType::SQLite
+        && pk_cols.len() == 1
+        && is_auto_increment(pk_cols[0], from);
+
+    let mut defs: Vec<String> = table
+        .columns
+        .iter()
+        .map(|c| {
+            if sqlite_rowid && c.is_primary_key {
+                format!("{} INTEGER PRIMARY KEY AUTOINCREMENT", to.quote_identifier(&c.name))
+            } else {
+                format!("{} {}", to.quote_identifier(&c.name), column_definition(c, from, to))
+            }
+        })
+        .collect();
+
+    if !pk_cols.is_empty() && !sqlite_rowid {
+        let cols: Vec<String> = pk_cols.iter().map(|c| to.quote_identifier(&c.name)).collect();
+        defs.push(format!("PRIMARY KEY ({})", cols.join(", ")));
+    }
+
+    defs.extend(table.constraints.iter().map(|c| constraint_definition(c, to)));
+    defs.extend(table.foreign_keys.iter().map(|fk| foreign_key_definition(fk, to)));
+
+    format!(
+        "CREATE TABLE {} (\n  {}\n)",
+        to.quote_table_name(&table.name),
+        defs.join(",\n  ")
+    )
+}
+
+/// Referential action as written in the target dialect, `None` for the default NO ACTION
+fn referential_action(action: &str, to: DbType) -> Option<&str> {
+    match action.trim().to_uppercase().as_str() {
+        "" | "NO ACTION" => None,
+        // SQL Server has no RESTRICT; NO ACTION behaves the same there
+        "RESTRICT" if to == DbType::SQLServer => None,
+        "RESTRICT" => Some("RESTRICT"),
+        "CASCADE" => Some("CASCADE"),
+        "SET NULL" => Some("SET NULL"),
+        "SET DEFAULT" => Some("SET DEFAULT"),
+        _ => None,
+    }
+}
+
+/// Build a `CONSTRAINT .. FOREIGN KEY .. REFERENCES ..` clause in the target dialect
+pub fn foreign_key_definition(fk: &ForeignKeyInfo, to: DbType) -> String {
+    let quote_all = |cols: &[String]| cols.iter().map(|c| to.quote_identifier(c)).collect::<Vec<_>>().join(", ");
+    let mut def = format!(
+        "CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({})",
+        to.quote_identifier(&fk.name),
+        quote_all(&fk.columns),
+        to.quote_table_name(&fk.referenced_table),
+        quote_all(&fk.referenced_columns)
+    );
+    if let Some(action) = referential_action(&fk.on_delete, to) {
+        def.push_str(&format!(" ON DELETE {}", action));
+    }
+    if let Some(action) = referential_action(&fk.on_update, to) {
+        def.push_str(&format!(" ON UPDATE {}", action));
+    }
+    def
+}
+
+/// Check if two foreign keys enforce the same constraint, ignoring their names
+pub fn foreign_keys_equivalent(a: &ForeignKeyInfo, b: &ForeignKeyInfo) -> bool {
+    // RESTRICT and NO ACTION only differ in when the check runs
+    let rule = |r: &str| match r.trim().to_uppercase().as_str() {
+        "" | "RESTRICT" => "NO ACTION".to_string(),
+        other => other.to_string(),
+    };
+    a.columns == b.columns
+        && a.referenced_table == b.referenced_table
+        && a.referenced_columns == b.referenced_columns
+        && rule(&a.on_delete) == rule(&b.on_delete)
+        && rule(&a.on_update) == rule(&b.on_update)
+}
+
+/// Build a `CONSTRAINT .. CHECK ..` or `CONSTRAINT .. UNIQUE ..` clause in the target dialect.
+///
+/// Check expressions are written as read from the source, without translation.
+pub fn constraint_definition(constraint: &ConstraintInfo, to: DbType) -> String {
+    if constraint.is_check() {
+        format!("CONSTRAINT {} CHECK ({})", to.quote_identifier(&constraint.name), constraint.check_clause)
+    } else {
+        let cols: Vec<String> = constraint.columns.iter().map(|c| to.quote_identifier(c)).collect();
+        format!("CONSTRAINT {} UNIQUE ({})", to.quote_identifier(&constraint.name), cols.join(", "))
+    }
+}
+
+/// Check if two constraints enforce the same rule, ignoring their names. Check
+/// expressions are compared without whitespace, case, identifier quotes and
+/// redundant parentheses.
+pub fn constraints_equivalent(a: &ConstraintInfo, b: &ConstraintInfo) -> bool {
+    let normalize = |expr: &str| {
+        strip_outer_parens(expr)
+            .chars()
+            .filter(|c| !c.is_whitespace() && !matches!(c, '"' | '`' | '[' | ']'))
+            .collect::<String>()
+            .to_lowercase()
+    };
+    a.kind == b.kind && a.columns == b.columns && normalize(&a.check_clause) == normalize(&b.check_clause)
+}
+
+/// Build a CREATE INDEX statement for `index` on `table_name` in the target dialect
+pub fn create_index_sql(index: &IndexInfo, table_name: &str, to: DbType) -> String {
+    let cols: Vec<String> = index.columns.iter().map(|c| to.quote_identifier(c)).collect();
+    format!(
+        "CREATE {}INDEX {} ON {} ({});",
+        if index.is_unique { "UNIQUE " } else { "" },
+        to.quote_identifier(&index.name),
+        to.quote_table_name(table_name),
+        cols.join(", ")
+    )
+}
+
+/// Build a DROP INDEX statement in the target dialect
+pub fn drop_index_sql(index_name: &str, table_name: &str, to: DbType) -> String {
+    match to {
+        // Index names are scoped to the table
+        DbType::MySQL | DbType::SQLServer => format!(
+            "DROP INDEX {} ON {};",
+            to.quote_identifier(index_name),
+            to.quote_table_name(table_name)
+        ),
+        // Indexes live in the schema of their table
+        DbType::PostgreSQL => match table_name.split_once('.') {
+            Some((schema, _)) => format!("DROP INDEX {};", to.quote_table_name(&format!("{}.{}", schema, index_name))),
+            None => format!("DROP INDEX {};", to.quote_identifier(index_name)),
+        },
+        DbType::SQLite => format!("DROP INDEX {};", to.quote_identifier(index_name)),
+    }
+}
+
+/// Build a statement creating view `name` in the target dialect. With
+/// `replace` an existing view of that name is replaced.
+///
+/// The definition is written as read from the source, without translation.
+pub fn create_view_sql(name: &str, definition: &str, replace: bool, to: DbType) -> String {
+    let name = to.quote_table_name(name);
+    match (replace, to) {
+        (false, _) => format!("CREATE VIEW {} AS\n{};", name, definition),
+        (true, DbType::MySQL | DbType::PostgreSQL) => format!("CREATE OR REPLACE VIEW {} AS\n{};", name, definition),
+        (true, DbType::SQLServer) => format!("CREATE OR ALTER VIEW {} AS\n{};", name, definition),
+        (true, DbType::SQLite) => format!("DROP VIEW IF EXISTS {};\nCREATE VIEW {} AS\n{};", name, name, definition),
+    }
+}
+
+/// Build a DROP VIEW statement in the target dialect
+pub fn drop_view_sql(name: &str, to: DbType) -> String {
+    format!("DROP VIEW {};", to.quote_table_name(name))
+}
+
+/// Whether two view definitions are the same query, ignoring whitespace and case
+pub fn views_equivalent(a: &str, b: &str) -> bool {
+    let normalize = |sql: &str| sql.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    normalize(a) == normalize(b)
+}
+
+/// Turn the leading `CREATE` of a SQL Server module definition into
+/// `CREATE OR ALTER`, skipping any comments before it
+fn create_or_alter(definition: &str) -> String {
+    match definition.as_bytes().windows(6).position(|w| w.eq_ignore_ascii_case(b"CREATE")) {
+        Some(i) => format!("{}CREATE OR ALTER{}", &definition[..i], &definition[i + "CREATE".len()..]),
+        None => definition.to_string(),
+    }
+}
+
+/// Build the statement(s) creating `routine` in the target dialect. With
+/// `replace` an existing routine of that name is replaced.
+///
+/// The definition is written as read from the source, without translation.
+pub fn create_routine_sql(routine: &RoutineInfo, replace: bool, to: DbType) -> String {
+    match (replace, to) {
+        // pg_get_functiondef already writes CREATE OR REPLACE
+        (false, _) | (true, DbType::PostgreSQL | DbType::SQLite) => format!("{};", routine.definition),
+        (true, DbType::MySQL) => format!("{}\n{};", drop_routine_sql(routine, true, to), routine.definition),
+        (true, DbType::SQLServer) => format!("{};", create_or_alter(&routine.definition)),
+    }
+}
+
+/// Build a DROP PROCEDURE or DROP FUNCTION statement in the target dialect
+pub fn drop_routine_sql(routine: &RoutineInfo, if_exists: bool, to: DbType) -> String {
+    let arguments = if to == DbType::PostgreSQL { format!("({})", routine.arguments) } else { String::new() };
+    format!(
+        "DROP {} {}{}{};",
+        routine.kind,
+        if if_exists { "IF EXISTS " } else { "" },
+        to.quote_table_name(&routine.name),
+        arguments
+    )
+}
+
+/// Build the statement(s) creating `trigger` in the target dialect. With
+/// `replace` an existing trigger of that name is replaced.
+///
+/// The definition is written as read from the source, without translation.
+pub fn create_trigger_sql(trigger: &TriggerInfo, replace: bool, to: DbType) -> String {
+    match (replace, to) {
+        (false, _) => format!("{};", trigger.definition),
+        (true, DbType::SQLServer) => format!("{};", create_or_alter(&trigger.definition)),
+        (true, _) => format!("{}\n{};", drop_trigger_sql(trigger, true, to), trigger.definition),
+    }
+}
+
+/// Build a DROP TRIGGER statement in the target dialect
+pub fn drop_trigger_sql(trigger: &TriggerInfo, if_exists: bool, to: DbType) -> String {
+    let if_exists = if if_exists { "IF EXISTS " } else { "" };
+    match to {
+        // Trigger names are scoped to the table
+        DbType::PostgreSQL => format!(
+            "DROP TRIGGER {}{} ON {};",
+            if_exists,
+            to.quote_identifier(&trigger.name),
+            to.quote_table_name(&trigger.table_name)
+        ),
+        _ => format!("DROP TRIGGER {}{};", if_exists, to.quote_identifier(&trigger.name)),
+    }
+}
+
+/// Whether two routine or trigger definitions are the same, ignoring whitespace
+pub fn bodies_equivalent(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
+}
+
+/// Whether a column type holds character data
+pub fn is_text_type(data_type: &str) -> bool {
+    matches!(
+        parse_type(data_type).base.as_str(),
+        "char"
+            | "character"
+            | "varchar"
+            | "character varying"
+            | "nchar"
+            | "nvarchar"
+            | "varchar2"
+            | "text"
+            | "tinytext"
+            | "mediumtext"
+            | "longtext"
+            | "ntext"
+            | "citext"
+            | "clob"
+    )
+}
+
+/// Whether a key column is ordered as text by `binary_sort_expr`. SQL Server
+/// orders GUIDs by byte groups, so they are ordered by their text instead.
+pub fn sorts_as_text(data_type: &str) -> bool {
+    is_text_type(data_type) || parse_type(data_type).base == "uniqueidentifier"
+}
+
+/// Expression ordering a column by the UTF-8 bytes of its text rather than the
+/// column collation, so text keys sort the same way on every engine and in the
+/// merge of two sides. Literals compared to the expression need it as well.
+///
+/// The expression keeps text keys from using an index: chunk queries over a
+/// text key scan and sort the whole table. On SQL Server it needs a UTF-8
+/// collation, so text and GUID keys require SQL Server 2019 or later.
+pub fn binary_sort_expr(quoted_column: &str, is_text: bool, to: DbType) -> String {
+    if !is_text {
+        return quoted_column.to_string();
+    }
+    match to {
+        // CAST AS BINARY alone gives the bytes of the column character set, e.g. latin1
+        DbType::MySQL => format!("CAST(CONVERT({} USING utf8mb4) AS BINARY)", quoted_column),
+        DbType::PostgreSQL => format!("{} COLLATE \"C\"", quoted_column),
+        DbType::SQLite => format!("{} COLLATE BINARY", quoted_column),
+        // Binary collations compare UTF-16 code units or code page bytes; going
+        // through a UTF-8 collation yields the UTF-8 bytes
+        DbType::SQLServer => format!(
+            "CAST(CAST(CAST({} AS nvarchar(max)) COLLATE Latin1_General_100_BIN2_UTF8 AS varchar(max)) AS varbinary(max))",
+            quoted_column
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(data_type: &str) -> ColumnInfo {
+        ColumnInfo {
+            name: "c".to_string(),
+            data_type: data_type.to_string(),
+            nullable: true,
+            default: None,
+            is_primary_key: false,
+            extra: String::new(),
+            position: 1,
+        }
+    }
+
+    #[test]
+    fn map_type_to_postgres_matches_format_type_names() {
+        let pairs = [
+            ("varchar(255)", "character varying(255)"),
+            ("char(10)", "character(10)"),
+            ("datetime", "timestamp without time zone"),
+            ("time", "time without time zone"),
+            ("decimal(10,2)", "numeric(10,2)"),
+        ];
+        for (mysql, postgres) in pairs {
+            let mapped = map_type(mysql, DbType::MySQL, DbType::PostgreSQL);
+            assert!(types_equivalent(&mapped, postgres, DbType::PostgreSQL), "{} -> {}", mysql, mapped);
+        }
+    }
+
+    #[test]
+    fn map_type_widens_mysql_unsigned_integers() {
+        let cases = [
+            ("tinyint unsigned", DbType::PostgreSQL, "smallint"),
+            ("tinyint(3) unsigned", DbType::SQLServer, "tinyint"),
+            ("tinyint", DbType::SQLServer, "smallint"),
+            ("smallint unsigned", DbType::PostgreSQL, "integer"),
+            ("smallint(5) unsigned", DbType::SQLServer, "int"),
+            ("mediumint unsigned", DbType::PostgreSQL, "integer"),
+            ("int unsigned", DbType::PostgreSQL, "bigint"),
+            ("int(10) unsigned zerofill", DbType::SQLServer, "bigint"),
+            ("int", DbType::PostgreSQL, "integer"),
+            ("bigint unsigned", DbType::PostgreSQL, "numeric(20,0)"),
+            ("bigint(20) unsigned", DbType::SQLServer, "decimal(20,0)"),
+            ("bigint", DbType::SQLServer, "bigint"),
+        ];
+        for (mysql, to, expected) in cases {
+            assert_eq!(map_type(mysql, DbType::MySQL, to), expected, "{} -> {:?}", mysql, to);
+        }
+    }
+
+    #[test]
+    fn normalize_pg_type_keeps_arguments() {
+        assert_eq!(normalize_pg_type("timestamp(3) without time zone"), "timestamp(3)");
+        assert_eq!(normalize_pg_type("timestamp(3) with time zone"), "timestamptz(3)");
+        assert_eq!(normalize_pg_type("numeric(10, 2)"), "numeric(10,2)");
+        assert_eq!(normalize_pg_type("bpchar"), "char");
+        assert_ne!(normalize_pg_type("character varying(100)"), normalize_pg_type("varchar(255)"));
+    }
+
+    #[test]
+    fn columns_equivalent_across_engines_into_postgres() {
+        let pairs = [
+            ("varchar(255)", "character varying(255)"),
+            ("char(2)", "character(2)"),
+            ("datetime", "timestamp without time zone"),
+            ("time", "time without time zone"),
+        ];
+        for (mysql, postgres) in pairs {
+            assert!(
+                columns_equivalent(&column(mysql), DbType::MySQL, &column(postgres), DbType::PostgreSQL),
+                "{} vs {}",
+                mysql,
+                postgres
+            );
+        }
+        assert!(columns_equivalent(
+            &column("nvarchar(50)"),
+            DbType::SQLServer,
+            &column("character varying(50)"),
+            DbType::PostgreSQL
+        ));
+    }
+
+    #[test]
+    fn columns_equivalent_detects_real_changes() {
+        assert!(!columns_equivalent(
+            &column("varchar(100)"),
+            DbType::MySQL,
+            &column("character varying(255)"),
+            DbType::PostgreSQL
+        ));
+        assert!(!columns_equivalent(
+            &column("datetime"),
+            DbType::MySQL,
+            &column("timestamp with time zone"),
+            DbType::PostgreSQL
+        ));
+    }
+
+    #[test]
+    fn binary_sort_expr_orders_by_utf8_bytes() {
+        assert_eq!(binary_sort_expr("`k`", false, DbType::MySQL), "`k`");
+        assert_eq!(binary_sort_expr("`k`", true, DbType::MySQL), "CAST(CONVERT(`k` USING utf8mb4) AS BINARY)");
+        assert_eq!(binary_sort_expr("\"k\"", true, DbType::PostgreSQL), "\"k\" COLLATE \"C\"");
+        assert!(binary_sort_expr("[k]", true, DbType::SQLServer).contains("COLLATE Latin1_General_100_BIN2_UTF8"));
+        assert!(sorts_as_text("uniqueidentifier"));
+        assert!(sorts_as_text("nvarchar(20)"));
+        assert!(!sorts_as_text("int"));
+    }
+}
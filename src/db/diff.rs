@@ -1,6 +1,17 @@
 use serde::{Deserialize, Serialize};
 
-use super::{ColumnInfo, DbType, SchemaInfo, TableInfo};
+use super::dialect::{
+    bodies_equivalent, build_create_table, column_default, column_definition, columns_equivalent,
+    constraint_definition, constraints_equivalent, create_index_sql, create_routine_sql, create_trigger_sql, create_view_sql, drop_index_sql,
+    drop_routine_sql, drop_trigger_sql, drop_view_sql, foreign_key_definition, foreign_keys_equivalent,
+    is_auto_increment, map_type, types_equivalent, views_equivalent,
+};
+use super::plan::{cyclic_tables, dependent_views, order_migration};
+use super::schema::pg_relation;
+use super::{
+    ColumnInfo, ConstraintInfo, DbType, DomainInfo, EnumInfo, ForeignKeyInfo, IndexInfo, SchemaInfo, SequenceInfo,
+    TableInfo, TriggerInfo,
+};
 
 /// Diff type
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -10,33 +21,132 @@ pub enum DiffType {
     Modified,
 }
 
+/// What a schema difference changes, used to order a migration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    CreateTable,
+    DropTable,
+    /// SQLite table recreated in the source shape
+    RebuildTable,
+    AddColumn,
+    DropColumn,
+    ModifyColumn,
+    AddIndex,
+    DropIndex,
+    ModifyIndex,
+    AddForeignKey,
+    DropForeignKey,
+    /// CHECK or UNIQUE constraint
+    AddConstraint,
+    DropConstraint,
+    CreateView,
+    /// View redefined in place
+    ReplaceView,
+    DropView,
+    CreateRoutine,
+    ReplaceRoutine,
+    DropRoutine,
+    CreateTrigger,
+    ReplaceTrigger,
+    DropTrigger,
+    CreateSequence,
+    /// Sequence options, owner or current value changed
+    AlterSequence,
+    DropSequence,
+    /// PostgreSQL enum type
+    CreateType,
+    AlterType,
+    DropType,
+    CreateDomain,
+    AlterDomain,
+    DropDomain,
+}
+
 /// Schema difference result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiffResult {
     pub diff_type: DiffType,
+    pub kind: ChangeKind,
     pub table_name: String,
     pub detail: String,
     pub sql: String,
+    /// Line diff of the target and source definitions of a changed routine or trigger
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body_diff: String,
 }
 
 /// Compare two schemas and return differences
 pub fn compare_schemas(source: &SchemaInfo, target: &SchemaInfo, target_db_type: DbType) -> Vec<DiffResult> {
     let mut results = Vec::new();
+    let source_db_type = source.db_type;
+
+    // MySQL reads UNIQUE constraints back as unique indexes, so create them as such
+    let unique_as_indexes: Vec<TableInfo>;
+    let source_table_list = if target_db_type == DbType::MySQL && source_db_type != DbType::MySQL {
+        unique_as_indexes = source.tables.iter().map(unique_constraints_as_indexes).collect();
+        &unique_as_indexes
+    } else {
+        &source.tables
+    };
 
     let source_tables: std::collections::HashMap<&str, &TableInfo> =
-        source.tables.iter().map(|t| (t.name.as_str(), t)).collect();
+        source_table_list.iter().map(|t| (t.name.as_str(), t)).collect();
     let target_tables: std::collections::HashMap<&str, &TableInfo> =
         target.tables.iter().map(|t| (t.name.as_str(), t)).collect();
 
+    // New tables in a foreign key cycle are created without the foreign keys
+    // between them, which are added once all of them exist. SQLite accepts
+    // references to tables that do not exist yet.
+    let new_tables: std::collections::BTreeSet<String> = source_tables
+        .keys()
+        .filter(|name| !target_tables.contains_key(*name))
+        .map(|name| name.to_string())
+        .collect();
+    let cyclic = match target_db_type {
+        DbType::SQLite => std::collections::BTreeSet::new(),
+        _ => cyclic_tables(&new_tables, source),
+    };
+
     // Find tables only in source (need to add to target)
     for (name, table) in &source_tables {
         if !target_tables.contains_key(name) {
+            let (deferred_fks, foreign_keys): (Vec<ForeignKeyInfo>, Vec<ForeignKeyInfo>) =
+                table.foreign_keys.iter().cloned().partition(|fk| {
+                    cyclic.contains(*name) && fk.referenced_table != *name && cyclic.contains(&fk.referenced_table)
+                });
+            // PostgreSQL and SQL Server DDL is synthesized from the columns only,
+            // as is the DDL of tables renamed by a name mapping
+            let use_create_sql = source_db_type == target_db_type
+                && matches!(source_db_type, DbType::MySQL | DbType::SQLite)
+                && !table.create_sql.is_empty()
+                && deferred_fks.is_empty();
+            let mut sql = if use_create_sql {
+                format!("{};", table.create_sql.trim_end().trim_end_matches(';'))
+            } else {
+                let table = TableInfo {
+                    foreign_keys,
+                    ..(*table).clone()
+                };
+                format!("{};", build_create_table(&table, source_db_type, target_db_type))
+            };
+            // MySQL's SHOW CREATE TABLE already includes the indexes
+            if !(use_create_sql && source_db_type == DbType::MySQL) {
+                for index in &table.indexes {
+                    sql.push('\n');
+                    sql.push_str(&create_index_sql(index, name, target_db_type));
+                }
+            }
             results.push(DiffResult {
                 diff_type: DiffType::Added,
+                kind: ChangeKind::CreateTable,
                 table_name: name.to_string(),
                 detail: "Table exists in source but not in target".to_string(),
-                sql: format!("{};", table.create_sql),
+                sql,
+                body_diff: String::new(),
             });
+            for fk in &deferred_fks {
+                results.push(add_foreign_key_diff(name, fk, target_db_type));
+            }
         }
     }
 
@@ -45,9 +155,11 @@ pub fn compare_schemas(source: &SchemaInfo, target: &SchemaInfo, target_db_type:
         if !source_tables.contains_key(name) {
             results.push(DiffResult {
                 diff_type: DiffType::Removed,
+                kind: ChangeKind::DropTable,
                 table_name: name.to_string(),
                 detail: "Table exists in target but not in source".to_string(),
-                sql: format!("DROP TABLE {};", target_db_type.quote_identifier(name)),
+                sql: format!("DROP TABLE {};", target_db_type.quote_table_name(name)),
+                body_diff: String::new(),
             });
         }
     }
@@ -55,31 +167,672 @@ pub fn compare_schemas(source: &SchemaInfo, target: &SchemaInfo, target_db_type:
     // Compare existing tables
     for (name, source_table) in &source_tables {
         if let Some(target_table) = target_tables.get(name) {
-            let table_diffs = compare_tables(name, source_table, target_table, target_db_type);
+            let table_diffs =
+                compare_tables(name, source_table, target_table, source_db_type, target_db_type);
             results.extend(table_diffs);
         }
     }
 
-    // Sort results
-    results.sort_by(|a, b| {
-        let type_order = |t: &DiffType| match t {
-            DiffType::Added => 0,
-            DiffType::Modified => 1,
-            DiffType::Removed => 2,
+    let views = compare_views(source, target, &results, target_db_type);
+    results.extend(views);
+    results.extend(compare_routines(source, target, target_db_type));
+    // Rebuilding a SQLite table drops its triggers
+    let rebuilt: Vec<String> = results
+        .iter()
+        .filter(|d| d.kind == ChangeKind::RebuildTable)
+        .map(|d| d.table_name.clone())
+        .collect();
+    results.extend(compare_triggers(source, target, &rebuilt, target_db_type));
+    // Sequences and types have no counterpart on the other engines
+    if source_db_type == DbType::PostgreSQL && target_db_type == DbType::PostgreSQL {
+        results.extend(compare_sequences(source, target));
+        results.extend(compare_enums(source, target));
+        results.extend(compare_domains(source, target));
+    }
+
+    // Order so the diffs can be applied top to bottom
+    order_migration(&mut results, source, target);
+
+    results
+}
+
+/// Copy of `table` with its UNIQUE constraints turned into unique indexes
+fn unique_constraints_as_indexes(table: &TableInfo) -> TableInfo {
+    let (unique, checks): (Vec<ConstraintInfo>, Vec<ConstraintInfo>) =
+        table.constraints.iter().cloned().partition(|c| !c.is_check());
+    let mut table = TableInfo {
+        constraints: checks,
+        ..table.clone()
+    };
+    table.indexes.extend(unique.into_iter().map(|c| IndexInfo {
+        name: c.name,
+        columns: c.columns,
+        is_unique: true,
+    }));
+    table
+}
+
+/// Compare the views of two schemas by name and definition.
+///
+/// Views over tables that `table_diffs` alter, rebuild or drop are dropped
+/// before those changes and created again afterwards: PostgreSQL refuses the
+/// changes while a view depends on them, and the other engines leave the
+/// view broken.
+fn compare_views(
+    source: &SchemaInfo,
+    target: &SchemaInfo,
+    table_diffs: &[DiffResult],
+    db_type: DbType,
+) -> Vec<DiffResult> {
+    let mut results = Vec::new();
+
+    let changed_tables: Vec<&str> = table_diffs
+        .iter()
+        .filter(|d| {
+            matches!(
+                d.kind,
+                ChangeKind::ModifyColumn | ChangeKind::DropColumn | ChangeKind::RebuildTable | ChangeKind::DropTable
+            )
+        })
+        .map(|d| d.table_name.as_str())
+        .collect();
+    let blocking = dependent_views(target, &changed_tables);
+
+    for view in &source.views {
+        match target.views.iter().find(|v| v.name == view.name) {
+            Some(existing) if blocking.contains(&existing.name) => {
+                results.push(DiffResult {
+                    diff_type: DiffType::Modified,
+                    kind: ChangeKind::DropView,
+                    table_name: view.name.clone(),
+                    detail: "Drop view while the tables it selects from change".to_string(),
+                    sql: drop_view_sql(&view.name, db_type),
+                    body_diff: String::new(),
+                });
+                results.push(DiffResult {
+                    diff_type: DiffType::Modified,
+                    kind: ChangeKind::CreateView,
+                    table_name: view.name.clone(),
+                    detail: "Recreate view after the tables it selects from change".to_string(),
+                    sql: create_view_sql(&view.name, &view.definition, false, db_type),
+                    body_diff: String::new(),
+                });
+            }
+            None => results.push(DiffResult {
+                diff_type: DiffType::Added,
+                kind: ChangeKind::CreateView,
+                table_name: view.name.clone(),
+                detail: "View exists in source but not in target".to_string(),
+                sql: create_view_sql(&view.name, &view.definition, false, db_type),
+                body_diff: String::new(),
+            }),
+            Some(existing) if !views_equivalent(&existing.definition, &view.definition) => {
+                results.push(DiffResult {
+                    diff_type: DiffType::Modified,
+                    kind: ChangeKind::ReplaceView,
+                    table_name: view.name.clone(),
+                    detail: "View definition differs".to_string(),
+                    sql: create_view_sql(&view.name, &view.definition, true, db_type),
+                    body_diff: String::new(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    for view in &target.views {
+        if !source.views.iter().any(|v| v.name == view.name) {
+            results.push(DiffResult {
+                diff_type: DiffType::Removed,
+                kind: ChangeKind::DropView,
+                table_name: view.name.clone(),
+                detail: "View exists in target but not in source".to_string(),
+                sql: drop_view_sql(&view.name, db_type),
+                body_diff: String::new(),
+            });
+        }
+    }
+
+    results
+}
+
+/// Compare the stored procedures and functions of two schemas by signature and definition
+fn compare_routines(source: &SchemaInfo, target: &SchemaInfo, db_type: DbType) -> Vec<DiffResult> {
+    let mut results = Vec::new();
+
+    for routine in &source.routines {
+        let signature = routine.signature();
+        let label = format!("{} {}", routine.kind.to_lowercase(), signature);
+        match target.routines.iter().find(|r| r.kind == routine.kind && r.signature() == signature) {
+            None => results.push(DiffResult {
+                diff_type: DiffType::Added,
+                kind: ChangeKind::CreateRoutine,
+                table_name: signature,
+                detail: format!("Create {}", label),
+                sql: create_routine_sql(routine, false, db_type),
+                body_diff: String::new(),
+            }),
+            Some(existing) if !bodies_equivalent(&existing.definition, &routine.definition) => {
+                results.push(DiffResult {
+                    diff_type: DiffType::Modified,
+                    kind: ChangeKind::ReplaceRoutine,
+                    table_name: signature,
+                    detail: format!("Replace {}: definition differs", label),
+                    sql: create_routine_sql(routine, true, db_type),
+                    body_diff: line_diff(&existing.definition, &routine.definition),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    for routine in &target.routines {
+        let signature = routine.signature();
+        if !source.routines.iter().any(|r| r.kind == routine.kind && r.signature() == signature) {
+            results.push(DiffResult {
+                diff_type: DiffType::Removed,
+                kind: ChangeKind::DropRoutine,
+                detail: format!("Drop {} {}", routine.kind.to_lowercase(), signature),
+                table_name: signature,
+                sql: drop_routine_sql(routine, false, db_type),
+                body_diff: String::new(),
+            });
+        }
+    }
+
+    results
+}
+
+/// Compare the triggers of two schemas by table, name and definition.
+/// Triggers of the tables in `rebuilt` are created again, as the rebuild drops them.
+fn compare_triggers(source: &SchemaInfo, target: &SchemaInfo, rebuilt: &[String], db_type: DbType) -> Vec<DiffResult> {
+    let mut results = Vec::new();
+    let same_trigger = |a: &TriggerInfo, b: &TriggerInfo| a.name == b.name && a.table_name == b.table_name;
+
+    for trigger in &source.triggers {
+        let existing = target.triggers.iter().find(|t| same_trigger(t, trigger));
+        let (diff_type, kind, detail, replace, body_diff) = match existing {
+            None => (DiffType::Added, ChangeKind::CreateTrigger, "Create trigger", false, String::new()),
+            Some(existing) if !bodies_equivalent(&existing.definition, &trigger.definition) => (
+                DiffType::Modified,
+                ChangeKind::ReplaceTrigger,
+                "Replace trigger",
+                !rebuilt.contains(&trigger.table_name),
+                line_diff(&existing.definition, &trigger.definition),
+            ),
+            Some(_) if rebuilt.contains(&trigger.table_name) => (
+                DiffType::Modified,
+                ChangeKind::CreateTrigger,
+                "Recreate trigger dropped by the table rebuild",
+                false,
+                String::new(),
+            ),
+            Some(_) => continue,
+        };
+        results.push(DiffResult {
+            diff_type,
+            kind,
+            table_name: trigger.table_name.clone(),
+            detail: format!("{}: {}", detail, trigger.name),
+            sql: create_trigger_sql(trigger, replace, db_type),
+            body_diff,
+        });
+    }
+
+    for trigger in &target.triggers {
+        if !source.triggers.iter().any(|t| same_trigger(t, trigger)) {
+            results.push(DiffResult {
+                diff_type: DiffType::Removed,
+                kind: ChangeKind::DropTrigger,
+                table_name: trigger.table_name.clone(),
+                detail: format!("Drop trigger: {}", trigger.name),
+                sql: drop_trigger_sql(trigger, false, db_type),
+                body_diff: String::new(),
+            });
+        }
+    }
+
+    results
+}
+
+/// Quote a string as a SQL literal
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// CREATE SEQUENCE statement for `sequence`, continuing from its current value
+fn create_sequence_sql(sequence: &SequenceInfo) -> String {
+    let name = DbType::PostgreSQL.quote_table_name(&sequence.name);
+    let mut sql = format!(
+        "CREATE SEQUENCE {} AS {} INCREMENT BY {} MINVALUE {} MAXVALUE {} START WITH {} {};",
+        name,
+        sequence.data_type,
+        sequence.increment,
+        sequence.min_value,
+        sequence.max_value,
+        sequence.start,
+        if sequence.cycle { "CYCLE" } else { "NO CYCLE" }
+    );
+    if let Some(value) = sequence.last_value {
+        sql.push_str(&format!("\nSELECT setval({}, {});", quote_literal(&pg_relation(&sequence.name)), value));
+    }
+    sql
+}
+
+/// ALTER SEQUENCE .. OWNED BY statement for the owner of `sequence`
+fn sequence_owner_sql(sequence: &SequenceInfo) -> String {
+    let owner = match &sequence.owned_by {
+        Some((table, column)) => format!(
+            "{}.{}",
+            DbType::PostgreSQL.quote_table_name(table),
+            DbType::PostgreSQL.quote_identifier(column)
+        ),
+        None => "NONE".to_string(),
+    };
+    format!("ALTER SEQUENCE {} OWNED BY {};", DbType::PostgreSQL.quote_table_name(&sequence.name), owner)
+}
+
+/// Compare the sequences of two PostgreSQL schemas. A target sequence behind
+/// its source counterpart is advanced to the source value.
+fn compare_sequences(source: &SchemaInfo, target: &SchemaInfo) -> Vec<DiffResult> {
+    let mut results = Vec::new();
+    let db_type = DbType::PostgreSQL;
+
+    for sequence in &source.sequences {
+        let name = db_type.quote_table_name(&sequence.name);
+        let Some(existing) = target.sequences.iter().find(|s| s.name == sequence.name) else {
+            results.push(DiffResult {
+                diff_type: DiffType::Added,
+                kind: ChangeKind::CreateSequence,
+                table_name: sequence.name.clone(),
+                detail: "Sequence exists in source but not in target".to_string(),
+                sql: create_sequence_sql(sequence),
+                body_diff: String::new(),
+            });
+            // The owning column exists only once its table is created
+            if let Some((table, column)) = &sequence.owned_by {
+                results.push(DiffResult {
+                    diff_type: DiffType::Added,
+                    kind: ChangeKind::AlterSequence,
+                    table_name: sequence.name.clone(),
+                    detail: format!("Set owner: {}.{}", table, column),
+                    sql: sequence_owner_sql(sequence),
+                    body_diff: String::new(),
+                });
+            }
+            continue;
+        };
+
+        let mut changes = Vec::new();
+        let mut statements = Vec::new();
+        let options_equal = existing.data_type == sequence.data_type
+            && existing.start == sequence.start
+            && existing.increment == sequence.increment
+            && existing.min_value == sequence.min_value
+            && existing.max_value == sequence.max_value
+            && existing.cycle == sequence.cycle;
+        if !options_equal {
+            changes.push(format!(
+                "options ({} by {}, {}..{} -> {} by {}, {}..{})",
+                existing.data_type,
+                existing.increment,
+                existing.min_value,
+                existing.max_value,
+                sequence.data_type,
+                sequence.increment,
+                sequence.min_value,
+                sequence.max_value
+            ));
+            statements.push(format!(
+                "ALTER SEQUENCE {} AS {} INCREMENT BY {} MINVALUE {} MAXVALUE {} START WITH {} {};",
+                name,
+                sequence.data_type,
+                sequence.increment,
+                sequence.min_value,
+                sequence.max_value,
+                sequence.start,
+                if sequence.cycle { "CYCLE" } else { "NO CYCLE" }
+            ));
+        }
+        if existing.owned_by != sequence.owned_by {
+            changes.push("owner".to_string());
+            statements.push(sequence_owner_sql(sequence));
+        }
+        if let Some(value) = sequence.last_value.filter(|v| !matches!(existing.last_value, Some(e) if e >= *v)) {
+            changes.push(format!("advance to {}", value));
+            statements.push(format!("SELECT setval({}, {});", quote_literal(&pg_relation(&sequence.name)), value));
+        }
+        if !changes.is_empty() {
+            results.push(DiffResult {
+                diff_type: DiffType::Modified,
+                kind: ChangeKind::AlterSequence,
+                table_name: sequence.name.clone(),
+                detail: format!("Alter sequence: {}", changes.join(", ")),
+                sql: statements.join("\n"),
+                body_diff: String::new(),
+            });
+        }
+    }
+
+    for sequence in &target.sequences {
+        if !source.sequences.iter().any(|s| s.name == sequence.name) {
+            results.push(DiffResult {
+                diff_type: DiffType::Removed,
+                kind: ChangeKind::DropSequence,
+                table_name: sequence.name.clone(),
+                detail: "Sequence exists in target but not in source".to_string(),
+                // A sequence owned by a dropped table is dropped with it
+                sql: format!("DROP SEQUENCE IF EXISTS {};", db_type.quote_table_name(&sequence.name)),
+                body_diff: String::new(),
+            });
+        }
+    }
+
+    results
+}
+
+/// Statements replacing type `name` of the target with the one `create_sql`
+/// creates: the old type is renamed, the new one created, the target columns
+/// of the type converted with `cast` (e.g. `::text::`) and the old type dropped
+fn recreate_type_sql(name: &str, keyword: &str, create_sql: &str, cast: &str, target: &SchemaInfo) -> String {
+    let db_type = DbType::PostgreSQL;
+    let old_name = format!("{}_old", name);
+    let bare_old_name = old_name.rsplit_once('.').map_or(old_name.as_str(), |(_, n)| n);
+    let unquoted = |t: &str| t.replace('"', "");
+
+    let mut statements = vec![
+        format!("ALTER {} {} RENAME TO {};", keyword, db_type.quote_table_name(name), db_type.quote_identifier(bare_old_name)),
+        create_sql.to_string(),
+    ];
+    for table in &target.tables {
+        for column in table.columns.iter().filter(|c| unquoted(&c.data_type) == name) {
+            let table_name = db_type.quote_table_name(&table.name);
+            let column_name = db_type.quote_identifier(&column.name);
+            // A default of the old type cannot be cast along with the column
+            if column.default.is_some() {
+                statements.push(format!("ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;", table_name, column_name));
+            }
+            statements.push(format!(
+                "ALTER TABLE {} ALTER COLUMN {} TYPE {} USING {}{}{};",
+                table_name,
+                column_name,
+                db_type.quote_table_name(name),
+                column_name,
+                cast,
+                db_type.quote_table_name(name)
+            ));
+            if let Some(default) = &column.default {
+                statements.push(format!("ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};", table_name, column_name, default));
+            }
+        }
+    }
+    statements.push(format!("DROP {} {};", keyword, db_type.quote_table_name(&old_name)));
+    statements.join("\n")
+}
+
+/// CREATE TYPE .. AS ENUM statement for `enum_type`
+fn create_enum_sql(enum_type: &EnumInfo) -> String {
+    let labels: Vec<String> = enum_type.labels.iter().map(|l| quote_literal(l)).collect();
+    format!(
+        "CREATE TYPE {} AS ENUM ({});",
+        DbType::PostgreSQL.quote_table_name(&enum_type.name),
+        labels.join(", ")
+    )
+}
+
+/// Compare the enum types of two PostgreSQL schemas. Labels new in the source
+/// are added in place; removed or reordered labels need the type recreated.
+fn compare_enums(source: &SchemaInfo, target: &SchemaInfo) -> Vec<DiffResult> {
+    let mut results = Vec::new();
+    let db_type = DbType::PostgreSQL;
+
+    for enum_type in &source.enums {
+        let Some(existing) = target.enums.iter().find(|e| e.name == enum_type.name) else {
+            results.push(DiffResult {
+                diff_type: DiffType::Added,
+                kind: ChangeKind::CreateType,
+                table_name: enum_type.name.clone(),
+                detail: format!("Create enum: {}", enum_type.labels.join(", ")),
+                sql: create_enum_sql(enum_type),
+                body_diff: String::new(),
+            });
+            continue;
+        };
+        if existing.labels == enum_type.labels {
+            continue;
+        }
+
+        let kept: Vec<&String> = enum_type.labels.iter().filter(|l| existing.labels.contains(l)).collect();
+        let only_added = kept.len() == existing.labels.len() && kept.iter().zip(&existing.labels).all(|(a, b)| *a == b);
+        let (detail, sql) = if only_added {
+            // Place each new label after the label before it in the source order
+            let statements: Vec<String> = enum_type
+                .labels
+                .iter()
+                .enumerate()
+                .filter(|(_, l)| !existing.labels.contains(l))
+                .map(|(i, label)| {
+                    let position = match i {
+                        0 => format!(" BEFORE {}", quote_literal(&enum_type.labels[1])),
+                        _ => format!(" AFTER {}", quote_literal(&enum_type.labels[i - 1])),
+                    };
+                    format!(
+                        "ALTER TYPE {} ADD VALUE {}{};",
+                        db_type.quote_table_name(&enum_type.name),
+                        quote_literal(label),
+                        position
+                    )
+                })
+                .collect();
+            let added: Vec<&str> = enum_type
+                .labels
+                .iter()
+                .filter(|l| !existing.labels.contains(l))
+                .map(String::as_str)
+                .collect();
+            (format!("Add enum labels: {}", added.join(", ")), statements.join("\n"))
+        } else {
+            (
+                format!("Recreate enum: {} -> {}", existing.labels.join(", "), enum_type.labels.join(", ")),
+                recreate_type_sql(&enum_type.name, "TYPE", &create_enum_sql(enum_type), "::text::", target),
+            )
+        };
+        results.push(DiffResult {
+            diff_type: DiffType::Modified,
+            kind: ChangeKind::AlterType,
+            table_name: enum_type.name.clone(),
+            detail,
+            sql,
+            body_diff: String::new(),
+        });
+    }
+
+    for enum_type in &target.enums {
+        if !source.enums.iter().any(|e| e.name == enum_type.name) {
+            results.push(DiffResult {
+                diff_type: DiffType::Removed,
+                kind: ChangeKind::DropType,
+                table_name: enum_type.name.clone(),
+                detail: "Enum exists in target but not in source".to_string(),
+                sql: format!("DROP TYPE {};", db_type.quote_table_name(&enum_type.name)),
+                body_diff: String::new(),
+            });
+        }
+    }
+
+    results
+}
+
+/// CREATE DOMAIN statement for `domain`
+fn create_domain_sql(domain: &DomainInfo) -> String {
+    let db_type = DbType::PostgreSQL;
+    let mut sql = format!("CREATE DOMAIN {} AS {}", db_type.quote_table_name(&domain.name), domain.base_type);
+    if let Some(default) = &domain.default {
+        sql.push_str(&format!(" DEFAULT {}", default));
+    }
+    if !domain.nullable {
+        sql.push_str(" NOT NULL");
+    }
+    for (name, check) in &domain.checks {
+        sql.push_str(&format!(" CONSTRAINT {} {}", db_type.quote_identifier(name), check));
+    }
+    sql.push(';');
+    sql
+}
+
+/// Compare the domains of two PostgreSQL schemas. Defaults, NOT NULL and
+/// CHECK constraints are altered in place; a new base type needs the domain
+/// recreated.
+fn compare_domains(source: &SchemaInfo, target: &SchemaInfo) -> Vec<DiffResult> {
+    let mut results = Vec::new();
+    let db_type = DbType::PostgreSQL;
+
+    for domain in &source.domains {
+        let name = db_type.quote_table_name(&domain.name);
+        let Some(existing) = target.domains.iter().find(|d| d.name == domain.name) else {
+            results.push(DiffResult {
+                diff_type: DiffType::Added,
+                kind: ChangeKind::CreateDomain,
+                table_name: domain.name.clone(),
+                detail: format!("Create domain over {}", domain.base_type),
+                sql: create_domain_sql(domain),
+                body_diff: String::new(),
+            });
+            continue;
         };
-        type_order(&a.diff_type)
-            .cmp(&type_order(&b.diff_type))
-            .then_with(|| a.table_name.cmp(&b.table_name))
-    });
+
+        if existing.base_type != domain.base_type {
+            results.push(DiffResult {
+                diff_type: DiffType::Modified,
+                kind: ChangeKind::AlterDomain,
+                table_name: domain.name.clone(),
+                detail: format!("Recreate domain: {} -> {}", existing.base_type, domain.base_type),
+                sql: recreate_type_sql(&domain.name, "DOMAIN", &create_domain_sql(domain), "::", target),
+                body_diff: String::new(),
+            });
+            continue;
+        }
+
+        let mut changes = Vec::new();
+        let mut statements = Vec::new();
+        if existing.default != domain.default {
+            changes.push("default".to_string());
+            statements.push(match &domain.default {
+                Some(default) => format!("ALTER DOMAIN {} SET DEFAULT {};", name, default),
+                None => format!("ALTER DOMAIN {} DROP DEFAULT;", name),
+            });
+        }
+        if existing.nullable != domain.nullable {
+            changes.push(if domain.nullable { "drop NOT NULL" } else { "set NOT NULL" }.to_string());
+            let action = if domain.nullable { "DROP" } else { "SET" };
+            statements.push(format!("ALTER DOMAIN {} {} NOT NULL;", name, action));
+        }
+        // Changed checks are dropped before they are added again
+        for (check_name, _) in existing.checks.iter().filter(|c| !domain.checks.contains(c)) {
+            changes.push(format!("drop check {}", check_name));
+            statements.push(format!("ALTER DOMAIN {} DROP CONSTRAINT {};", name, db_type.quote_identifier(check_name)));
+        }
+        for (check_name, check) in domain.checks.iter().filter(|c| !existing.checks.contains(c)) {
+            changes.push(format!("add check {}", check_name));
+            statements.push(format!(
+                "ALTER DOMAIN {} ADD CONSTRAINT {} {};",
+                name,
+                db_type.quote_identifier(check_name),
+                check
+            ));
+        }
+        if !changes.is_empty() {
+            results.push(DiffResult {
+                diff_type: DiffType::Modified,
+                kind: ChangeKind::AlterDomain,
+                table_name: domain.name.clone(),
+                detail: format!("Alter domain: {}", changes.join(", ")),
+                sql: statements.join("\n"),
+                body_diff: String::new(),
+            });
+        }
+    }
+
+    for domain in &target.domains {
+        if !source.domains.iter().any(|d| d.name == domain.name) {
+            results.push(DiffResult {
+                diff_type: DiffType::Removed,
+                kind: ChangeKind::DropDomain,
+                table_name: domain.name.clone(),
+                detail: "Domain exists in target but not in source".to_string(),
+                sql: format!("DROP DOMAIN {};", db_type.quote_table_name(&domain.name)),
+                body_diff: String::new(),
+            });
+        }
+    }
 
     results
 }
 
+/// Lines of context kept around each change by `line_diff`
+const DIFF_CONTEXT: usize = 2;
+
+/// Line diff from `old` to `new`: changed lines prefixed with `-` and `+`,
+/// with a few unchanged lines of context around them and `...` between hunks
+fn line_diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence lengths of every pair of suffixes
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i].trim_end() == new[j].trim_end() {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines: Vec<(char, &str)> = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i].trim_end() == new[j].trim_end() {
+            lines.push((' ', new[j]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    let near_change = |k: usize| {
+        let start = k.saturating_sub(DIFF_CONTEXT);
+        let end = (k + DIFF_CONTEXT + 1).min(lines.len());
+        lines[start..end].iter().any(|(tag, _)| *tag != ' ')
+    };
+    let mut out: Vec<String> = Vec::new();
+    let mut skipped = false;
+    for (k, (tag, line)) in lines.iter().enumerate() {
+        if near_change(k) {
+            if skipped && !out.is_empty() {
+                out.push("...".to_string());
+            }
+            skipped = false;
+            out.push(format!("{} {}", tag, line));
+        } else {
+            skipped = true;
+        }
+    }
+    out.join("\n")
+}
+
 /// Compare two tables
 fn compare_tables(
     table_name: &str,
     source: &TableInfo,
     target: &TableInfo,
+    source_db_type: DbType,
     db_type: DbType,
 ) -> Vec<DiffResult> {
     let mut results = Vec::new();
@@ -89,20 +842,101 @@ fn compare_tables(
     let target_cols: std::collections::HashMap<&str, &ColumnInfo> =
         target.columns.iter().map(|c| (c.name.as_str(), c)).collect();
 
+    let modified: Vec<(&ColumnInfo, &ColumnInfo)> = source
+        .columns
+        .iter()
+        .filter_map(|source_col| {
+            let target_col = *target_cols.get(source_col.name.as_str())?;
+            if columns_equivalent(source_col, source_db_type, target_col, db_type) {
+                None
+            } else {
+                Some((source_col, target_col))
+            }
+        })
+        .collect();
+
+    let added_fks: Vec<&ForeignKeyInfo> = source
+        .foreign_keys
+        .iter()
+        .filter(|fk| !target.foreign_keys.iter().any(|t| foreign_keys_equivalent(fk, t)))
+        .collect();
+    let removed_fks: Vec<&ForeignKeyInfo> = target
+        .foreign_keys
+        .iter()
+        .filter(|fk| !source.foreign_keys.iter().any(|s| foreign_keys_equivalent(fk, s)))
+        .collect();
+
+    // Constraints are matched by definition like foreign keys
+    let added_constraints: Vec<&ConstraintInfo> = source
+        .constraints
+        .iter()
+        .filter(|c| !target.constraints.iter().any(|t| constraints_equivalent(c, t)))
+        .collect();
+    let removed_constraints: Vec<&ConstraintInfo> = target
+        .constraints
+        .iter()
+        .filter(|c| !source.constraints.iter().any(|s| constraints_equivalent(c, s)))
+        .collect();
+
+    // SQLite can neither alter a column nor add or drop a constraint in place:
+    // rebuild the table in the source shape, which also covers added and dropped
+    // columns and indexes
+    if db_type == DbType::SQLite
+        && !(modified.is_empty()
+            && added_fks.is_empty()
+            && removed_fks.is_empty()
+            && added_constraints.is_empty()
+            && removed_constraints.is_empty())
+    {
+        let mut reasons = Vec::new();
+        if !modified.is_empty() {
+            let names: Vec<&str> = modified.iter().map(|(c, _)| c.name.as_str()).collect();
+            reasons.push(format!("modify columns: {}", names.join(", ")));
+        }
+        if !added_fks.is_empty() || !removed_fks.is_empty() {
+            reasons.push(format!(
+                "change foreign keys: {} added, {} dropped",
+                added_fks.len(),
+                removed_fks.len()
+            ));
+        }
+        if !added_constraints.is_empty() || !removed_constraints.is_empty() {
+            reasons.push(format!(
+                "change constraints: {} added, {} dropped",
+                added_constraints.len(),
+                removed_constraints.len()
+            ));
+        }
+        results.push(DiffResult {
+            diff_type: DiffType::Modified,
+            kind: ChangeKind::RebuildTable,
+            table_name: table_name.to_string(),
+            detail: format!("Rebuild table to {}", reasons.join("; ")),
+            sql: sqlite_rebuild_sql(source, target, source_db_type),
+            body_diff: String::new(),
+        });
+        return results;
+    }
+
     // Find added columns
     for (col_name, col) in &source_cols {
         if !target_cols.contains_key(col_name) {
-            let col_def = build_column_def(col);
+            let col_def = column_definition(col, source_db_type, db_type);
+            // SQL Server does not accept the COLUMN keyword here
+            let add = if db_type == DbType::SQLServer { "ADD" } else { "ADD COLUMN" };
             results.push(DiffResult {
                 diff_type: DiffType::Modified,
+                kind: ChangeKind::AddColumn,
                 table_name: table_name.to_string(),
                 detail: format!("Add column: {}", col_name),
                 sql: format!(
-                    "ALTER TABLE {} ADD COLUMN {} {};",
-                    db_type.quote_identifier(table_name),
+                    "ALTER TABLE {} {} {} {};",
+                    db_type.quote_table_name(table_name),
+                    add,
                     db_type.quote_identifier(col_name),
                     col_def
                 ),
+                body_diff: String::new(),
             });
         }
     }
@@ -110,84 +944,425 @@ fn compare_tables(
     // Find removed columns
     for col_name in target_cols.keys() {
         if !source_cols.contains_key(col_name) {
+            let mut sql = String::new();
+            if db_type == DbType::SQLServer {
+                // A column with a default constraint cannot be dropped
+                sql.push_str(&mssql_drop_default_sql(table_name, col_name));
+                sql.push('\n');
+            }
+            sql.push_str(&format!(
+                "ALTER TABLE {} DROP COLUMN {};",
+                db_type.quote_table_name(table_name),
+                db_type.quote_identifier(col_name)
+            ));
             results.push(DiffResult {
                 diff_type: DiffType::Modified,
+                kind: ChangeKind::DropColumn,
                 table_name: table_name.to_string(),
                 detail: format!("Drop column: {}", col_name),
-                sql: format!(
-                    "ALTER TABLE {} DROP COLUMN {};",
-                    db_type.quote_identifier(table_name),
-                    db_type.quote_identifier(col_name)
-                ),
+                sql,
+                body_diff: String::new(),
             });
         }
     }
 
     // Find modified columns
-    for (col_name, source_col) in &source_cols {
-        if let Some(target_col) = target_cols.get(col_name) {
-            if !columns_equal(source_col, target_col) {
-                let col_def = build_column_def(source_col);
+    for (source_col, target_col) in modified {
+        let Some(sql) = alter_column_sql(table_name, source_col, target_col, source_db_type, db_type) else {
+            continue;
+        };
+        results.push(DiffResult {
+            diff_type: DiffType::Modified,
+            kind: ChangeKind::ModifyColumn,
+            table_name: table_name.to_string(),
+            detail: format!(
+                "Modify column: {} ({} -> {})",
+                source_col.name, target_col.data_type, source_col.data_type
+            ),
+            sql,
+            body_diff: String::new(),
+        });
+    }
+
+    // Indexes are matched by name
+    for index in &target.indexes {
+        if !source.indexes.iter().any(|i| i.name == index.name) {
+            results.push(DiffResult {
+                diff_type: DiffType::Modified,
+                kind: ChangeKind::DropIndex,
+                table_name: table_name.to_string(),
+                detail: format!("Drop index: {}", index.name),
+                sql: drop_index_sql(&index.name, table_name, db_type),
+                body_diff: String::new(),
+            });
+        }
+    }
+
+    for index in &source.indexes {
+        match target.indexes.iter().find(|i| i.name == index.name) {
+            None => results.push(DiffResult {
+                diff_type: DiffType::Modified,
+                kind: ChangeKind::AddIndex,
+                table_name: table_name.to_string(),
+                detail: format!("Add index: {} ({})", index.name, index.columns.join(", ")),
+                sql: create_index_sql(index, table_name, db_type),
+                body_diff: String::new(),
+            }),
+            Some(existing) if existing.columns != index.columns || existing.is_unique != index.is_unique => {
                 results.push(DiffResult {
                     diff_type: DiffType::Modified,
+                    kind: ChangeKind::ModifyIndex,
                     table_name: table_name.to_string(),
                     detail: format!(
-                        "Modify column: {} ({} -> {})",
-                        col_name, target_col.data_type, source_col.data_type
+                        "Modify index: {} ({}{} -> {}{})",
+                        index.name,
+                        if existing.is_unique { "unique " } else { "" },
+                        existing.columns.join(", "),
+                        if index.is_unique { "unique " } else { "" },
+                        index.columns.join(", ")
                     ),
                     sql: format!(
-                        "ALTER TABLE {} MODIFY COLUMN {} {};",
-                        db_type.quote_identifier(table_name),
-                        db_type.quote_identifier(col_name),
-                        col_def
+                        "{}\n{}",
+                        drop_index_sql(&index.name, table_name, db_type),
+                        create_index_sql(index, table_name, db_type)
                     ),
+                    body_diff: String::new(),
                 });
             }
+            Some(_) => {}
         }
     }
 
+    // Foreign keys are matched by definition, as names differ between engines
+    for fk in removed_fks {
+        let drop = if db_type == DbType::MySQL { "FOREIGN KEY" } else { "CONSTRAINT" };
+        results.push(DiffResult {
+            diff_type: DiffType::Modified,
+            kind: ChangeKind::DropForeignKey,
+            table_name: table_name.to_string(),
+            detail: format!("Drop foreign key: {} ({})", fk.name, fk.columns.join(", ")),
+            sql: format!(
+                "ALTER TABLE {} DROP {} {};",
+                db_type.quote_table_name(table_name),
+                drop,
+                db_type.quote_identifier(&fk.name)
+            ),
+            body_diff: String::new(),
+        });
+    }
+
+    for fk in added_fks {
+        results.push(add_foreign_key_diff(table_name, fk, db_type));
+    }
+
+    for constraint in removed_constraints {
+        let drop = match (db_type, constraint.is_check()) {
+            (DbType::MySQL, true) => "CHECK",
+            (DbType::MySQL, false) => "INDEX",
+            _ => "CONSTRAINT",
+        };
+        results.push(DiffResult {
+            diff_type: DiffType::Modified,
+            kind: ChangeKind::DropConstraint,
+            table_name: table_name.to_string(),
+            detail: format!("Drop constraint: {} {}", constraint.name, constraint.definition()),
+            sql: format!(
+                "ALTER TABLE {} DROP {} {};",
+                db_type.quote_table_name(table_name),
+                drop,
+                db_type.quote_identifier(&constraint.name)
+            ),
+            body_diff: String::new(),
+        });
+    }
+
+    for constraint in added_constraints {
+        results.push(DiffResult {
+            diff_type: DiffType::Modified,
+            kind: ChangeKind::AddConstraint,
+            table_name: table_name.to_string(),
+            detail: format!("Add constraint: {} {}", constraint.name, constraint.definition()),
+            sql: format!(
+                "ALTER TABLE {} ADD {};",
+                db_type.quote_table_name(table_name),
+                constraint_definition(constraint, db_type)
+            ),
+            body_diff: String::new(),
+        });
+    }
+
     results
 }
 
-/// Build column definition string
-fn build_column_def(col: &ColumnInfo) -> String {
-    let mut def = col.data_type.clone();
-    if !col.nullable {
-        def.push_str(" NOT NULL");
+/// Diff adding the foreign key `fk` to an existing table
+fn add_foreign_key_diff(table_name: &str, fk: &ForeignKeyInfo, db_type: DbType) -> DiffResult {
+    DiffResult {
+        diff_type: DiffType::Modified,
+        kind: ChangeKind::AddForeignKey,
+        table_name: table_name.to_string(),
+        detail: format!(
+            "Add foreign key: {} ({}) -> {} ({})",
+            fk.name,
+            fk.columns.join(", "),
+            fk.referenced_table,
+            fk.referenced_columns.join(", ")
+        ),
+        sql: format!(
+            "ALTER TABLE {} ADD {};",
+            db_type.quote_table_name(table_name),
+            foreign_key_definition(fk, db_type)
+        ),
+        body_diff: String::new(),
+    }
+}
+
+/// Build the statement(s) turning `target_col` into `source_col` on an existing table,
+/// or `None` when the differences need no change on the target
+fn alter_column_sql(
+    table_name: &str,
+    source_col: &ColumnInfo,
+    target_col: &ColumnInfo,
+    source_db_type: DbType,
+    db_type: DbType,
+) -> Option<String> {
+    let table = db_type.quote_table_name(table_name);
+    let column = db_type.quote_identifier(&source_col.name);
+    let new_type = map_type(&source_col.data_type, source_db_type, db_type);
+    let type_changed = !types_equivalent(&new_type, &target_col.data_type, db_type);
+    let source_auto = is_auto_increment(source_col, source_db_type);
+    let target_auto = is_auto_increment(target_col, db_type);
+    let new_default = column_default(source_col, source_db_type, db_type);
+    // Auto-increment defaults are managed by the identity/sequence, not compared
+    let default_changed =
+        !(source_auto && target_auto) && new_default != column_default(target_col, db_type, db_type);
+
+    match db_type {
+        DbType::MySQL => Some(format!(
+            "ALTER TABLE {} MODIFY COLUMN {} {};",
+            table,
+            column,
+            column_definition(source_col, source_db_type, db_type)
+        )),
+        DbType::PostgreSQL => {
+            // GENERATED ALWAYS or BY DEFAULT, for PostgreSQL identity columns
+            let identity_kind = |col: &ColumnInfo, db_type: DbType| {
+                let extra = col.extra.to_uppercase();
+                match db_type {
+                    DbType::PostgreSQL if extra.contains("ALWAYS AS IDENTITY") => Some("ALWAYS"),
+                    DbType::PostgreSQL if extra.contains("BY DEFAULT AS IDENTITY") => Some("BY DEFAULT"),
+                    _ => None,
+                }
+            };
+            let source_kind = identity_kind(source_col, source_db_type);
+            let target_kind = identity_kind(target_col, db_type);
+
+            let mut actions = Vec::new();
+            if !source_auto && target_auto {
+                // Before any new default, which an identity column refuses
+                actions.push(format!("ALTER COLUMN {} DROP IDENTITY IF EXISTS", column));
+            }
+            if type_changed {
+                actions.push(format!(
+                    "ALTER COLUMN {} TYPE {} USING {}::{}",
+                    column, new_type, column, new_type
+                ));
+            }
+            if source_col.nullable != target_col.nullable {
+                let action = if source_col.nullable { "DROP" } else { "SET" };
+                actions.push(format!("ALTER COLUMN {} {} NOT NULL", column, action));
+            }
+            if source_auto && !target_auto {
+                // The identity replaces any default of the target column
+                if column_default(target_col, db_type, db_type).is_some() {
+                    actions.push(format!("ALTER COLUMN {} DROP DEFAULT", column));
+                }
+                actions.push(format!(
+                    "ALTER COLUMN {} ADD GENERATED {} AS IDENTITY",
+                    column,
+                    source_kind.unwrap_or("BY DEFAULT")
+                ));
+            } else if default_changed {
+                actions.push(match &new_default {
+                    Some(d) => format!("ALTER COLUMN {} SET DEFAULT {}", column, d),
+                    None => format!("ALTER COLUMN {} DROP DEFAULT", column),
+                });
+            }
+            if let (Some(kind), Some(current)) = (source_kind, target_kind) {
+                if kind != current {
+                    actions.push(format!("ALTER COLUMN {} SET GENERATED {}", column, kind));
+                }
+            }
+            if actions.is_empty() {
+                return None;
+            }
+            Some(format!("ALTER TABLE {} {};", table, actions.join(", ")))
+        }
+        DbType::SQLServer => {
+            let mut statements = Vec::new();
+            if default_changed {
+                // ALTER COLUMN fails while a default constraint is bound to the column
+                statements.push(mssql_drop_default_sql(table_name, &source_col.name));
+            }
+            statements.push(format!(
+                "ALTER TABLE {} ALTER COLUMN {} {} {};",
+                table,
+                column,
+                new_type,
+                if source_col.nullable { "NULL" } else { "NOT NULL" }
+            ));
+            if let (true, Some(d)) = (default_changed, &new_default) {
+                statements.push(format!("ALTER TABLE {} ADD DEFAULT {} FOR {};", table, d, column));
+            }
+            Some(statements.join("\n"))
+        }
+        DbType::SQLite => unreachable!("SQLite columns are modified by rebuilding the table"),
     }
-    if let Some(ref default) = col.default {
-        if is_numeric_or_special(default) {
-            def.push_str(&format!(" DEFAULT {}", default));
+}
+
+/// Drop the system-named default constraint of a SQL Server column, if it has one
+fn mssql_drop_default_sql(table_name: &str, column_name: &str) -> String {
+    let table = DbType::SQLServer.quote_identifier(table_name);
+    format!(
+        "DECLARE @df sysname = (SELECT dc.name FROM sys.default_constraints dc \
+         JOIN sys.columns c ON c.object_id = dc.parent_object_id AND c.column_id = dc.parent_column_id \
+         WHERE dc.parent_object_id = OBJECT_ID('{}') AND c.name = '{}');\n\
+         IF @df IS NOT NULL EXEC('ALTER TABLE {} DROP CONSTRAINT [' + @df + ']');",
+        table.replace('\'', "''"),
+        column_name.replace('\'', "''"),
+        table.replace('\'', "''''")
+    )
+}
+
+/// Rebuild a SQLite table in the shape of `source`: create a new table, copy
+/// the shared columns over, drop the old table and rename the new one.
+/// Added NOT NULL columns without a default are filled with a zero value.
+fn sqlite_rebuild_sql(source: &TableInfo, target: &TableInfo, source_db_type: DbType) -> String {
+    let db_type = DbType::SQLite;
+    let temp_name = format!("_{}_new", target.name);
+    let new_table = TableInfo {
+        name: temp_name.clone(),
+        ..source.clone()
+    };
+
+    let mut columns = Vec::new();
+    let mut values = Vec::new();
+    for column in &source.columns {
+        let quoted = db_type.quote_identifier(&column.name);
+        if target.columns.iter().any(|t| t.name == column.name) {
+            values.push(quoted.clone());
+        } else if !column.nullable
+            && !column.is_primary_key
+            && column_default(column, source_db_type, db_type).is_none()
+        {
+            values.push(sqlite_zero_value(&map_type(&column.data_type, source_db_type, db_type)).to_string());
         } else {
-            def.push_str(&format!(" DEFAULT '{}'", default));
+            continue;
         }
+        columns.push(quoted);
     }
-    if !col.extra.is_empty() {
-        def.push(' ');
-        def.push_str(&col.extra);
+
+    let mut statements = vec![
+        // foreign_keys cannot be toggled inside a transaction, defer the checks instead
+        "PRAGMA defer_foreign_keys = ON;".to_string(),
+        format!("{};", build_create_table(&new_table, source_db_type, db_type)),
+        format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {};",
+            db_type.quote_identifier(&temp_name),
+            columns.join(", "),
+            values.join(", "),
+            db_type.quote_identifier(&target.name)
+        ),
+        format!("DROP TABLE {};", db_type.quote_identifier(&target.name)),
+        // Otherwise the rename fails on views and triggers naming the dropped
+        // table, and rewrites the ones naming the new table
+        "PRAGMA legacy_alter_table = ON;".to_string(),
+        format!(
+            "ALTER TABLE {} RENAME TO {};",
+            db_type.quote_identifier(&temp_name),
+            db_type.quote_identifier(&target.name)
+        ),
+        "PRAGMA legacy_alter_table = OFF;".to_string(),
+    ];
+
+    // Indexes are dropped with the old table; create the source ones
+    statements.extend(source.indexes.iter().map(|index| create_index_sql(index, &target.name, db_type)));
+
+    statements.join("\n")
+}
+
+/// Literal of the zero value for a SQLite column type, by its type affinity
+fn sqlite_zero_value(data_type: &str) -> &'static str {
+    let data_type = data_type.to_uppercase();
+    if data_type.contains("INT") {
+        "0"
+    } else if ["CHAR", "CLOB", "TEXT"].iter().any(|t| data_type.contains(t)) {
+        "''"
+    } else if data_type.contains("BLOB") || data_type.is_empty() {
+        "X''"
+    } else if ["REAL", "FLOA", "DOUB"].iter().any(|t| data_type.contains(t)) {
+        "0.0"
+    } else {
+        "0"
     }
-    def
 }
 
-/// Check if value is numeric or special (doesn't need quotes)
-fn is_numeric_or_special(val: &str) -> bool {
-    let upper = val.to_uppercase();
-    let specials = ["NULL", "CURRENT_TIMESTAMP", "CURRENT_DATE", "CURRENT_TIME", "NOW()", "TRUE", "FALSE"];
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if specials.contains(&upper.as_str()) {
-        return true;
+    #[test]
+    fn line_diff_marks_changed_lines() {
+        assert_eq!(line_diff("a\nb\nc", "a\nx\nc"), "  a\n- b\n+ x\n  c");
+        assert_eq!(line_diff("a\nb", "a\nb\nc"), "  a\n  b\n+ c");
+        // Trailing whitespace is not a change
+        assert_eq!(line_diff("a  \nb", "a\nb"), "");
     }
-    if upper.ends_with("()") || upper.starts_with('(') {
-        return true;
+
+    #[test]
+    fn line_diff_elides_unchanged_stretches() {
+        let old: Vec<String> = (1..=10).map(|i| format!("l{}", i)).collect();
+        let mut new = old.clone();
+        new[1] = "x".to_string();
+        new[8] = "y".to_string();
+        let diff = line_diff(&old.join("\n"), &new.join("\n"));
+        assert_eq!(
+            diff.lines().collect::<Vec<_>>(),
+            ["  l1", "- l2", "+ x", "  l3", "  l4", "...", "  l7", "  l8", "- l9", "+ y", "  l10"]
+        );
+    }
+
+    /// PostgreSQL integer column `id` with the given default and extra
+    fn pg_column(default: Option<&str>, extra: &str) -> ColumnInfo {
+        ColumnInfo {
+            name: "id".to_string(),
+            data_type: "integer".to_string(),
+            nullable: false,
+            default: default.map(String::from),
+            is_primary_key: true,
+            extra: extra.to_string(),
+            position: 1,
+        }
+    }
+
+    #[test]
+    fn alter_column_switches_identity_kind() {
+        let always = pg_column(None, "GENERATED ALWAYS AS IDENTITY");
+        let by_default = pg_column(None, "GENERATED BY DEFAULT AS IDENTITY");
+        assert_eq!(
+            alter_column_sql("t", &always, &by_default, DbType::PostgreSQL, DbType::PostgreSQL).as_deref(),
+            Some("ALTER TABLE \"t\" ALTER COLUMN \"id\" SET GENERATED ALWAYS;")
+        );
+        assert_eq!(
+            alter_column_sql("t", &always, &pg_column(Some("0"), ""), DbType::PostgreSQL, DbType::PostgreSQL).as_deref(),
+            Some("ALTER TABLE \"t\" ALTER COLUMN \"id\" DROP DEFAULT, ALTER COLUMN \"id\" ADD GENERATED ALWAYS AS IDENTITY;")
+        );
     }
-    // Check if numeric
-    val.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '-')
-}
 
-/// Check if two columns are equal
-fn columns_equal(a: &ColumnInfo, b: &ColumnInfo) -> bool {
-    a.data_type == b.data_type
-        && a.nullable == b.nullable
-        && a.default == b.default
-        && a.extra == b.extra
+    #[test]
+    fn alter_column_leaves_serial_and_identity_alone() {
+        let serial = pg_column(Some("nextval('t_id_seq'::regclass)"), "");
+        let identity = pg_column(None, "GENERATED BY DEFAULT AS IDENTITY");
+        assert_eq!(alter_column_sql("t", &serial, &identity, DbType::PostgreSQL, DbType::PostgreSQL), None);
+        assert_eq!(alter_column_sql("t", &identity, &serial, DbType::PostgreSQL, DbType::PostgreSQL), None);
+    }
 }
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{ColumnInfo, CompareOptions, DbConnection, SchemaInfo};
+
+/// Columns a table's name mapping leaves without a counterpart
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnmappedColumns {
+    /// Source columns whose target name is not a column of the target table
+    pub source: Vec<String>,
+    /// Target columns no source column maps to
+    pub target: Vec<String>,
+}
+
+impl UnmappedColumns {
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty() && self.target.is_empty()
+    }
+}
+
+/// Parse a comma-separated list of `source=target` column name pairs
+pub fn parse_column_map(value: &str) -> Result<BTreeMap<String, String>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((source, target)) if !source.trim().is_empty() && !target.trim().is_empty() => {
+                Ok((source.trim().to_string(), target.trim().to_string()))
+            }
+            _ => Err(anyhow::anyhow!("Invalid column mapping '{}', expected <SOURCE>=<TARGET>", pair)),
+        })
+        .collect()
+}
+
+/// Match the columns of a source table to those of its target table,
+/// renaming source columns as `options` maps them
+pub fn unmapped_columns(
+    source_columns: &[String],
+    target_columns: &[String],
+    options: &CompareOptions,
+) -> UnmappedColumns {
+    let mapped: Vec<&str> = source_columns.iter().map(|c| options.target_column(c)).collect();
+    UnmappedColumns {
+        source: source_columns
+            .iter()
+            .zip(&mapped)
+            .filter(|(_, name)| !target_columns.iter().any(|t| t == *name))
+            .map(|(c, _)| c.clone())
+            .collect(),
+        target: target_columns
+            .iter()
+            .filter(|t| !mapped.contains(&t.as_str()))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Check which columns of source table `table_name` and its target table
+/// the name mapping of `options` leaves unmatched. A table missing in the
+/// target leaves every source column unmatched.
+pub async fn check_mapping(
+    source: &DbConnection,
+    target: &DbConnection,
+    table_name: &str,
+    options: &CompareOptions,
+) -> Result<UnmappedColumns> {
+    let names = |columns: Vec<ColumnInfo>| -> Vec<String> { columns.into_iter().map(|c| c.name).collect() };
+    let source_columns = names(source.get_columns(table_name).await?);
+    let target_columns = names(target.get_columns(options.target_table(table_name)).await?);
+    Ok(unmapped_columns(&source_columns, &target_columns, options))
+}
+
+/// Whether `options` names the table or any of its columns differently in the target
+fn renames(options: Option<&CompareOptions>) -> bool {
+    options.is_some_and(|o| !o.target_table.is_empty() || !o.column_map.is_empty())
+}
+
+/// Rename the tables and columns of a source schema to their names in the
+/// target, as mapped by the compare settings of each table, so the schema
+/// can be compared with the target schema directly
+pub fn map_schema(schema: &SchemaInfo, options: &HashMap<String, CompareOptions>) -> SchemaInfo {
+    let default = CompareOptions::default();
+    let options_of = |table: &str| options.get(table).unwrap_or(&default);
+    let map_columns = |options: &CompareOptions, columns: &[String]| -> Vec<String> {
+        columns.iter().map(|c| options.target_column(c).to_string()).collect()
+    };
+
+    let mut mapped = schema.clone();
+    for table in &mut mapped.tables {
+        let table_options = options_of(&table.name);
+        // The saved DDL still uses the source names
+        if renames(options.get(&table.name))
+            || table.foreign_keys.iter().any(|fk| renames(options.get(&fk.referenced_table)))
+        {
+            table.create_sql.clear();
+        }
+
+        for column in &mut table.columns {
+            column.name = table_options.target_column(&column.name).to_string();
+        }
+        for index in &mut table.indexes {
+            index.columns = map_columns(table_options, &index.columns);
+        }
+        for fk in &mut table.foreign_keys {
+            let referenced = options_of(&fk.referenced_table);
+            fk.columns = map_columns(table_options, &fk.columns);
+            fk.referenced_columns = map_columns(referenced, &fk.referenced_columns);
+            fk.referenced_table = referenced.target_table(&fk.referenced_table).to_string();
+        }
+        table.name = table_options.target_table(&table.name).to_string();
+    }
+    mapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_column_map_reads_pairs() {
+        let map = parse_column_map(" email = mail, full_name=name ,").unwrap();
+        assert_eq!(
+            map,
+            BTreeMap::from([
+                ("email".to_string(), "mail".to_string()),
+                ("full_name".to_string(), "name".to_string()),
+            ])
+        );
+        assert!(parse_column_map("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_column_map_rejects_incomplete_pairs() {
+        assert!(parse_column_map("email").is_err());
+        assert!(parse_column_map("email=").is_err());
+        assert!(parse_column_map("a=b, =c").is_err());
+    }
+
+    #[test]
+    fn unmapped_columns_compares_under_the_mapped_names() {
+        let options = CompareOptions {
+            column_map: parse_column_map("email=mail").unwrap(),
+            ..Default::default()
+        };
+        let names = |columns: &[&str]| -> Vec<String> { columns.iter().map(|c| c.to_string()).collect() };
+        let unmapped = unmapped_columns(&names(&["id", "email", "age"]), &names(&["id", "mail", "born"]), &options);
+        assert_eq!(unmapped.source, ["age"]);
+        assert_eq!(unmapped.target, ["born"]);
+    }
+}
@@ -2,8 +2,29 @@ mod connection;
 mod schema;
 mod diff;
 mod sync;
+mod checksum;
+mod apply;
+mod dialect;
+mod plan;
+mod value;
+mod settings;
+mod mapping;
+mod statements;
+mod copy;
+mod summary;
+mod watermark;
 
 pub use connection::*;
 pub use schema::*;
 pub use diff::*;
 pub use sync::*;
+pub use checksum::*;
+pub use apply::*;
+pub use plan::*;
+pub use value::*;
+pub use settings::*;
+pub use mapping::*;
+pub use statements::*;
+pub use copy::*;
+pub use summary::*;
+pub use watermark::*;
@@ -0,0 +1,499 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::apply::adds_enum_label;
+use super::{ChangeKind, DbType, DiffResult, SchemaInfo};
+
+/// Position of a change in the migration: triggers, views, routines and
+/// constraints that could block other changes are dropped first, sequences
+/// and types are created before the columns using them, existing tables are
+/// altered before new tables whose foreign keys may need their unique keys,
+/// columns, tables and then the types they used are dropped late, and
+/// routines, then views, then triggers are created once the tables they use
+/// are in their final shape
+fn phase(kind: ChangeKind) -> u8 {
+    match kind {
+        ChangeKind::DropTrigger => 0,
+        ChangeKind::DropView => 1,
+        ChangeKind::DropRoutine => 2,
+        ChangeKind::DropForeignKey => 3,
+        // Foreign keys may rely on a UNIQUE constraint
+        ChangeKind::DropIndex | ChangeKind::DropConstraint => 4,
+        ChangeKind::CreateSequence | ChangeKind::CreateType => 5,
+        ChangeKind::AlterType | ChangeKind::CreateDomain => 6,
+        ChangeKind::AlterDomain => 7,
+        ChangeKind::AddColumn => 8,
+        ChangeKind::ModifyColumn => 9,
+        ChangeKind::AddConstraint | ChangeKind::AddIndex | ChangeKind::ModifyIndex => 10,
+        ChangeKind::CreateTable => 11,
+        // SQLite checks the foreign keys of the copied rows against their parent tables
+        ChangeKind::RebuildTable => 12,
+        // Owners are columns, so they must exist
+        ChangeKind::AlterSequence => 13,
+        ChangeKind::AddForeignKey => 14,
+        ChangeKind::DropColumn => 15,
+        ChangeKind::DropTable => 16,
+        ChangeKind::DropSequence | ChangeKind::DropDomain => 17,
+        // Domains may be over enums
+        ChangeKind::DropType => 18,
+        ChangeKind::CreateRoutine | ChangeKind::ReplaceRoutine => 19,
+        ChangeKind::CreateView | ChangeKind::ReplaceView => 20,
+        ChangeKind::CreateTrigger | ChangeKind::ReplaceTrigger => 21,
+    }
+}
+
+/// Tables each table of `schema` references by its foreign keys
+fn foreign_key_references(schema: &SchemaInfo) -> HashMap<&str, BTreeSet<&str>> {
+    schema
+        .tables
+        .iter()
+        .map(|t| {
+            let parents = t.foreign_keys.iter().map(|fk| fk.referenced_table.as_str()).collect();
+            (t.name.as_str(), parents)
+        })
+        .collect()
+}
+
+/// Sort `tables` so that every table comes after the tables it references.
+/// Tables in a reference cycle keep their name order at the end.
+fn parents_first(tables: &BTreeSet<String>, schema: &SchemaInfo) -> Vec<String> {
+    dependency_order(tables, &foreign_key_references(schema))
+}
+
+/// Tables of `tables` that cannot be created after the tables they reference,
+/// because they are in a foreign key cycle or reference a table that is
+pub(super) fn cyclic_tables(tables: &BTreeSet<String>, schema: &SchemaInfo) -> BTreeSet<String> {
+    split_by_references(tables, &foreign_key_references(schema)).1.into_iter().collect()
+}
+
+/// Lowercased words of a view definition, keeping `schema.name` together
+fn definition_words(definition: &str) -> BTreeSet<String> {
+    definition
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Views of `schema` selecting from any of `tables`, directly or through
+/// other views. A view is taken to select from every table or view whose
+/// name appears as a word of its definition.
+pub(super) fn dependent_views(schema: &SchemaInfo, tables: &[&str]) -> BTreeSet<String> {
+    let mut names: BTreeSet<String> = tables.iter().map(|t| t.to_lowercase()).collect();
+    let mut dependent = BTreeSet::new();
+    loop {
+        let found: Vec<&str> = schema
+            .views
+            .iter()
+            .filter(|v| !dependent.contains(&v.name))
+            .filter(|v| definition_words(&v.definition).iter().any(|w| names.contains(w)))
+            .map(|v| v.name.as_str())
+            .collect();
+        if found.is_empty() {
+            break;
+        }
+        for name in found {
+            names.insert(name.to_lowercase());
+            dependent.insert(name.to_string());
+        }
+    }
+    dependent
+}
+
+/// Sort `views` so that every view comes after the views it selects from.
+/// A view is taken to select from every view whose name appears as a word
+/// of its definition.
+fn views_first(views: &BTreeSet<String>, schema: &SchemaInfo) -> Vec<String> {
+    let references: HashMap<&str, BTreeSet<&str>> = schema
+        .views
+        .iter()
+        .map(|v| {
+            let words = definition_words(&v.definition);
+            let parents = schema
+                .views
+                .iter()
+                .map(|p| p.name.as_str())
+                .filter(|p| words.contains(&p.to_lowercase()))
+                .collect();
+            (v.name.as_str(), parents)
+        })
+        .collect();
+    dependency_order(views, &references)
+}
+
+/// Sort `tables` so that every table comes after the tables it references,
+/// `references` holding the tables each table references. References to
+/// the table itself or to tables outside `tables` are ignored.
+/// Tables in a reference cycle keep their name order at the end.
+pub(super) fn dependency_order(tables: &BTreeSet<String>, references: &HashMap<&str, BTreeSet<&str>>) -> Vec<String> {
+    let (mut ordered, rest) = split_by_references(tables, references);
+    ordered.extend(rest);
+    ordered
+}
+
+/// Split `tables` into the tables `dependency_order` can sort, in that order,
+/// and the tables in or depending on a reference cycle, in name order
+fn split_by_references(
+    tables: &BTreeSet<String>,
+    references: &HashMap<&str, BTreeSet<&str>>,
+) -> (Vec<String>, Vec<String>) {
+    let references: HashMap<&str, Vec<&str>> = references
+        .iter()
+        .filter(|(t, _)| tables.contains(**t))
+        .map(|(t, parents)| {
+            let parents = parents.iter().copied().filter(|p| p != t && tables.contains(*p)).collect();
+            (*t, parents)
+        })
+        .collect();
+
+    let mut ordered: Vec<String> = Vec::with_capacity(tables.len());
+    let mut remaining: Vec<&str> = tables.iter().map(|t| t.as_str()).collect();
+    loop {
+        let ready: Vec<&str> = remaining
+            .iter()
+            .copied()
+            .filter(|t| {
+                references
+                    .get(t)
+                    .map(|parents| parents.iter().all(|p| ordered.iter().any(|o| o == p)))
+                    .unwrap_or(true)
+            })
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+        remaining.retain(|t| !ready.contains(t));
+        ordered.extend(ready.into_iter().map(String::from));
+    }
+    (ordered, remaining.into_iter().map(String::from).collect())
+}
+
+/// Order schema diffs into a migration that can be applied top to bottom.
+///
+/// New tables are created parents first (by the source foreign keys) and
+/// dropped tables are dropped children first (by the target foreign keys).
+/// Views are likewise created after and dropped before the views they
+/// select from.
+pub fn order_migration(diffs: &mut [DiffResult], source: &SchemaInfo, target: &SchemaInfo) {
+    let created: BTreeSet<String> = diffs
+        .iter()
+        .filter(|d| d.kind == ChangeKind::CreateTable)
+        .map(|d| d.table_name.clone())
+        .collect();
+    let dropped: BTreeSet<String> = diffs
+        .iter()
+        .filter(|d| d.kind == ChangeKind::DropTable)
+        .map(|d| d.table_name.clone())
+        .collect();
+
+    let create_order: HashMap<String, usize> = parents_first(&created, source)
+        .into_iter()
+        .enumerate()
+        .map(|(i, t)| (t, i))
+        .collect();
+    let drop_order: HashMap<String, usize> = parents_first(&dropped, target)
+        .into_iter()
+        .rev()
+        .enumerate()
+        .map(|(i, t)| (t, i))
+        .collect();
+
+    let created_views: BTreeSet<String> = diffs
+        .iter()
+        .filter(|d| matches!(d.kind, ChangeKind::CreateView | ChangeKind::ReplaceView))
+        .map(|d| d.table_name.clone())
+        .collect();
+    let dropped_views: BTreeSet<String> = diffs
+        .iter()
+        .filter(|d| d.kind == ChangeKind::DropView)
+        .map(|d| d.table_name.clone())
+        .collect();
+    let create_view_order: HashMap<String, usize> = views_first(&created_views, source)
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| (v, i))
+        .collect();
+    let drop_view_order: HashMap<String, usize> = views_first(&dropped_views, target)
+        .into_iter()
+        .rev()
+        .enumerate()
+        .map(|(i, v)| (v, i))
+        .collect();
+
+    let table_order = |d: &DiffResult| match d.kind {
+        ChangeKind::CreateTable => create_order.get(&d.table_name).copied().unwrap_or(0),
+        ChangeKind::DropTable => drop_order.get(&d.table_name).copied().unwrap_or(0),
+        ChangeKind::CreateView | ChangeKind::ReplaceView => {
+            create_view_order.get(&d.table_name).copied().unwrap_or(0)
+        }
+        ChangeKind::DropView => drop_view_order.get(&d.table_name).copied().unwrap_or(0),
+        _ => 0,
+    };
+
+    diffs.sort_by(|a, b| {
+        phase(a.kind)
+            .cmp(&phase(b.kind))
+            .then_with(|| table_order(a).cmp(&table_order(b)))
+            .then_with(|| a.table_name.cmp(&b.table_name))
+            .then_with(|| a.detail.cmp(&b.detail))
+    });
+}
+
+/// Render ordered diffs as a single migration script for the target database
+pub fn migration_script(diffs: &[DiffResult], db_type: DbType) -> String {
+    let mut script = format!(
+        "-- SyncForge migration script\n-- Target: {:?}\n-- Changes: {}\n\n",
+        db_type,
+        diffs.len()
+    );
+
+    // MySQL commits implicitly on DDL, so a transaction would be misleading there.
+    // On SQL Server XACT_ABORT rolls the transaction back on an error, but the
+    // client goes on with the next batch, so every batch is followed by a guard
+    // that stops executing the rest of the script once the transaction is gone.
+    let (begin, commit) = match db_type {
+        DbType::MySQL => ("", ""),
+        DbType::SQLServer => (
+            "SET XACT_ABORT ON;\nBEGIN TRANSACTION;\nGO\n\n",
+            "COMMIT TRANSACTION;\nGO\nSET NOEXEC OFF;\nGO\n",
+        ),
+        DbType::PostgreSQL | DbType::SQLite => ("BEGIN;\n\n", "COMMIT;\n"),
+    };
+
+    // PostgreSQL cannot use an enum label added in the same transaction
+    let label_commit = match db_type {
+        DbType::PostgreSQL => diffs.iter().rposition(|d| adds_enum_label(&d.sql)),
+        _ => None,
+    };
+
+    script.push_str(begin);
+    for (i, diff) in diffs.iter().enumerate() {
+        script.push_str(&format!("-- {}: {}\n", diff.table_name, diff.detail));
+        let has_body = matches!(
+            diff.kind,
+            ChangeKind::CreateRoutine
+                | ChangeKind::ReplaceRoutine
+                | ChangeKind::CreateTrigger
+                | ChangeKind::ReplaceTrigger
+        );
+        if db_type == DbType::MySQL && has_body {
+            script.push_str(&mysql_delimited(&diff.sql));
+        } else {
+            script.push_str(&diff.sql);
+        }
+        script.push('\n');
+        // Separate batches so each step's variables are scoped to it
+        if db_type == DbType::SQLServer {
+            script.push_str("GO\nIF @@TRANCOUNT = 0 SET NOEXEC ON;\nGO\n");
+        }
+        script.push('\n');
+        if label_commit == Some(i) && i + 1 < diffs.len() {
+            script.push_str("-- Commit the new enum labels before they are used\nCOMMIT;\nBEGIN;\n\n");
+        }
+    }
+    script.push_str(commit);
+    script
+}
+
+/// Wrap the definition of a MySQL routine or trigger in `DELIMITER`, so the
+/// mysql client sends the body whole rather than splitting it at its inner
+/// semicolons. A leading `DROP .. IF EXISTS` line is kept outside.
+fn mysql_delimited(sql: &str) -> String {
+    let (drop, definition) = match sql.split_once('\n') {
+        Some((first, rest)) if first.starts_with("DROP ") => (format!("{}\n", first), rest),
+        _ => (String::new(), sql),
+    };
+    format!(
+        "{}DELIMITER $$\n{}$$\nDELIMITER ;",
+        drop,
+        definition.trim_end().trim_end_matches(';')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{compare_schemas, DiffType, ForeignKeyInfo, TableInfo, ViewInfo};
+
+    /// Schema of `tables`, each with the tables its foreign keys reference, and `views`
+    fn schema(tables: &[(&str, &[&str])], views: &[(&str, &str)]) -> SchemaInfo {
+        SchemaInfo {
+            database: String::new(),
+            db_type: DbType::PostgreSQL,
+            schemas: Vec::new(),
+            tables: tables
+                .iter()
+                .map(|(name, parents)| TableInfo {
+                    name: name.to_string(),
+                    columns: Vec::new(),
+                    indexes: Vec::new(),
+                    foreign_keys: parents
+                        .iter()
+                        .map(|parent| ForeignKeyInfo {
+                            name: format!("fk_{}_{}", name, parent),
+                            columns: vec![format!("{}_id", parent)],
+                            referenced_table: parent.to_string(),
+                            referenced_columns: vec!["id".to_string()],
+                            on_delete: "NO ACTION".to_string(),
+                            on_update: "NO ACTION".to_string(),
+                        })
+                        .collect(),
+                    constraints: Vec::new(),
+                    create_sql: String::new(),
+                })
+                .collect(),
+            views: views
+                .iter()
+                .map(|(name, definition)| ViewInfo {
+                    name: name.to_string(),
+                    definition: definition.to_string(),
+                })
+                .collect(),
+            routines: Vec::new(),
+            triggers: Vec::new(),
+            sequences: Vec::new(),
+            enums: Vec::new(),
+            domains: Vec::new(),
+        }
+    }
+
+    fn diff(kind: ChangeKind, name: &str) -> DiffResult {
+        DiffResult {
+            diff_type: DiffType::Modified,
+            kind,
+            table_name: name.to_string(),
+            detail: format!("{:?}", kind),
+            sql: format!("-- {:?} {}", kind, name),
+            body_diff: String::new(),
+        }
+    }
+
+    fn names(diffs: &[DiffResult]) -> Vec<String> {
+        diffs.iter().map(|d| format!("{:?} {}", d.kind, d.table_name)).collect()
+    }
+
+    #[test]
+    fn dependency_order_puts_parents_first() {
+        let tables: BTreeSet<String> = ["items", "orders", "customers", "notes"].iter().map(|t| t.to_string()).collect();
+        let references = HashMap::from([
+            ("items", BTreeSet::from(["orders"])),
+            ("orders", BTreeSet::from(["customers", "orders"])),
+            // Tables outside the set are ignored
+            ("notes", BTreeSet::from(["users"])),
+        ]);
+        assert_eq!(dependency_order(&tables, &references), ["customers", "notes", "orders", "items"]);
+    }
+
+    #[test]
+    fn dependency_order_keeps_cycles_at_the_end() {
+        let tables: BTreeSet<String> = ["a", "b", "c"].iter().map(|t| t.to_string()).collect();
+        let references = HashMap::from([("b", BTreeSet::from(["a"])), ("a", BTreeSet::from(["b"]))]);
+        assert_eq!(dependency_order(&tables, &references), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn order_migration_creates_parents_and_drops_children_first() {
+        let source = schema(&[("customers", &[]), ("orders", &["customers"])], &[]);
+        let target = schema(&[("old_parent", &[]), ("old_child", &["old_parent"])], &[]);
+        let mut diffs = vec![
+            diff(ChangeKind::CreateTable, "orders"),
+            diff(ChangeKind::DropTable, "old_parent"),
+            diff(ChangeKind::CreateTable, "customers"),
+            diff(ChangeKind::DropTable, "old_child"),
+            diff(ChangeKind::AddForeignKey, "orders"),
+        ];
+        order_migration(&mut diffs, &source, &target);
+        assert_eq!(
+            names(&diffs),
+            [
+                "CreateTable customers",
+                "CreateTable orders",
+                "AddForeignKey orders",
+                "DropTable old_child",
+                "DropTable old_parent",
+            ]
+        );
+    }
+
+    #[test]
+    fn tables_in_a_cycle_get_their_foreign_keys_after_all_creates() {
+        let source = schema(&[("a", &["b"]), ("b", &["a"]), ("c", &["a"]), ("d", &[])], &[]);
+        let target = schema(&[], &[]);
+        let tables: BTreeSet<String> = ["a", "b", "c", "d"].iter().map(|t| t.to_string()).collect();
+        let cyclic: Vec<String> = cyclic_tables(&tables, &source).into_iter().collect();
+        assert_eq!(cyclic, ["a", "b", "c"]);
+
+        let diffs = compare_schemas(&source, &target, DbType::PostgreSQL);
+        assert_eq!(
+            names(&diffs),
+            [
+                "CreateTable d",
+                "CreateTable a",
+                "CreateTable b",
+                "CreateTable c",
+                "AddForeignKey a",
+                "AddForeignKey b",
+                "AddForeignKey c",
+            ]
+        );
+        assert!(diffs.iter().filter(|d| d.kind == ChangeKind::CreateTable).all(|d| !d.sql.contains("REFERENCES")));
+        assert_eq!(
+            diffs[4].sql,
+            "ALTER TABLE \"a\" ADD CONSTRAINT \"fk_a_b\" FOREIGN KEY (\"b_id\") REFERENCES \"b\" (\"id\");"
+        );
+    }
+
+    #[test]
+    fn order_migration_drops_views_before_and_creates_them_after_table_changes() {
+        let views = [("active", "SELECT * FROM customers WHERE active"), ("recent", "SELECT * FROM active")];
+        let source = schema(&[("customers", &[])], &views);
+        let target = schema(&[("customers", &[])], &views);
+        let mut diffs = vec![
+            diff(ChangeKind::CreateView, "recent"),
+            diff(ChangeKind::CreateView, "active"),
+            diff(ChangeKind::ModifyColumn, "customers"),
+            diff(ChangeKind::DropView, "active"),
+            diff(ChangeKind::DropView, "recent"),
+        ];
+        order_migration(&mut diffs, &source, &target);
+        assert_eq!(
+            names(&diffs),
+            [
+                "DropView recent",
+                "DropView active",
+                "ModifyColumn customers",
+                "CreateView active",
+                "CreateView recent",
+            ]
+        );
+    }
+
+    #[test]
+    fn dependent_views_follow_views_over_views() {
+        let target = schema(
+            &[("customers", &[]), ("orders", &[])],
+            &[
+                ("active", "SELECT * FROM Customers WHERE active"),
+                ("recent", "SELECT * FROM active"),
+                ("totals", "SELECT sum(total) FROM orders"),
+            ],
+        );
+        let dependent: Vec<String> = dependent_views(&target, &["customers"]).into_iter().collect();
+        assert_eq!(dependent, ["active", "recent"]);
+    }
+
+    #[test]
+    fn sql_server_script_stops_after_a_failed_batch() {
+        let script = migration_script(&[diff(ChangeKind::AddColumn, "t")], DbType::SQLServer);
+        assert!(script.contains("-- AddColumn t\nGO\nIF @@TRANCOUNT = 0 SET NOEXEC ON;\nGO\n"));
+        assert!(script.ends_with("COMMIT TRANSACTION;\nGO\nSET NOEXEC OFF;\nGO\n"));
+    }
+
+    #[test]
+    fn mysql_script_delimits_routine_bodies() {
+        let mut routine = diff(ChangeKind::ReplaceRoutine, "f");
+        routine.sql = "DROP FUNCTION IF EXISTS `f`;\nCREATE FUNCTION `f`() RETURNS int\nBEGIN\n  RETURN 1;\nEND;".to_string();
+        let script = migration_script(&[routine], DbType::MySQL);
+        assert!(script.contains(
+            "DROP FUNCTION IF EXISTS `f`;\nDELIMITER $$\nCREATE FUNCTION `f`() RETURNS int\nBEGIN\n  RETURN 1;\nEND$$\nDELIMITER ;\n"
+        ));
+    }
+}
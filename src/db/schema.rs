@@ -1,7 +1,9 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use super::DbConnection;
+use super::connection::mssql_query;
+use super::dialect::strip_outer_parens;
+use super::{DbConnection, DbType};
 
 /// Column information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,20 +25,163 @@ pub struct IndexInfo {
     pub is_unique: bool,
 }
 
+/// Foreign key information
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForeignKeyInfo {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    /// Referential action, e.g. "CASCADE", "SET NULL", "NO ACTION"
+    pub on_delete: String,
+    pub on_update: String,
+}
+
+/// CHECK or UNIQUE table constraint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintInfo {
+    pub name: String,
+    /// "CHECK" or "UNIQUE"
+    pub kind: String,
+    /// Columns of a UNIQUE constraint
+    pub columns: Vec<String>,
+    /// Expression of a CHECK constraint, without the CHECK keyword
+    pub check_clause: String,
+}
+
+impl ConstraintInfo {
+    /// Whether this is a CHECK constraint
+    pub fn is_check(&self) -> bool {
+        self.kind == "CHECK"
+    }
+
+    /// The constraint as written in a table definition, without its name
+    pub fn definition(&self) -> String {
+        if self.is_check() {
+            format!("CHECK ({})", self.check_clause)
+        } else {
+            format!("UNIQUE ({})", self.columns.join(", "))
+        }
+    }
+}
+
+/// Row of `pragma_foreign_key_list`: (id, seq, table, from, to, on_update, on_delete)
+type SqliteForeignKeyRow = (i64, i64, String, String, Option<String>, String, String);
+
 /// Table information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableInfo {
     pub name: String,
     pub columns: Vec<ColumnInfo>,
     pub indexes: Vec<IndexInfo>,
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+    /// CHECK and UNIQUE constraints; UNIQUE constraints are unique indexes on MySQL
+    #[serde(default)]
+    pub constraints: Vec<ConstraintInfo>,
     pub create_sql: String,
 }
 
+/// View information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewInfo {
+    pub name: String,
+    /// The query of the view, without the `CREATE VIEW .. AS` head
+    pub definition: String,
+}
+
+/// Stored procedure or function information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutineInfo {
+    pub name: String,
+    /// "PROCEDURE" or "FUNCTION"
+    pub kind: String,
+    /// Argument types identifying an overload, PostgreSQL only
+    #[serde(default)]
+    pub arguments: String,
+    /// The full CREATE statement
+    pub definition: String,
+}
+
+impl RoutineInfo {
+    /// Name identifying the routine, with its argument types on PostgreSQL
+    pub fn signature(&self) -> String {
+        if self.arguments.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}({})", self.name, self.arguments)
+        }
+    }
+}
+
+/// Trigger information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerInfo {
+    pub name: String,
+    pub table_name: String,
+    /// The full CREATE TRIGGER statement
+    pub definition: String,
+}
+
+/// PostgreSQL sequence information
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequenceInfo {
+    pub name: String,
+    pub data_type: String,
+    pub start: i64,
+    pub increment: i64,
+    pub min_value: i64,
+    pub max_value: i64,
+    pub cycle: bool,
+    /// Last value handed out, `None` before the first `nextval`
+    pub last_value: Option<i64>,
+    /// Table and column owning the sequence, as for a `serial` column
+    pub owned_by: Option<(String, String)>,
+}
+
+/// PostgreSQL enum type information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnumInfo {
+    pub name: String,
+    /// Labels in sort order
+    pub labels: Vec<String>,
+}
+
+/// PostgreSQL domain information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainInfo {
+    pub name: String,
+    pub base_type: String,
+    pub nullable: bool,
+    pub default: Option<String>,
+    /// CHECK constraints as (name, definition)
+    pub checks: Vec<(String, String)>,
+}
+
 /// Database schema
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchemaInfo {
     pub database: String,
+    #[serde(default)]
+    pub db_type: DbType,
+    /// PostgreSQL schemas the tables were read from, in search order. Tables
+    /// of the first one have bare names, the others `schema.table` names.
+    #[serde(default)]
+    pub schemas: Vec<String>,
     pub tables: Vec<TableInfo>,
+    #[serde(default)]
+    pub views: Vec<ViewInfo>,
+    #[serde(default)]
+    pub routines: Vec<RoutineInfo>,
+    #[serde(default)]
+    pub triggers: Vec<TriggerInfo>,
+    /// Sequences not backing an identity column, PostgreSQL only
+    #[serde(default)]
+    pub sequences: Vec<SequenceInfo>,
+    #[serde(default)]
+    pub enums: Vec<EnumInfo>,
+    #[serde(default)]
+    pub domains: Vec<DomainInfo>,
 }
 
 impl DbConnection {
@@ -63,9 +208,17 @@ impl DbConnection {
             DbConnection::SQLite(_) => {
                 Ok(vec!["main".to_string()])
             }
-            DbConnection::SQLServer(_) => {
-                // SQL Server needs mutable access for queries
-                Ok(Vec::new())
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    "SELECT name FROM sys.databases WHERE database_id > 4 ORDER BY name",
+                    &[],
+                )
+                .await?;
+                Ok(rows
+                    .iter()
+                    .filter_map(|r| r.get::<&str, _>(0).map(|s| s.to_string()))
+                    .collect())
             }
         }
     }
@@ -80,8 +233,13 @@ impl DbConnection {
                 Ok(rows.into_iter().map(|r| r.0).collect())
             }
             DbConnection::PostgreSQL(pool) => {
+                // Tables of the first schema in the search path keep their bare name
                 let rows: Vec<(String,)> = sqlx::query_as(
-                    "SELECT tablename FROM pg_tables WHERE schemaname = 'public'"
+                    r#"SELECT CASE WHEN schemaname = current_schema() THEN tablename::text
+                                   ELSE schemaname || '.' || tablename END
+                       FROM pg_tables
+                       WHERE schemaname = ANY(current_schemas(false))
+                       ORDER BY array_position(current_schemas(false), schemaname::text), tablename"#
                 )
                 .fetch_all(pool)
                 .await?;
@@ -95,8 +253,19 @@ impl DbConnection {
                 .await?;
                 Ok(rows.into_iter().map(|r| r.0).collect())
             }
-            DbConnection::SQLServer(_) => {
-                Ok(Vec::new())
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    r#"SELECT TABLE_NAME FROM INFORMATION_SCHEMA.TABLES
+                       WHERE TABLE_TYPE = 'BASE TABLE' AND TABLE_SCHEMA = SCHEMA_NAME()
+                       ORDER BY TABLE_NAME"#,
+                    &[],
+                )
+                .await?;
+                Ok(rows
+                    .iter()
+                    .filter_map(|r| r.get::<&str, _>(0).map(|s| s.to_string()))
+                    .collect())
             }
         }
     }
@@ -139,13 +308,22 @@ impl DbConnection {
                     .collect())
             }
             DbConnection::PostgreSQL(pool) => {
-                let rows: Vec<(String, String, String, Option<String>, i64)> = sqlx::query_as(
-                    r#"SELECT column_name, data_type, is_nullable, column_default, ordinal_position::bigint
-                       FROM information_schema.columns
-                       WHERE table_schema = 'public' AND table_name = $1
-                       ORDER BY ordinal_position"#
+                // format_type gives the full type including length/precision
+                // Identity columns carry their clause in `extra`, as written in a column definition
+                let rows: Vec<(String, String, String, Option<String>, i64, String)> = sqlx::query_as(
+                    r#"SELECT c.column_name, pg_catalog.format_type(a.atttypid, a.atttypmod),
+                              CASE WHEN a.attnotnull THEN 'NO' ELSE 'YES' END,
+                              c.column_default, c.ordinal_position::bigint,
+                              CASE a.attidentity WHEN 'a' THEN 'GENERATED ALWAYS AS IDENTITY'
+                                                 WHEN 'd' THEN 'GENERATED BY DEFAULT AS IDENTITY' ELSE '' END
+                       FROM information_schema.columns c
+                       JOIN pg_catalog.pg_class t ON t.oid = $1::regclass
+                       JOIN pg_catalog.pg_namespace n ON n.oid = t.relnamespace
+                       JOIN pg_catalog.pg_attribute a ON a.attrelid = t.oid AND a.attname = c.column_name
+                       WHERE c.table_schema = n.nspname AND c.table_name = t.relname
+                       ORDER BY c.ordinal_position"#
                 )
-                .bind(table_name)
+                .bind(pg_relation(table_name))
                 .fetch_all(pool)
                 .await?;
 
@@ -156,7 +334,7 @@ impl DbConnection {
                        JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
                        WHERE i.indrelid = $1::regclass AND i.indisprimary"#
                 )
-                .bind(table_name)
+                .bind(pg_relation(table_name))
                 .fetch_all(pool)
                 .await
                 .unwrap_or_default();
@@ -164,13 +342,13 @@ impl DbConnection {
 
                 Ok(rows
                     .into_iter()
-                    .map(|(name, data_type, nullable, default, position)| ColumnInfo {
+                    .map(|(name, data_type, nullable, default, position, extra)| ColumnInfo {
                         is_primary_key: primary_keys.contains(&name),
                         name,
                         data_type,
                         nullable: nullable == "YES",
                         default,
-                        extra: String::new(),
+                        extra,
                         position: position as u64,
                     })
                     .collect())
@@ -195,90 +373,777 @@ impl DbConnection {
                     })
                     .collect())
             }
-            DbConnection::SQLServer(_) => {
-                Ok(Vec::new())
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    r#"SELECT COLUMN_NAME, DATA_TYPE,
+                              CAST(CHARACTER_MAXIMUM_LENGTH AS INT),
+                              CAST(NUMERIC_PRECISION AS INT),
+                              CAST(NUMERIC_SCALE AS INT),
+                              IS_NULLABLE, COLUMN_DEFAULT,
+                              CAST(ORDINAL_POSITION AS INT),
+                              CAST(COLUMNPROPERTY(OBJECT_ID(QUOTENAME(TABLE_SCHEMA) + '.' + QUOTENAME(TABLE_NAME)),
+                                                  COLUMN_NAME, 'IsIdentity') AS INT)
+                       FROM INFORMATION_SCHEMA.COLUMNS
+                       WHERE TABLE_SCHEMA = SCHEMA_NAME() AND TABLE_NAME = @P1
+                       ORDER BY ORDINAL_POSITION"#,
+                    &[&table_name],
+                )
+                .await?;
+
+                let primary_keys = self.get_primary_keys(table_name, "").await?;
+
+                Ok(rows
+                    .iter()
+                    .map(|r| {
+                        let name = r.get::<&str, _>(0).unwrap_or_default().to_string();
+                        let data_type = mssql_column_type(
+                            r.get::<&str, _>(1).unwrap_or_default(),
+                            r.get::<i32, _>(2),
+                            r.get::<i32, _>(3),
+                            r.get::<i32, _>(4),
+                        );
+                        let is_identity = r.get::<i32, _>(8).unwrap_or(0) == 1;
+                        ColumnInfo {
+                            is_primary_key: primary_keys.contains(&name),
+                            name,
+                            data_type,
+                            nullable: r.get::<&str, _>(5) == Some("YES"),
+                            default: r.get::<&str, _>(6).map(|s| s.to_string()),
+                            extra: if is_identity { "IDENTITY".to_string() } else { String::new() },
+                            position: r.get::<i32, _>(7).unwrap_or(0) as u64,
+                        }
+                    })
+                    .collect())
             }
         }
     }
 
-    /// Get table info including columns and indexes
+    /// Get table info including columns, indexes, foreign keys and constraints
     pub async fn get_table_info(&self, table_name: &str) -> Result<TableInfo> {
         let columns = self.get_columns(table_name).await?;
         let indexes = self.get_indexes(table_name).await?;
+        let foreign_keys = self.get_foreign_keys(table_name).await?;
+        let constraints = self.get_constraints(table_name).await?;
         let create_sql = self.get_create_table_sql(table_name).await?;
 
         Ok(TableInfo {
             name: table_name.to_string(),
             columns,
             indexes,
+            foreign_keys,
+            constraints,
             create_sql,
         })
     }
 
-    /// Get indexes for a table
+    /// Get secondary indexes for a table. Indexes backing the primary key or
+    /// a constraint are left out, as they are dropped and created with it.
     pub async fn get_indexes(&self, table_name: &str) -> Result<Vec<IndexInfo>> {
-        match self {
+        // Rows of (index name, column, unique), ordered by index name and column position
+        let rows: Vec<(String, String, bool)> = match self {
+            DbConnection::MySQL(pool) => {
+                let rows: Vec<(String, String, i64)> = sqlx::query_as(
+                    r#"SELECT INDEX_NAME, COLUMN_NAME, CAST(NON_UNIQUE AS SIGNED)
+                       FROM INFORMATION_SCHEMA.STATISTICS
+                       WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?
+                         AND INDEX_NAME <> 'PRIMARY' AND COLUMN_NAME IS NOT NULL
+                       ORDER BY INDEX_NAME, SEQ_IN_INDEX"#
+                )
+                .bind(table_name)
+                .fetch_all(pool)
+                .await?;
+                rows.into_iter()
+                    .map(|(name, column, non_unique)| (name, column, non_unique == 0))
+                    .collect()
+            }
+            DbConnection::PostgreSQL(pool) => {
+                // Expression columns (attnum 0) have no attribute and are skipped
+                sqlx::query_as(
+                    r#"SELECT ic.relname::text, a.attname::text, ix.indisunique
+                       FROM pg_catalog.pg_index ix
+                       JOIN pg_catalog.pg_class ic ON ic.oid = ix.indexrelid
+                       CROSS JOIN LATERAL unnest(ix.indkey::int2[]) WITH ORDINALITY AS k(attnum, ord)
+                       JOIN pg_catalog.pg_attribute a ON a.attrelid = ix.indrelid AND a.attnum = k.attnum
+                       WHERE ix.indrelid = $1::regclass
+                         AND NOT ix.indisprimary
+                         AND NOT EXISTS (
+                             SELECT 1 FROM pg_catalog.pg_constraint con
+                             WHERE con.conindid = ix.indexrelid AND con.conrelid = ix.indrelid
+                         )
+                       ORDER BY ic.relname, k.ord"#
+                )
+                .bind(pg_relation(table_name))
+                .fetch_all(pool)
+                .await?
+            }
+            DbConnection::SQLite(pool) => {
+                // origin 'c' is CREATE INDEX; 'pk' and 'u' are automatic indexes
+                let rows: Vec<(String, Option<String>, i64)> = sqlx::query_as(
+                    &format!(
+                        r#"SELECT il.name, ii.name, il."unique"
+                           FROM pragma_index_list('{}') il
+                           JOIN pragma_index_info(il.name) ii
+                           WHERE il.origin = 'c'
+                           ORDER BY il.name, ii.seqno"#,
+                        table_name.replace('\'', "''")
+                    )
+                )
+                .fetch_all(pool)
+                .await?;
+                rows.into_iter()
+                    .filter_map(|(name, column, unique)| Some((name, column?, unique == 1)))
+                    .collect()
+            }
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    r#"SELECT i.name, c.name, CAST(i.is_unique AS INT)
+                       FROM sys.indexes i
+                       JOIN sys.index_columns ic ON ic.object_id = i.object_id AND ic.index_id = i.index_id
+                       JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+                       WHERE i.object_id = OBJECT_ID(QUOTENAME(SCHEMA_NAME()) + '.' + QUOTENAME(@P1))
+                         AND i.name IS NOT NULL AND ic.is_included_column = 0
+                         AND i.is_primary_key = 0 AND i.is_unique_constraint = 0
+                       ORDER BY i.name, ic.key_ordinal"#,
+                    &[&table_name],
+                )
+                .await?;
+                rows.iter()
+                    .map(|row| {
+                        (
+                            row.get::<&str, _>(0).unwrap_or_default().to_string(),
+                            row.get::<&str, _>(1).unwrap_or_default().to_string(),
+                            row.get::<i32, _>(2).unwrap_or(0) == 1,
+                        )
+                    })
+                    .collect()
+            }
+        };
+
+        // Group columns by index name, keeping the query order
+        let mut indexes: Vec<IndexInfo> = Vec::new();
+        for (name, column, is_unique) in rows {
+            match indexes.iter_mut().find(|i| i.name == name) {
+                Some(index) => index.columns.push(column),
+                None => indexes.push(IndexInfo {
+                    name,
+                    columns: vec![column],
+                    is_unique,
+                }),
+            }
+        }
+
+        Ok(indexes)
+    }
+
+    /// Get foreign keys for a table
+    pub async fn get_foreign_keys(&self, table_name: &str) -> Result<Vec<ForeignKeyInfo>> {
+        // Rows of (name, column, referenced table, referenced column, on delete, on update),
+        // ordered by constraint name and column position
+        let rows: Vec<(String, String, String, String, String, String)> = match self {
             DbConnection::MySQL(pool) => {
-                let rows: Vec<(String, i32, String)> = sqlx::query_as(
-                    &format!("SHOW INDEX FROM `{}`", table_name)
+                sqlx::query_as(
+                    r#"SELECT k.CONSTRAINT_NAME, k.COLUMN_NAME, k.REFERENCED_TABLE_NAME, k.REFERENCED_COLUMN_NAME,
+                              r.DELETE_RULE, r.UPDATE_RULE
+                       FROM INFORMATION_SCHEMA.KEY_COLUMN_USAGE k
+                       JOIN INFORMATION_SCHEMA.REFERENTIAL_CONSTRAINTS r
+                         ON r.CONSTRAINT_SCHEMA = k.CONSTRAINT_SCHEMA AND r.CONSTRAINT_NAME = k.CONSTRAINT_NAME
+                       WHERE k.TABLE_SCHEMA = DATABASE() AND k.TABLE_NAME = ?
+                         AND k.REFERENCED_TABLE_NAME IS NOT NULL
+                       ORDER BY k.CONSTRAINT_NAME, k.ORDINAL_POSITION"#
+                )
+                .bind(table_name)
+                .fetch_all(pool)
+                .await?
+            }
+            DbConnection::PostgreSQL(pool) => {
+                sqlx::query_as(
+                    r#"SELECT con.conname::text, a.attname::text,
+                              CASE WHEN refns.nspname = current_schema() THEN ref.relname::text
+                                   ELSE refns.nspname || '.' || ref.relname END,
+                              fa.attname::text,
+                              CASE con.confdeltype WHEN 'c' THEN 'CASCADE' WHEN 'n' THEN 'SET NULL'
+                                   WHEN 'd' THEN 'SET DEFAULT' WHEN 'r' THEN 'RESTRICT' ELSE 'NO ACTION' END,
+                              CASE con.confupdtype WHEN 'c' THEN 'CASCADE' WHEN 'n' THEN 'SET NULL'
+                                   WHEN 'd' THEN 'SET DEFAULT' WHEN 'r' THEN 'RESTRICT' ELSE 'NO ACTION' END
+                       FROM pg_catalog.pg_constraint con
+                       CROSS JOIN LATERAL unnest(con.conkey, con.confkey) WITH ORDINALITY AS k(attnum, refattnum, ord)
+                       JOIN pg_catalog.pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+                       JOIN pg_catalog.pg_attribute fa ON fa.attrelid = con.confrelid AND fa.attnum = k.refattnum
+                       JOIN pg_catalog.pg_class ref ON ref.oid = con.confrelid
+                       JOIN pg_catalog.pg_namespace refns ON refns.oid = ref.relnamespace
+                       WHERE con.contype = 'f' AND con.conrelid = $1::regclass
+                       ORDER BY con.conname, k.ord"#
+                )
+                .bind(pg_relation(table_name))
+                .fetch_all(pool)
+                .await?
+            }
+            DbConnection::SQLite(pool) => {
+                // SQLite foreign keys are unnamed; "to" is NULL when referencing the primary key
+                let fk_rows: Vec<SqliteForeignKeyRow> = sqlx::query_as(
+                    &format!(
+                        "SELECT id, seq, \"table\", \"from\", \"to\", on_update, on_delete FROM pragma_foreign_key_list('{}') ORDER BY id, seq",
+                        table_name.replace('\'', "''")
+                    )
                 )
                 .fetch_all(pool)
                 .await
                 .unwrap_or_default();
 
-                // Group by index name
-                let mut index_map: std::collections::HashMap<String, IndexInfo> = std::collections::HashMap::new();
-                for (key_name, non_unique, column_name) in rows {
-                    index_map
-                        .entry(key_name.clone())
-                        .or_insert_with(|| IndexInfo {
-                            name: key_name,
-                            columns: Vec::new(),
-                            is_unique: non_unique == 0,
-                        })
-                        .columns
-                        .push(column_name);
+                let mut rows = Vec::with_capacity(fk_rows.len());
+                for (id, seq, ref_table, from, to, on_update, on_delete) in fk_rows {
+                    let to = match to {
+                        Some(to) => to,
+                        None => self
+                            .get_primary_keys(&ref_table, "")
+                            .await?
+                            .get(seq as usize)
+                            .cloned()
+                            .unwrap_or_default(),
+                    };
+                    rows.push((format!("fk_{}_{}", table_name, id), from, ref_table, to, on_delete, on_update));
                 }
+                rows
+            }
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    r#"SELECT fk.name, c.name, OBJECT_NAME(fk.referenced_object_id), rc.name,
+                              fk.delete_referential_action_desc, fk.update_referential_action_desc
+                       FROM sys.foreign_keys fk
+                       JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+                       JOIN sys.columns c ON c.object_id = fkc.parent_object_id AND c.column_id = fkc.parent_column_id
+                       JOIN sys.columns rc ON rc.object_id = fkc.referenced_object_id AND rc.column_id = fkc.referenced_column_id
+                       WHERE fk.parent_object_id = OBJECT_ID(QUOTENAME(SCHEMA_NAME()) + '.' + QUOTENAME(@P1))
+                       ORDER BY fk.name, fkc.constraint_column_id"#,
+                    &[&table_name],
+                )
+                .await?;
+
+                let text = |row: &tiberius::Row, i: usize| row.get::<&str, _>(i).unwrap_or_default().to_string();
+                rows.iter()
+                    .map(|row| {
+                        (
+                            text(row, 0),
+                            text(row, 1),
+                            text(row, 2),
+                            text(row, 3),
+                            // NO_ACTION, SET_NULL, ... -> NO ACTION, SET NULL, ...
+                            text(row, 4).replace('_', " "),
+                            text(row, 5).replace('_', " "),
+                        )
+                    })
+                    .collect()
+            }
+        };
 
-                Ok(index_map.into_values().collect())
+        // Group columns by constraint, keeping the query order
+        let mut foreign_keys: Vec<ForeignKeyInfo> = Vec::new();
+        for (name, column, referenced_table, referenced_column, on_delete, on_update) in rows {
+            match foreign_keys.iter_mut().find(|fk| fk.name == name) {
+                Some(fk) => {
+                    fk.columns.push(column);
+                    fk.referenced_columns.push(referenced_column);
+                }
+                None => foreign_keys.push(ForeignKeyInfo {
+                    name,
+                    columns: vec![column],
+                    referenced_table,
+                    referenced_columns: vec![referenced_column],
+                    on_delete: on_delete.to_uppercase(),
+                    on_update: on_update.to_uppercase(),
+                }),
             }
-            DbConnection::PostgreSQL(pool) => {
+        }
+
+        Ok(foreign_keys)
+    }
+
+    /// Get CHECK and UNIQUE constraints for a table. MySQL UNIQUE constraints
+    /// are read as unique indexes, as MySQL does not tell them apart.
+    pub async fn get_constraints(&self, table_name: &str) -> Result<Vec<ConstraintInfo>> {
+        // Rows of (name, kind, column, check clause), ordered by constraint name and column
+        // position; CHECK constraints have a single row without a column
+        let rows: Vec<(String, String, Option<String>, String)> = match self {
+            DbConnection::MySQL(pool) => {
+                // CHECK_CONSTRAINTS exists from MySQL 8.0.16, older servers ignore CHECK clauses
                 let rows: Vec<(String, String)> = sqlx::query_as(
-                    r#"SELECT indexname, indexdef FROM pg_indexes WHERE schemaname = 'public' AND tablename = $1"#
+                    r#"SELECT tc.CONSTRAINT_NAME, cc.CHECK_CLAUSE
+                       FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc
+                       JOIN INFORMATION_SCHEMA.CHECK_CONSTRAINTS cc
+                         ON cc.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA AND cc.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+                       WHERE tc.TABLE_SCHEMA = DATABASE() AND tc.TABLE_NAME = ?
+                         AND tc.CONSTRAINT_TYPE = 'CHECK'
+                       ORDER BY tc.CONSTRAINT_NAME"#
                 )
                 .bind(table_name)
                 .fetch_all(pool)
+                .await
+                .unwrap_or_default();
+                rows.into_iter()
+                    .map(|(name, clause)| (name, "CHECK".to_string(), None, clause))
+                    .collect()
+            }
+            DbConnection::PostgreSQL(pool) => {
+                let rows: Vec<(String, String, Option<String>, String)> = sqlx::query_as(
+                    r#"SELECT con.conname::text, con.contype::text, a.attname::text, pg_get_constraintdef(con.oid)
+                       FROM pg_catalog.pg_constraint con
+                       LEFT JOIN LATERAL unnest(con.conkey) WITH ORDINALITY AS k(attnum, ord) ON con.contype = 'u'
+                       LEFT JOIN pg_catalog.pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+                       WHERE con.conrelid = $1::regclass AND con.contype IN ('c', 'u')
+                       ORDER BY con.conname, k.ord"#
+                )
+                .bind(pg_relation(table_name))
+                .fetch_all(pool)
                 .await?;
+                rows.into_iter()
+                    .map(|(name, contype, column, definition)| {
+                        if contype == "c" {
+                            // pg_get_constraintdef gives `CHECK ((expr))`
+                            let clause = definition.trim_start_matches("CHECK").trim_end_matches(" NOT VALID");
+                            (name, "CHECK".to_string(), None, strip_outer_parens(clause).to_string())
+                        } else {
+                            (name, "UNIQUE".to_string(), column, String::new())
+                        }
+                    })
+                    .collect()
+            }
+            DbConnection::SQLite(pool) => {
+                // UNIQUE constraints get automatic indexes (origin 'u'); CHECK constraints
+                // are only kept in the CREATE TABLE statement
+                let unique: Vec<(String, Option<String>)> = sqlx::query_as(
+                    &format!(
+                        r#"SELECT il.name, ii.name
+                           FROM pragma_index_list('{}') il
+                           JOIN pragma_index_info(il.name) ii
+                           WHERE il.origin = 'u'
+                           ORDER BY il.name, ii.seqno"#,
+                        table_name.replace('\'', "''")
+                    )
+                )
+                .fetch_all(pool)
+                .await?;
+                let create_sql: Option<(String,)> =
+                    sqlx::query_as("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?")
+                        .bind(table_name)
+                        .fetch_optional(pool)
+                        .await?;
 
-                Ok(rows
+                // Constraints are unnamed unless declared with CONSTRAINT, name them after the table
+                let mut rows: Vec<(String, String, Option<String>, String)> = unique
                     .into_iter()
-                    .map(|(name, def)| IndexInfo {
-                        name,
-                        columns: vec![def],
-                        is_unique: false,
+                    .filter_map(|(index, column)| {
+                        let n = index.rsplit('_').next().unwrap_or_default().to_string();
+                        Some((format!("uq_{}_{}", table_name, n), "UNIQUE".to_string(), Some(column?), String::new()))
                     })
-                    .collect())
+                    .collect();
+                let checks = create_sql.map(|(sql,)| sqlite_checks(&sql)).unwrap_or_default();
+                for (i, (name, clause)) in checks.into_iter().enumerate() {
+                    let name = name.unwrap_or_else(|| format!("ck_{}_{}", table_name, i + 1));
+                    rows.push((name, "CHECK".to_string(), None, clause));
+                }
+                rows
+            }
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    r#"SELECT cc.name, 'CHECK', NULL, cc.definition, 0
+                       FROM sys.check_constraints cc
+                       WHERE cc.parent_object_id = OBJECT_ID(QUOTENAME(SCHEMA_NAME()) + '.' + QUOTENAME(@P1))
+                       UNION ALL
+                       SELECT kc.name, 'UNIQUE', c.name, '', ic.key_ordinal
+                       FROM sys.key_constraints kc
+                       JOIN sys.index_columns ic ON ic.object_id = kc.parent_object_id AND ic.index_id = kc.unique_index_id
+                       JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+                       WHERE kc.type = 'UQ'
+                         AND kc.parent_object_id = OBJECT_ID(QUOTENAME(SCHEMA_NAME()) + '.' + QUOTENAME(@P1))
+                       ORDER BY 1, 5"#,
+                    &[&table_name],
+                )
+                .await?;
+
+                let text = |row: &tiberius::Row, i: usize| row.get::<&str, _>(i).unwrap_or_default().to_string();
+                rows.iter()
+                    .map(|row| {
+                        (
+                            text(row, 0),
+                            text(row, 1),
+                            row.get::<&str, _>(2).map(str::to_string),
+                            // SQL Server wraps check definitions in parentheses
+                            strip_outer_parens(&text(row, 3)).to_string(),
+                        )
+                    })
+                    .collect()
+            }
+        };
+
+        // Group UNIQUE columns by constraint, keeping the query order
+        let mut constraints: Vec<ConstraintInfo> = Vec::new();
+        for (name, kind, column, check_clause) in rows {
+            match constraints.iter_mut().find(|c| c.name == name) {
+                Some(constraint) => constraint.columns.extend(column),
+                None => constraints.push(ConstraintInfo {
+                    name,
+                    kind,
+                    columns: column.into_iter().collect(),
+                    check_clause,
+                }),
+            }
+        }
+
+        Ok(constraints)
+    }
+
+    /// Get views with their definitions
+    pub async fn get_views(&self) -> Result<Vec<ViewInfo>> {
+        let rows: Vec<(String, String)> = match self {
+            DbConnection::MySQL(pool) => {
+                let rows: Vec<(String, String, String)> = sqlx::query_as(
+                    r#"SELECT TABLE_SCHEMA, TABLE_NAME, VIEW_DEFINITION
+                       FROM INFORMATION_SCHEMA.VIEWS
+                       WHERE TABLE_SCHEMA = DATABASE()
+                       ORDER BY TABLE_NAME"#
+                )
+                .fetch_all(pool)
+                .await?;
+                // Definitions qualify every table with the database; drop it so
+                // they compare across databases
+                rows.into_iter()
+                    .map(|(database, name, definition)| {
+                        let qualifier = format!("{}.", DbType::MySQL.quote_identifier(&database));
+                        (name, definition.replace(&qualifier, ""))
+                    })
+                    .collect()
+            }
+            DbConnection::PostgreSQL(pool) => {
+                sqlx::query_as(
+                    r#"SELECT CASE WHEN n.nspname = current_schema() THEN c.relname::text
+                                   ELSE n.nspname || '.' || c.relname END,
+                              pg_get_viewdef(c.oid, true)
+                       FROM pg_catalog.pg_class c
+                       JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+                       WHERE c.relkind = 'v' AND n.nspname = ANY(current_schemas(false))
+                       ORDER BY array_position(current_schemas(false), n.nspname::text), c.relname"#
+                )
+                .fetch_all(pool)
+                .await?
             }
             DbConnection::SQLite(pool) => {
-                let rows: Vec<(i32, String, i32, String, i32)> = sqlx::query_as(
-                    &format!("PRAGMA index_list('{}')", table_name)
+                let rows: Vec<(String, String)> = sqlx::query_as(
+                    "SELECT name, sql FROM sqlite_master WHERE type = 'view' ORDER BY name"
                 )
                 .fetch_all(pool)
-                .await
-                .unwrap_or_default();
+                .await?;
+                rows.into_iter().map(|(name, sql)| (name, view_body(&sql).to_string())).collect()
+            }
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    r#"SELECT v.name, m.definition
+                       FROM sys.views v
+                       JOIN sys.sql_modules m ON m.object_id = v.object_id
+                       WHERE v.schema_id = SCHEMA_ID()
+                       ORDER BY v.name"#,
+                    &[],
+                )
+                .await?;
+                rows.iter()
+                    .map(|row| {
+                        let sql = row.get::<&str, _>(1).unwrap_or_default();
+                        (row.get::<&str, _>(0).unwrap_or_default().to_string(), view_body(sql).to_string())
+                    })
+                    .collect()
+            }
+        };
 
-                Ok(rows
-                    .into_iter()
-                    .map(|(_, name, unique, _, _)| IndexInfo {
-                        name,
-                        columns: Vec::new(),
-                        is_unique: unique == 1,
+        Ok(rows
+            .into_iter()
+            .map(|(name, definition)| ViewInfo {
+                definition: definition.trim().trim_end_matches(';').trim_end().to_string(),
+                name,
+            })
+            .collect())
+    }
+
+    /// Get stored procedures and functions with their definitions.
+    /// SQLite has none.
+    pub async fn get_routines(&self) -> Result<Vec<RoutineInfo>> {
+        // Rows of (name, kind, arguments, definition)
+        let rows: Vec<(String, String, String, String)> = match self {
+            DbConnection::MySQL(pool) => {
+                let names: Vec<(String, String)> = sqlx::query_as(
+                    r#"SELECT ROUTINE_NAME, ROUTINE_TYPE FROM INFORMATION_SCHEMA.ROUTINES
+                       WHERE ROUTINE_SCHEMA = DATABASE()
+                       ORDER BY ROUTINE_TYPE, ROUTINE_NAME"#
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut rows = Vec::with_capacity(names.len());
+                for (name, kind) in names {
+                    // The statement is NULL without the privilege to see it
+                    let row = sqlx::query(&format!("SHOW CREATE {} {}", kind, DbType::MySQL.quote_identifier(&name)))
+                        .fetch_one(pool)
+                        .await?;
+                    let definition: Option<String> = sqlx::Row::try_get(&row, 2)?;
+                    rows.push((name, kind, String::new(), strip_definer(&definition.unwrap_or_default())));
+                }
+                rows
+            }
+            DbConnection::PostgreSQL(pool) => {
+                // Functions of extensions are created with the extension
+                sqlx::query_as(
+                    r#"SELECT CASE WHEN n.nspname = current_schema() THEN p.proname::text
+                                   ELSE n.nspname || '.' || p.proname END,
+                              CASE p.prokind WHEN 'p' THEN 'PROCEDURE' ELSE 'FUNCTION' END,
+                              pg_get_function_identity_arguments(p.oid),
+                              pg_get_functiondef(p.oid)
+                       FROM pg_catalog.pg_proc p
+                       JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+                       WHERE p.prokind IN ('f', 'p') AND n.nspname = ANY(current_schemas(false))
+                         AND NOT EXISTS (
+                             SELECT 1 FROM pg_catalog.pg_depend d
+                             WHERE d.classid = 'pg_proc'::regclass AND d.objid = p.oid AND d.deptype = 'e'
+                         )
+                       ORDER BY array_position(current_schemas(false), n.nspname::text), p.proname, 3"#
+                )
+                .fetch_all(pool)
+                .await?
+            }
+            DbConnection::SQLite(_) => Vec::new(),
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    r#"SELECT o.name, CASE WHEN o.type = 'P' THEN 'PROCEDURE' ELSE 'FUNCTION' END, m.definition
+                       FROM sys.objects o
+                       JOIN sys.sql_modules m ON m.object_id = o.object_id
+                       WHERE o.type IN ('P', 'FN', 'IF', 'TF') AND o.schema_id = SCHEMA_ID()
+                       ORDER BY o.name"#,
+                    &[],
+                )
+                .await?;
+                let text = |row: &tiberius::Row, i: usize| row.get::<&str, _>(i).unwrap_or_default().to_string();
+                rows.iter().map(|row| (text(row, 0), text(row, 1), String::new(), text(row, 2))).collect()
+            }
+        };
+
+        Ok(rows
+            .into_iter()
+            .map(|(name, kind, arguments, definition)| RoutineInfo {
+                name,
+                kind,
+                arguments,
+                definition: definition.trim().trim_end_matches(';').trim_end().to_string(),
+            })
+            .collect())
+    }
+
+    /// Get triggers with their definitions
+    pub async fn get_triggers(&self) -> Result<Vec<TriggerInfo>> {
+        // Rows of (name, table, definition)
+        let rows: Vec<(String, String, String)> = match self {
+            DbConnection::MySQL(pool) => {
+                let rows: Vec<(String, String, String, String, String)> = sqlx::query_as(
+                    r#"SELECT TRIGGER_NAME, EVENT_OBJECT_TABLE, ACTION_TIMING, EVENT_MANIPULATION, ACTION_STATEMENT
+                       FROM INFORMATION_SCHEMA.TRIGGERS
+                       WHERE TRIGGER_SCHEMA = DATABASE()
+                       ORDER BY EVENT_OBJECT_TABLE, TRIGGER_NAME"#
+                )
+                .fetch_all(pool)
+                .await?;
+                let quote = |name: &str| DbType::MySQL.quote_identifier(name);
+                rows.into_iter()
+                    .map(|(name, table, timing, event, statement)| {
+                        let definition = format!(
+                            "CREATE TRIGGER {} {} {} ON {} FOR EACH ROW {}",
+                            quote(&name),
+                            timing,
+                            event,
+                            quote(&table),
+                            statement
+                        );
+                        (name, table, definition)
                     })
-                    .collect())
+                    .collect()
             }
-            DbConnection::SQLServer(_) => {
-                Ok(Vec::new())
+            DbConnection::PostgreSQL(pool) => {
+                sqlx::query_as(
+                    r#"SELECT t.tgname::text,
+                              CASE WHEN n.nspname = current_schema() THEN c.relname::text
+                                   ELSE n.nspname || '.' || c.relname END,
+                              pg_get_triggerdef(t.oid, true)
+                       FROM pg_catalog.pg_trigger t
+                       JOIN pg_catalog.pg_class c ON c.oid = t.tgrelid
+                       JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+                       WHERE NOT t.tgisinternal AND n.nspname = ANY(current_schemas(false))
+                       ORDER BY 2, 1"#
+                )
+                .fetch_all(pool)
+                .await?
+            }
+            DbConnection::SQLite(pool) => {
+                sqlx::query_as(
+                    "SELECT name, tbl_name, sql FROM sqlite_master WHERE type = 'trigger' ORDER BY tbl_name, name"
+                )
+                .fetch_all(pool)
+                .await?
+            }
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    r#"SELECT t.name, OBJECT_NAME(t.parent_id), m.definition
+                       FROM sys.triggers t
+                       JOIN sys.sql_modules m ON m.object_id = t.object_id
+                       WHERE t.parent_class = 1 AND OBJECT_SCHEMA_NAME(t.parent_id) = SCHEMA_NAME()
+                       ORDER BY 2, 1"#,
+                    &[],
+                )
+                .await?;
+                let text = |row: &tiberius::Row, i: usize| row.get::<&str, _>(i).unwrap_or_default().to_string();
+                rows.iter().map(|row| (text(row, 0), text(row, 1), text(row, 2))).collect()
             }
+        };
+
+        Ok(rows
+            .into_iter()
+            .map(|(name, table_name, definition)| TriggerInfo {
+                name,
+                table_name,
+                definition: definition.trim().trim_end_matches(';').trim_end().to_string(),
+            })
+            .collect())
+    }
+
+    /// Get PostgreSQL sequences, except those of identity columns, which
+    /// come and go with their column. Empty on other engines.
+    pub async fn get_sequences(&self) -> Result<Vec<SequenceInfo>> {
+        let DbConnection::PostgreSQL(pool) = self else {
+            return Ok(Vec::new());
+        };
+        type SequenceRow = (String, String, i64, i64, i64, i64, bool, Option<i64>, Option<String>, Option<String>);
+        let rows: Vec<SequenceRow> = sqlx::query_as(
+            r#"SELECT CASE WHEN n.nspname = current_schema() THEN c.relname::text
+                           ELSE n.nspname || '.' || c.relname END,
+                      format_type(s.seqtypid, NULL), s.seqstart, s.seqincrement, s.seqmin, s.seqmax, s.seqcycle,
+                      ps.last_value,
+                      CASE WHEN tn.nspname = current_schema() THEN t.relname::text
+                           ELSE tn.nspname || '.' || t.relname END,
+                      a.attname::text
+               FROM pg_catalog.pg_class c
+               JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+               JOIN pg_catalog.pg_sequence s ON s.seqrelid = c.oid
+               JOIN pg_catalog.pg_sequences ps ON ps.schemaname = n.nspname AND ps.sequencename = c.relname
+               LEFT JOIN pg_catalog.pg_depend d
+                 ON d.classid = 'pg_class'::regclass AND d.objid = c.oid
+                AND d.refclassid = 'pg_class'::regclass AND d.deptype = 'a'
+               LEFT JOIN pg_catalog.pg_class t ON t.oid = d.refobjid
+               LEFT JOIN pg_catalog.pg_namespace tn ON tn.oid = t.relnamespace
+               LEFT JOIN pg_catalog.pg_attribute a ON a.attrelid = d.refobjid AND a.attnum = d.refobjsubid
+               WHERE c.relkind = 'S' AND n.nspname = ANY(current_schemas(false))
+                 AND NOT EXISTS (
+                     SELECT 1 FROM pg_catalog.pg_depend i
+                     WHERE i.classid = 'pg_class'::regclass AND i.objid = c.oid AND i.deptype = 'i'
+                 )
+               ORDER BY 1"#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(name, data_type, start, increment, min_value, max_value, cycle, last_value, table, column)| {
+                SequenceInfo {
+                    name,
+                    data_type,
+                    start,
+                    increment,
+                    min_value,
+                    max_value,
+                    cycle,
+                    last_value,
+                    owned_by: table.zip(column),
+                }
+            })
+            .collect())
+    }
+
+    /// Get PostgreSQL enum types with their labels. Empty on other engines.
+    pub async fn get_enums(&self) -> Result<Vec<EnumInfo>> {
+        let DbConnection::PostgreSQL(pool) = self else {
+            return Ok(Vec::new());
+        };
+        // Types of extensions are created with the extension
+        let rows: Vec<(String, Vec<String>)> = sqlx::query_as(
+            r#"SELECT CASE WHEN n.nspname = current_schema() THEN t.typname::text
+                           ELSE n.nspname || '.' || t.typname END,
+                      array_agg(e.enumlabel::text ORDER BY e.enumsortorder)
+               FROM pg_catalog.pg_type t
+               JOIN pg_catalog.pg_namespace n ON n.oid = t.typnamespace
+               JOIN pg_catalog.pg_enum e ON e.enumtypid = t.oid
+               WHERE n.nspname = ANY(current_schemas(false))
+                 AND NOT EXISTS (
+                     SELECT 1 FROM pg_catalog.pg_depend d
+                     WHERE d.classid = 'pg_type'::regclass AND d.objid = t.oid AND d.deptype = 'e'
+                 )
+               GROUP BY n.nspname, t.typname
+               ORDER BY 1"#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|(name, labels)| EnumInfo { name, labels }).collect())
+    }
+
+    /// Get PostgreSQL domains with their constraints. Empty on other engines.
+    pub async fn get_domains(&self) -> Result<Vec<DomainInfo>> {
+        let DbConnection::PostgreSQL(pool) = self else {
+            return Ok(Vec::new());
+        };
+        type DomainRow = (String, String, bool, Option<String>, Vec<String>, Vec<String>);
+        let rows: Vec<DomainRow> = sqlx::query_as(
+            r#"SELECT CASE WHEN n.nspname = current_schema() THEN t.typname::text
+                           ELSE n.nspname || '.' || t.typname END,
+                      format_type(t.typbasetype, t.typtypmod), t.typnotnull, t.typdefault,
+                      COALESCE(array_agg(c.conname::text ORDER BY c.conname) FILTER (WHERE c.oid IS NOT NULL), '{}'),
+                      COALESCE(array_agg(pg_get_constraintdef(c.oid) ORDER BY c.conname) FILTER (WHERE c.oid IS NOT NULL), '{}')
+               FROM pg_catalog.pg_type t
+               JOIN pg_catalog.pg_namespace n ON n.oid = t.typnamespace
+               LEFT JOIN pg_catalog.pg_constraint c ON c.contypid = t.oid AND c.contype = 'c'
+               WHERE t.typtype = 'd' AND n.nspname = ANY(current_schemas(false))
+                 AND NOT EXISTS (
+                     SELECT 1 FROM pg_catalog.pg_depend d
+                     WHERE d.classid = 'pg_type'::regclass AND d.objid = t.oid AND d.deptype = 'e'
+                 )
+               GROUP BY n.nspname, t.typname, t.typbasetype, t.typtypmod, t.typnotnull, t.typdefault
+               ORDER BY 1"#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(name, base_type, not_null, default, check_names, check_defs)| DomainInfo {
+                name,
+                base_type,
+                nullable: !not_null,
+                default,
+                checks: check_names.into_iter().zip(check_defs).collect(),
+            })
+            .collect())
+    }
+
+    /// PostgreSQL schemas tables are read from, in search order; empty elsewhere
+    pub async fn get_schema_names(&self) -> Result<Vec<String>> {
+        match self {
+            DbConnection::PostgreSQL(pool) => {
+                let rows: Vec<(String,)> = sqlx::query_as("SELECT unnest(current_schemas(false))::text")
+                    .fetch_all(pool)
+                    .await?;
+                Ok(rows.into_iter().map(|r| r.0).collect())
+            }
+            _ => Ok(Vec::new()),
         }
     }
 
@@ -353,7 +1218,179 @@ impl DbConnection {
 
         Ok(SchemaInfo {
             database: database.to_string(),
+            db_type: self.db_type(),
+            schemas: self.get_schema_names().await?,
             tables,
+            views: self.get_views().await?,
+            routines: self.get_routines().await?,
+            triggers: self.get_triggers().await?,
+            sequences: self.get_sequences().await?,
+            enums: self.get_enums().await?,
+            domains: self.get_domains().await?,
         })
     }
 }
+
+/// Drop the `DEFINER=user@host` clause MySQL adds to routines, which
+/// differs between environments and is not part of the routine itself
+fn strip_definer(create_sql: &str) -> String {
+    match create_sql.find(" DEFINER=") {
+        Some(start) => {
+            let rest = &create_sql[start + 1..];
+            let end = rest.find(' ').unwrap_or(rest.len());
+            format!("{}{}", &create_sql[..start], &rest[end..])
+        }
+        None => create_sql.to_string(),
+    }
+}
+
+/// Query of a `CREATE VIEW name [(columns)] AS query` statement: everything
+/// after the first `AS` word outside quotes and parentheses
+fn view_body(create_sql: &str) -> &str {
+    let bytes = create_sql.as_bytes();
+    let mut depth = 0;
+    let mut quote: Option<u8> = None;
+    for (i, &b) in bytes.iter().enumerate() {
+        match (quote, b) {
+            (Some(q), _) if b == if q == b'[' { b']' } else { q } => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'`' | b'\'' | b'[') => quote = Some(b),
+            (None, b'(') => depth += 1,
+            (None, b')') => depth -= 1,
+            (None, b'a' | b'A') if depth == 0 => {
+                let word_start = i == 0 || !bytes[i - 1].is_ascii_alphanumeric() && bytes[i - 1] != b'_';
+                let is_as = create_sql[i..].len() > 2
+                    && create_sql[i..i + 2].eq_ignore_ascii_case("as")
+                    && !(bytes[i + 2].is_ascii_alphanumeric() || bytes[i + 2] == b'_');
+                if word_start && is_as {
+                    return create_sql[i + 2..].trim();
+                }
+            }
+            _ => {}
+        }
+    }
+    create_sql
+}
+
+/// CHECK constraints of a SQLite CREATE TABLE statement as (name, expression),
+/// the name set when declared with `CONSTRAINT name CHECK (..)`
+fn sqlite_checks(create_sql: &str) -> Vec<(Option<String>, String)> {
+    // Words, quoted strings and names, and single punctuation characters with their offsets
+    let bytes = create_sql.as_bytes();
+    let mut tokens: Vec<(usize, &str)> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`' | b'[') => {
+                let close = if quote == b'[' { b']' } else { quote };
+                i += 1;
+                while i < bytes.len() && bytes[i] != close {
+                    i += 1;
+                }
+                i = (i + 1).min(bytes.len());
+            }
+            b if b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80 => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] >= 0x80) {
+                    i += 1;
+                }
+            }
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            _ => i += 1,
+        }
+        tokens.push((start, &create_sql[start..i]));
+    }
+
+    let mut checks = Vec::new();
+    for (k, (_, token)) in tokens.iter().enumerate() {
+        if !token.eq_ignore_ascii_case("CHECK") || tokens.get(k + 1).map(|t| t.1) != Some("(") {
+            continue;
+        }
+        let mut depth = 0;
+        let close = tokens[k + 1..].iter().find(|(_, t)| {
+            match *t {
+                "(" => depth += 1,
+                ")" => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        });
+        let Some((close, _)) = close else {
+            continue;
+        };
+        let name = (k >= 2 && tokens[k - 2].1.eq_ignore_ascii_case("CONSTRAINT"))
+            .then(|| tokens[k - 1].1.trim_matches(|c| matches!(c, '"' | '`' | '[' | ']')).to_string());
+        checks.push((name, create_sql[tokens[k + 1].0 + 1..*close].trim().to_string()));
+    }
+    checks
+}
+
+/// PostgreSQL relation name of a table, for casting to `regclass`.
+/// Unqualified names resolve through the search path.
+pub(super) fn pg_relation(table_name: &str) -> String {
+    let quote = |part: &str| format!("\"{}\"", part.replace('"', "\"\""));
+    match table_name.split_once('.') {
+        Some((schema, table)) => format!("{}.{}", quote(schema), quote(table)),
+        None => quote(table_name),
+    }
+}
+
+/// Build a SQL Server column type from INFORMATION_SCHEMA.COLUMNS parts
+fn mssql_column_type(
+    data_type: &str,
+    char_length: Option<i32>,
+    precision: Option<i32>,
+    scale: Option<i32>,
+) -> String {
+    match data_type {
+        "char" | "varchar" | "nchar" | "nvarchar" | "binary" | "varbinary" => match char_length {
+            Some(-1) => format!("{}(max)", data_type),
+            Some(len) => format!("{}({})", data_type, len),
+            None => data_type.to_string(),
+        },
+        "decimal" | "numeric" => match (precision, scale) {
+            (Some(p), Some(s)) => format!("{}({},{})", data_type, p, s),
+            _ => data_type.to_string(),
+        },
+        _ => data_type.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_definer_removes_the_definer_clause() {
+        assert_eq!(
+            strip_definer("CREATE DEFINER=`root`@`%` PROCEDURE `p`()\nBEGIN\nEND"),
+            "CREATE PROCEDURE `p`()\nBEGIN\nEND"
+        );
+        assert_eq!(
+            strip_definer("CREATE DEFINER=`app`@`10.0.0.1` TRIGGER t BEFORE INSERT ON a FOR EACH ROW SET NEW.x = 1"),
+            "CREATE TRIGGER t BEFORE INSERT ON a FOR EACH ROW SET NEW.x = 1"
+        );
+        assert_eq!(strip_definer("CREATE FUNCTION f() RETURNS int RETURN 1"), "CREATE FUNCTION f() RETURNS int RETURN 1");
+    }
+
+    #[test]
+    fn sqlite_checks_finds_named_and_unnamed_checks() {
+        let create_sql = "CREATE TABLE t (\n  id INTEGER PRIMARY KEY,\n  qty INT CHECK (qty >= 0),\n  \"check\" TEXT,\n  CONSTRAINT \"qty_max\" CHECK (qty < (100 + 1)),\n  CONSTRAINT [note_ok] CHECK (note <> 'CHECK (x)')\n)";
+        assert_eq!(
+            sqlite_checks(create_sql),
+            [
+                (None, "qty >= 0".to_string()),
+                (Some("qty_max".to_string()), "qty < (100 + 1)".to_string()),
+                (Some("note_ok".to_string()), "note <> 'CHECK (x)'".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn sqlite_checks_ignores_tables_without_checks() {
+        assert!(sqlite_checks("CREATE TABLE checks (checked INT, check_id INT)").is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::connection::config_dir;
+use super::CompareOptions;
+
+/// Compare settings of one table, saved for a source/target connection pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedTableSettings {
+    pub source: String,
+    pub target: String,
+    pub table: String,
+    pub options: CompareOptions,
+}
+
+/// Contents of the settings file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Settings {
+    /// Column name patterns ignored in every data comparison
+    #[serde(default)]
+    ignored_columns: Vec<String>,
+    #[serde(default)]
+    tables: Vec<SavedTableSettings>,
+}
+
+/// Settings store for compare settings reused across sessions
+pub struct SettingsStore {
+    settings: Settings,
+    file_path: PathBuf,
+}
+
+impl SettingsStore {
+    /// Create new settings store
+    pub fn new() -> Result<Self> {
+        let file_path = config_dir()?.join("settings.json");
+        let settings = if file_path.exists() {
+            let content = std::fs::read_to_string(&file_path)?;
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Settings::default()
+        };
+
+        Ok(Self { settings, file_path })
+    }
+
+    /// Column name patterns ignored in every data comparison
+    pub fn ignored_columns(&self) -> &[String] {
+        &self.settings.ignored_columns
+    }
+
+    /// Save the column name patterns ignored in every data comparison
+    pub fn save_ignored_columns(&mut self, patterns: Vec<String>) -> Result<()> {
+        self.settings.ignored_columns = patterns;
+        self.persist()
+    }
+
+    /// Saved compare settings of every table of a connection pair, by table name
+    pub fn table_options(&self, source: &str, target: &str) -> HashMap<String, CompareOptions> {
+        self.settings
+            .tables
+            .iter()
+            .filter(|t| t.source == source && t.target == target)
+            .map(|t| (t.table.clone(), t.options.clone()))
+            .collect()
+    }
+
+    /// Save the compare settings of a table; default settings remove the entry
+    pub fn save_table_options(&mut self, source: &str, target: &str, table: &str, options: &CompareOptions) -> Result<()> {
+        self.settings
+            .tables
+            .retain(|t| !(t.source == source && t.target == target && t.table == table));
+        if *options != CompareOptions::default() {
+            self.settings.tables.push(SavedTableSettings {
+                source: source.to_string(),
+                target: target.to_string(),
+                table: table.to_string(),
+                options: options.clone(),
+            });
+        }
+        self.persist()
+    }
+
+    /// Persist settings to file
+    fn persist(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.settings)?;
+        std::fs::write(&self.file_path, content)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use super::sync::with_identity_insert;
+use super::{DataDiffResult, DataDiffType, DbType, KeyedTable, Value};
+
+/// Default number of rows written by one multi-row INSERT or upsert
+pub const DEFAULT_ROWS_PER_STATEMENT: usize = 100;
+
+/// SQL Server accepts at most 1000 rows in a VALUES list
+pub(super) const MSSQL_MAX_ROWS: usize = 1000;
+
+/// How row differences are written as SQL statements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SqlMode {
+    /// One INSERT, UPDATE or DELETE per row
+    #[default]
+    PerRow,
+    /// Inserted rows grouped into multi-row INSERTs
+    MultiRow,
+    /// Inserted and updated rows written as multi-row upserts, which need a
+    /// primary key or unique constraint on the key columns in the target
+    Upsert,
+}
+
+impl SqlMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SqlMode::PerRow => "per row",
+            SqlMode::MultiRow => "multi-row insert",
+            SqlMode::Upsert => "upsert",
+        }
+    }
+
+    /// The mode after this one, cycling
+    pub fn next(&self) -> Self {
+        match self {
+            SqlMode::PerRow => SqlMode::MultiRow,
+            SqlMode::MultiRow => SqlMode::Upsert,
+            SqlMode::Upsert => SqlMode::PerRow,
+        }
+    }
+}
+
+/// How to write the statements applying row differences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SqlOptions {
+    pub mode: SqlMode,
+    /// Rows per multi-row INSERT or upsert
+    pub rows_per_statement: usize,
+}
+
+impl Default for SqlOptions {
+    fn default() -> Self {
+        Self {
+            mode: SqlMode::default(),
+            rows_per_statement: DEFAULT_ROWS_PER_STATEMENT,
+        }
+    }
+}
+
+/// Statement applying row differences, with the number of rows it writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataStatement {
+    pub sql: String,
+    pub rows: usize,
+}
+
+impl DataStatement {
+    /// Statement writing a single row
+    pub fn single(sql: &str) -> Self {
+        Self {
+            sql: sql.to_string(),
+            rows: 1,
+        }
+    }
+}
+
+/// Statements applying `diffs` of one table to the target, `table` being
+/// its target side. Per row, the statements of the diffs are kept in order;
+/// otherwise deletes and updates come first and the grouped rows last.
+pub fn data_statements(
+    diffs: &[&DataDiffResult],
+    table: &KeyedTable,
+    db_type: DbType,
+    options: &SqlOptions,
+) -> Vec<DataStatement> {
+    // Upserts match rows by key; keyless rows can only be inserted
+    let mode = match options.mode {
+        SqlMode::Upsert if !table.has_key() => SqlMode::MultiRow,
+        mode => mode,
+    };
+    if mode == SqlMode::PerRow {
+        return diffs.iter().map(|d| DataStatement::single(&d.sql)).collect();
+    }
+
+    let grouped = |d: &&&DataDiffResult| match d.diff_type {
+        DataDiffType::Insert => true,
+        DataDiffType::Update => mode == SqlMode::Upsert,
+        DataDiffType::Delete => false,
+    };
+    let mut statements: Vec<DataStatement> =
+        diffs.iter().filter(|d| !grouped(d)).map(|d| DataStatement::single(&d.sql)).collect();
+
+    let rows: Vec<&HashMap<String, Value>> = diffs
+        .iter()
+        .filter(grouped)
+        .filter_map(|d| d.new_values.as_ref())
+        .collect();
+    let mut chunk_size = options.rows_per_statement.max(1);
+    if db_type == DbType::SQLServer {
+        chunk_size = chunk_size.min(MSSQL_MAX_ROWS);
+    }
+    for chunk in rows.chunks(chunk_size) {
+        statements.push(DataStatement {
+            sql: match mode {
+                SqlMode::Upsert => upsert_sql(db_type, table, chunk),
+                _ => multi_row_insert_sql(db_type, table, chunk),
+            },
+            rows: chunk.len(),
+        });
+    }
+
+    statements
+}
+
+/// Quoted column list and the VALUES rows of `rows`
+fn values_list(db_type: DbType, table: &KeyedTable, rows: &[&HashMap<String, Value>]) -> (String, String) {
+    let cols: Vec<String> = table.columns.iter().map(|c| table.quote_column(c, db_type)).collect();
+    let values: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let vals: Vec<String> = table
+                .columns
+                .iter()
+                .map(|c| row.get(c).unwrap_or(&Value::Null).to_sql_literal(db_type))
+                .collect();
+            format!("({})", vals.join(", "))
+        })
+        .collect();
+    (cols.join(", "), values.join(",\n  "))
+}
+
+pub(super) fn multi_row_insert_sql(db_type: DbType, table: &KeyedTable, rows: &[&HashMap<String, Value>]) -> String {
+    let (cols, values) = values_list(db_type, table, rows);
+    let insert = format!(
+        "INSERT INTO {} ({}) VALUES\n  {};",
+        db_type.quote_table_name(&table.name),
+        cols,
+        values
+    );
+    with_identity_insert(db_type, table, insert)
+}
+
+/// Insert `rows`, updating the compared columns of rows whose key exists
+fn upsert_sql(db_type: DbType, table: &KeyedTable, rows: &[&HashMap<String, Value>]) -> String {
+    let name = db_type.quote_table_name(&table.name);
+    let (cols, values) = values_list(db_type, table, rows);
+    let keys: Vec<String> = table.key_columns.iter().map(|c| table.quote_column(c, db_type)).collect();
+    // Columns left out of the comparison keep their target values
+    let updated: Vec<String> = table
+        .compared_columns
+        .iter()
+        .filter(|c| !table.key_columns.contains(c))
+        .map(|c| table.quote_column(c, db_type))
+        .collect();
+
+    match db_type {
+        DbType::MySQL => {
+            // A no-op assignment when only key columns are compared
+            let assigned = if updated.is_empty() { &keys } else { &updated };
+            let sets: Vec<String> = assigned.iter().map(|c| format!("{} = VALUES({})", c, c)).collect();
+            format!(
+                "INSERT INTO {} ({}) VALUES\n  {}\nON DUPLICATE KEY UPDATE {};",
+                name,
+                cols,
+                values,
+                sets.join(", ")
+            )
+        }
+        DbType::PostgreSQL | DbType::SQLite => {
+            let action = if updated.is_empty() {
+                "DO NOTHING".to_string()
+            } else {
+                let sets: Vec<String> = updated.iter().map(|c| format!("{} = excluded.{}", c, c)).collect();
+                format!("DO UPDATE SET {}", sets.join(", "))
+            };
+            format!(
+                "INSERT INTO {} ({}) VALUES\n  {}\nON CONFLICT ({}) {};",
+                name,
+                cols,
+                values,
+                keys.join(", "),
+                action
+            )
+        }
+        DbType::SQLServer => {
+            let on: Vec<String> = keys.iter().map(|k| format!("t.{} = s.{}", k, k)).collect();
+            let matched = if updated.is_empty() {
+                String::new()
+            } else {
+                let sets: Vec<String> = updated.iter().map(|c| format!("t.{} = s.{}", c, c)).collect();
+                format!("\nWHEN MATCHED THEN UPDATE SET {}", sets.join(", "))
+            };
+            let source_cols: Vec<String> = table
+                .columns
+                .iter()
+                .map(|c| format!("s.{}", table.quote_column(c, db_type)))
+                .collect();
+            let merge = format!(
+                "MERGE INTO {} AS t\nUSING (VALUES\n  {}) AS s ({})\nON {}{}\nWHEN NOT MATCHED THEN INSERT ({}) VALUES ({});",
+                name,
+                values,
+                cols,
+                on.join(" AND "),
+                matched,
+                cols,
+                source_cols.join(", ")
+            );
+            with_identity_insert(db_type, table, merge)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Target side of table `t` keyed by `id`, comparing `id` and `name`;
+    /// `note` is only copied and `name` is called `full_name` in the target
+    fn table() -> KeyedTable {
+        KeyedTable {
+            name: "t".to_string(),
+            columns: vec!["id".to_string(), "name".to_string(), "note".to_string()],
+            column_names: HashMap::from([("name".to_string(), "full_name".to_string())]),
+            key_columns: vec!["id".to_string()],
+            text_keys: vec![false],
+            compared_columns: vec!["id".to_string(), "name".to_string()],
+            filter: None,
+            identity_column: None,
+        }
+    }
+
+    fn row(id: i64, name: &str) -> HashMap<String, Value> {
+        HashMap::from([
+            ("id".to_string(), Value::Int(id)),
+            ("name".to_string(), Value::Text(name.to_string())),
+            ("note".to_string(), Value::Null),
+        ])
+    }
+
+    fn diff(diff_type: DataDiffType, id: i64) -> DataDiffResult {
+        DataDiffResult {
+            sql: format!("-- {:?} {}", diff_type, id),
+            diff_type,
+            table_name: "t".to_string(),
+            primary_key: HashMap::new(),
+            old_values: None,
+            new_values: Some(row(id, "x")),
+        }
+    }
+
+    #[test]
+    fn upsert_updates_only_compared_columns() {
+        let (a, b) = (row(1, "a"), row(2, "b"));
+        assert_eq!(
+            upsert_sql(DbType::PostgreSQL, &table(), &[&a, &b]),
+            "INSERT INTO \"t\" (\"id\", \"full_name\", \"note\") VALUES\n  (1, 'a', NULL),\n  (2, 'b', NULL)\n\
+             ON CONFLICT (\"id\") DO UPDATE SET \"full_name\" = excluded.\"full_name\";"
+        );
+        assert!(upsert_sql(DbType::MySQL, &table(), &[&a])
+            .ends_with("ON DUPLICATE KEY UPDATE `full_name` = VALUES(`full_name`);"));
+    }
+
+    #[test]
+    fn upsert_of_key_only_tables_leaves_existing_rows() {
+        let mut table = table();
+        table.compared_columns = vec!["id".to_string()];
+        let a = row(1, "a");
+        assert!(upsert_sql(DbType::SQLite, &table, &[&a]).ends_with("ON CONFLICT (\"id\") DO NOTHING;"));
+        assert!(upsert_sql(DbType::MySQL, &table, &[&a]).ends_with("ON DUPLICATE KEY UPDATE `id` = VALUES(`id`);"));
+        assert!(!upsert_sql(DbType::SQLServer, &table, &[&a]).contains("WHEN MATCHED"));
+    }
+
+    #[test]
+    fn merge_matches_on_key_and_inserts_all_columns() {
+        let a = row(1, "a");
+        assert_eq!(
+            upsert_sql(DbType::SQLServer, &table(), &[&a]),
+            "MERGE INTO [t] AS t\nUSING (VALUES\n  (1, N'a', NULL)) AS s ([id], [full_name], [note])\n\
+             ON t.[id] = s.[id]\nWHEN MATCHED THEN UPDATE SET t.[full_name] = s.[full_name]\n\
+             WHEN NOT MATCHED THEN INSERT ([id], [full_name], [note]) VALUES (s.[id], s.[full_name], s.[note]);"
+        );
+    }
+
+    #[test]
+    fn merge_into_identity_columns_allows_explicit_values() {
+        let mut table = table();
+        table.identity_column = Some("id".to_string());
+        let a = row(1, "a");
+        let merge = upsert_sql(DbType::SQLServer, &table, &[&a]);
+        assert!(merge.starts_with("SET IDENTITY_INSERT [t] ON;\nMERGE INTO [t] AS t"));
+        assert!(merge.ends_with(";\nSET IDENTITY_INSERT [t] OFF;"));
+        // Other engines accept explicit values as they are
+        assert!(upsert_sql(DbType::PostgreSQL, &table, &[&a]).starts_with("INSERT INTO"));
+    }
+
+    #[test]
+    fn data_statements_group_rows_and_count_them() {
+        let diffs = [
+            diff(DataDiffType::Insert, 1),
+            diff(DataDiffType::Update, 2),
+            diff(DataDiffType::Delete, 3),
+            diff(DataDiffType::Insert, 4),
+            diff(DataDiffType::Insert, 5),
+        ];
+        let diffs: Vec<&DataDiffResult> = diffs.iter().collect();
+        let options = SqlOptions {
+            mode: SqlMode::Upsert,
+            rows_per_statement: 2,
+        };
+        let statements = data_statements(&diffs, &table(), DbType::PostgreSQL, &options);
+        let rows: Vec<usize> = statements.iter().map(|s| s.rows).collect();
+        assert_eq!(rows, [1, 2, 2]);
+        assert_eq!(statements[0].sql, "-- Delete 3");
+        assert!(statements[1].sql.contains("(1, 'x', NULL),\n  (2, 'x', NULL)"));
+
+        // Keyless tables cannot be upserted; their inserts are grouped, the rest kept per row
+        let mut keyless = table();
+        keyless.key_columns.clear();
+        let statements = data_statements(&diffs, &keyless, DbType::PostgreSQL, &options);
+        let rows: Vec<usize> = statements.iter().map(|s| s.rows).collect();
+        assert_eq!(rows, [1, 1, 2, 1]);
+        assert!(statements[2].sql.starts_with("INSERT INTO"));
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use futures_util::{stream, StreamExt};
+use std::collections::{BTreeSet, HashMap};
+
+use super::plan::dependency_order;
+use super::{
+    compare_table_pair, compare_table_pair_checksums, CompareOptions, DataDiffType, DbConnection, TableComparison,
+    TableDataInfo, DEFAULT_CHUNK_SIZE,
+};
+
+/// Default number of tables compared at the same time
+pub const DEFAULT_TABLE_CONCURRENCY: usize = 4;
+
+impl DbConnection {
+    /// All tables, each after the tables its foreign keys reference
+    pub async fn get_tables_in_dependency_order(&self) -> Result<Vec<String>> {
+        let tables = self.get_tables().await?;
+        let mut foreign_keys = HashMap::new();
+        for table in &tables {
+            foreign_keys.insert(table.as_str(), self.get_foreign_keys(table).await?);
+        }
+
+        let references = foreign_keys
+            .iter()
+            .map(|(table, fks)| (*table, fks.iter().map(|fk| fk.referenced_table.as_str()).collect()))
+            .collect();
+        let names: BTreeSet<String> = tables.iter().cloned().collect();
+        Ok(dependency_order(&names, &references))
+    }
+}
+
+/// Compare the data of one table and count its differences. Without
+/// `keep_diffs` the differences are dropped once counted.
+async fn compare_table_summary(
+    source: &DbConnection,
+    target: &DbConnection,
+    table_name: &str,
+    database: &str,
+    options: &CompareOptions,
+    checksum: bool,
+    keep_diffs: bool,
+) -> Result<(TableDataInfo, TableComparison)> {
+    let mut comparison = if checksum {
+        compare_table_pair_checksums(source, target, table_name, database, options, DEFAULT_CHUNK_SIZE, |_| {}).await?
+    } else {
+        compare_table_pair(source, target, table_name, database, options, DEFAULT_CHUNK_SIZE, |_| {}).await?
+    };
+
+    let diffs = &comparison.diffs;
+    let count = |diff_type: DataDiffType| diffs.iter().filter(|d| d.diff_type == diff_type).count() as i64;
+    let info = TableDataInfo {
+        table_name: table_name.to_string(),
+        columns: comparison.source_table.columns.clone(),
+        primary_keys: comparison.source_table.key_columns.clone(),
+        source_count: comparison.source_count as i64,
+        target_count: comparison.target_count as i64,
+        insert_count: count(DataDiffType::Insert),
+        update_count: count(DataDiffType::Update),
+        delete_count: count(DataDiffType::Delete),
+        error: None,
+    };
+    if !keep_diffs {
+        comparison.diffs = Vec::new();
+    }
+    Ok((info, comparison))
+}
+
+/// Compare one table, turning an error into a summary holding it
+async fn compare_table_or_error(
+    source: &DbConnection,
+    target: &DbConnection,
+    table_name: &str,
+    database: &str,
+    options: &CompareOptions,
+    checksum: bool,
+    keep_diffs: bool,
+) -> (TableDataInfo, Option<TableComparison>) {
+    match compare_table_summary(source, target, table_name, database, options, checksum, keep_diffs).await {
+        Ok((info, comparison)) => (info, Some(comparison)),
+        Err(e) => {
+            let info = TableDataInfo {
+                table_name: table_name.to_string(),
+                error: Some(e.to_string()),
+                ..Default::default()
+            };
+            (info, None)
+        }
+    }
+}
+
+/// Compare the data of every table in `tables`, at most `concurrency`
+/// tables at a time, with the settings of `options` by table name.
+///
+/// Results are in the order of `tables`. A table that cannot be compared
+/// gets the error in its summary and no comparison; the others are still
+/// compared. Without `keep_diffs` the comparisons hold no differences, only
+/// the summaries count them. `on_table` is called with the summary of each
+/// table in turn.
+#[allow(clippy::too_many_arguments)]
+pub async fn compare_tables(
+    source: &DbConnection,
+    target: &DbConnection,
+    tables: &[String],
+    database: &str,
+    options: &HashMap<String, CompareOptions>,
+    checksum: bool,
+    keep_diffs: bool,
+    concurrency: usize,
+    mut on_table: impl FnMut(&TableDataInfo) + Send,
+) -> Vec<(TableDataInfo, Option<TableComparison>)> {
+    let default = CompareOptions::default();
+    // Futures built up front: a closure creating them trips up the Send check of spawned tasks
+    let mut pending = Vec::with_capacity(tables.len());
+    for table in tables {
+        let options = options.get(table).unwrap_or(&default);
+        pending.push(compare_table_or_error(source, target, table, database, options, checksum, keep_diffs));
+    }
+    let mut summaries = stream::iter(pending).buffered(concurrency.max(1));
+
+    let mut results = Vec::with_capacity(tables.len());
+    while let Some(result) = summaries.next().await {
+        on_table(&result.0);
+        results.push(result);
+    }
+    results
+}
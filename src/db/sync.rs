@@ -1,8 +1,15 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 
-use super::{DbConnection, DbType};
+use super::connection::mssql_query;
+use super::dialect::{binary_sort_expr, is_auto_increment, sorts_as_text};
+use super::mapping::unmapped_columns;
+use super::schema::pg_relation;
+use super::watermark::resolve_watermark_edges;
+use super::value::{decode_mssql, decode_mysql, decode_postgres, decode_sqlite};
+use super::{ColumnInfo, DbConnection, DbType, Value};
 
 /// Data diff type
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,14 +24,17 @@ pub enum DataDiffType {
 pub struct DataDiffResult {
     pub diff_type: DataDiffType,
     pub table_name: String,
-    pub primary_key: HashMap<String, String>,
-    pub old_values: Option<HashMap<String, String>>,
-    pub new_values: Option<HashMap<String, String>>,
+    /// Key values of the row; empty for tables without a key
+    pub primary_key: HashMap<String, Value>,
+    /// Target row; for updates only its compared columns
+    pub old_values: Option<HashMap<String, Value>>,
+    /// Source row
+    pub new_values: Option<HashMap<String, Value>>,
     pub sql: String,
 }
 
 /// Table data info for sync
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TableDataInfo {
     pub table_name: String,
     pub columns: Vec<String>,
@@ -34,15 +44,28 @@ pub struct TableDataInfo {
     pub insert_count: i64,
     pub update_count: i64,
     pub delete_count: i64,
+    /// Why the table could not be compared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TableDataInfo {
+    /// Rows to insert, update or delete
+    pub fn difference_count(&self) -> i64 {
+        self.insert_count + self.update_count + self.delete_count
+    }
 }
 
 impl DbConnection {
     /// Get row count for a table
     pub async fn get_row_count(&self, table_name: &str) -> Result<i64> {
         let db_type = self.db_type();
+        // COUNT(*) is an INT on SQL Server
+        let count_fn = if db_type == DbType::SQLServer { "COUNT_BIG(*)" } else { "COUNT(*)" };
         let query = format!(
-            "SELECT COUNT(*) FROM {}",
-            db_type.quote_identifier(table_name)
+            "SELECT {} FROM {}",
+            count_fn,
+            db_type.quote_table_name(table_name)
         );
 
         match self {
@@ -58,14 +81,36 @@ impl DbConnection {
                 let row: (i64,) = sqlx::query_as(&query).fetch_one(pool).await?;
                 Ok(row.0)
             }
-            DbConnection::SQLServer(ref client) => {
-                // SQL Server needs mutable access - return placeholder for now
-                let _ = client;
-                Ok(0)
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(client, &query, &[]).await?;
+                Ok(rows.first().and_then(|r| r.get::<i64, _>(0)).unwrap_or(0))
             }
         }
     }
 
+    /// Count the rows of a compared table that match its filter
+    pub async fn count_rows(&self, table: &KeyedTable) -> Result<u64> {
+        let db_type = self.db_type();
+        let count_fn = if db_type == DbType::SQLServer { "COUNT_BIG(*)" } else { "COUNT(*)" };
+        let query = format!(
+            "SELECT {} FROM {}{}",
+            count_fn,
+            db_type.quote_table_name(&table.name),
+            range_filter(table, &KeyRange::default(), db_type)
+        );
+
+        let count = match self {
+            DbConnection::MySQL(pool) => sqlx::query_as::<_, (i64,)>(&query).fetch_one(pool).await?.0,
+            DbConnection::PostgreSQL(pool) => sqlx::query_as::<_, (i64,)>(&query).fetch_one(pool).await?.0,
+            DbConnection::SQLite(pool) => sqlx::query_as::<_, (i64,)>(&query).fetch_one(pool).await?.0,
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(client, &query, &[]).await?;
+                rows.first().and_then(|r| r.get::<i64, _>(0)).unwrap_or(0)
+            }
+        };
+        Ok(count.max(0) as u64)
+    }
+
     /// Get primary key columns for a table
     pub async fn get_primary_keys(&self, table_name: &str, database: &str) -> Result<Vec<String>> {
         match self {
@@ -88,7 +133,7 @@ impl DbConnection {
                        JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
                        WHERE i.indrelid = $1::regclass AND i.indisprimary"#
                 )
-                .bind(table_name)
+                .bind(pg_relation(table_name))
                 .fetch_all(pool)
                 .await
                 .unwrap_or_default();
@@ -106,73 +151,142 @@ impl DbConnection {
                     .map(|r| r.1)
                     .collect())
             }
-            DbConnection::SQLServer(_) => {
-                // SQL Server needs mutable access
-                Ok(Vec::new())
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(
+                    client,
+                    r#"SELECT kcu.COLUMN_NAME
+                       FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc
+                       JOIN INFORMATION_SCHEMA.KEY_COLUMN_USAGE kcu
+                         ON kcu.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+                        AND kcu.TABLE_SCHEMA = tc.TABLE_SCHEMA
+                        AND kcu.TABLE_NAME = tc.TABLE_NAME
+                       WHERE tc.CONSTRAINT_TYPE = 'PRIMARY KEY'
+                         AND tc.TABLE_SCHEMA = SCHEMA_NAME() AND tc.TABLE_NAME = @P1
+                       ORDER BY kcu.ORDINAL_POSITION"#,
+                    &[&table_name],
+                )
+                .await?;
+                Ok(rows
+                    .iter()
+                    .filter_map(|r| r.get::<&str, _>(0).map(|s| s.to_string()))
+                    .collect())
             }
         }
     }
 
-    /// Get all data from a table as a map keyed by primary key
-    pub async fn get_table_data(
+    /// Get up to `limit` rows of `range`, ordered by key
+    pub async fn get_table_chunk(
         &self,
-        table_name: &str,
-        columns: &[String],
-        primary_keys: &[String],
-    ) -> Result<HashMap<String, HashMap<String, String>>> {
+        table: &KeyedTable,
+        range: &KeyRange,
+        limit: usize,
+    ) -> Result<Vec<HashMap<String, Value>>> {
         let db_type = self.db_type();
-        let quoted_cols: Vec<String> = columns
+        let quoted_cols: Vec<String> = table
+            .columns
             .iter()
-            .map(|c| db_type.quote_identifier(c))
+            .map(|c| table.quote_column(c, db_type))
             .collect();
 
-        let query = format!(
-            "SELECT {} FROM {}",
-            quoted_cols.join(", "),
-            db_type.quote_identifier(table_name)
-        );
+        let query = match db_type {
+            DbType::SQLServer => format!(
+                "SELECT TOP ({}) {} FROM {}{} ORDER BY {}",
+                limit,
+                quoted_cols.join(", "),
+                db_type.quote_table_name(&table.name),
+                range_filter(table, range, db_type),
+                table.key_exprs(db_type).join(", ")
+            ),
+            _ => format!(
+                "SELECT {} FROM {}{} ORDER BY {} LIMIT {}",
+                quoted_cols.join(", "),
+                db_type.quote_table_name(&table.name),
+                range_filter(table, range, db_type),
+                table.key_exprs(db_type).join(", "),
+                limit
+            ),
+        };
 
-        let mut result: HashMap<String, HashMap<String, String>> = HashMap::new();
+        self.fetch_rows(&query, &table.columns).await
+    }
+
+    /// Rows of `table` with the given keys, whatever its filter
+    pub(super) async fn get_rows_by_key(
+        &self,
+        table: &KeyedTable,
+        keys: &[Vec<Value>],
+    ) -> Result<Vec<HashMap<String, Value>>> {
+        let db_type = self.db_type();
+        let quoted_cols: Vec<String> = table.columns.iter().map(|c| table.quote_column(c, db_type)).collect();
+        let mut rows = Vec::new();
+        for chunk in keys.chunks(DEFAULT_CHUNK_SIZE / 2) {
+            let matches: Vec<String> = chunk
+                .iter()
+                .map(|key| {
+                    let terms: Vec<String> = table
+                        .key_columns
+                        .iter()
+                        .zip(key)
+                        .map(|(k, v)| format!("{} = {}", table.quote_column(k, db_type), v.to_sql_literal(db_type)))
+                        .collect();
+                    format!("({})", terms.join(" AND "))
+                })
+                .collect();
+            let query = format!(
+                "SELECT {} FROM {} WHERE {}",
+                quoted_cols.join(", "),
+                db_type.quote_table_name(&table.name),
+                matches.join(" OR ")
+            );
+            rows.extend(self.fetch_rows(&query, &table.columns).await?);
+        }
+        Ok(rows)
+    }
+
+    /// Run a query and stream its rows into maps keyed by `columns`
+    pub(super) async fn fetch_rows(&self, query: &str, columns: &[String]) -> Result<Vec<HashMap<String, Value>>> {
+        Ok(self
+            .fetch_values(query, columns.len())
+            .await?
+            .into_iter()
+            .map(|row| columns.iter().cloned().zip(row).collect())
+            .collect())
+    }
+
+    /// Run a query and decode the first `column_count` columns of every row.
+    ///
+    /// MySQL and PostgreSQL queries go through the simple query protocol,
+    /// which returns values as text whatever their type.
+    pub(super) async fn fetch_values(&self, query: &str, column_count: usize) -> Result<Vec<Vec<Value>>> {
+        use futures_util::TryStreamExt;
+        use sqlx::Executor;
+
+        let mut result: Vec<Vec<Value>> = Vec::new();
 
         match self {
             DbConnection::MySQL(pool) => {
-                let rows = sqlx::query(&query).fetch_all(pool).await?;
-                for row in rows {
-                    let mut row_data: HashMap<String, String> = HashMap::new();
-                    for (i, col) in columns.iter().enumerate() {
-                        let val: Option<String> = sqlx::Row::try_get(&row, i).ok();
-                        row_data.insert(col.clone(), val.unwrap_or_else(|| "NULL".to_string()));
-                    }
-                    let pk_key = build_pk_key(&row_data, primary_keys);
-                    result.insert(pk_key, row_data);
+                let mut rows = pool.fetch(query);
+                while let Some(row) = rows.try_next().await? {
+                    result.push((0..column_count).map(|i| decode_mysql(&row, i)).collect::<Result<_>>()?);
                 }
             }
             DbConnection::PostgreSQL(pool) => {
-                let rows = sqlx::query(&query).fetch_all(pool).await?;
-                for row in rows {
-                    let mut row_data: HashMap<String, String> = HashMap::new();
-                    for (i, col) in columns.iter().enumerate() {
-                        let val: Option<String> = sqlx::Row::try_get(&row, i).ok();
-                        row_data.insert(col.clone(), val.unwrap_or_else(|| "NULL".to_string()));
-                    }
-                    let pk_key = build_pk_key(&row_data, primary_keys);
-                    result.insert(pk_key, row_data);
+                let mut rows = pool.fetch(query);
+                while let Some(row) = rows.try_next().await? {
+                    result.push((0..column_count).map(|i| decode_postgres(&row, i)).collect::<Result<_>>()?);
                 }
             }
             DbConnection::SQLite(pool) => {
-                let rows = sqlx::query(&query).fetch_all(pool).await?;
-                for row in rows {
-                    let mut row_data: HashMap<String, String> = HashMap::new();
-                    for (i, col) in columns.iter().enumerate() {
-                        let val: Option<String> = sqlx::Row::try_get(&row, i).ok();
-                        row_data.insert(col.clone(), val.unwrap_or_else(|| "NULL".to_string()));
-                    }
-                    let pk_key = build_pk_key(&row_data, primary_keys);
-                    result.insert(pk_key, row_data);
+                let mut rows = sqlx::query(query).fetch(pool);
+                while let Some(row) = rows.try_next().await? {
+                    result.push((0..column_count).map(|i| decode_sqlite(&row, i)).collect::<Result<_>>()?);
                 }
             }
-            DbConnection::SQLServer(_) => {
-                // SQL Server needs mutable access
+            DbConnection::SQLServer(client) => {
+                let rows = mssql_query(client, query, &[]).await?;
+                for row in rows {
+                    result.push(row.into_iter().take(column_count).map(decode_mssql).collect());
+                }
             }
         }
 
@@ -186,7 +300,7 @@ impl DbConnection {
         columns: &[String],
         page: usize,
         page_size: usize,
-    ) -> Result<Vec<Vec<String>>> {
+    ) -> Result<Vec<Vec<Value>>> {
         let db_type = self.db_type();
         let quoted_cols: Vec<String> = columns
             .iter()
@@ -199,71 +313,622 @@ impl DbConnection {
             DbType::SQLServer => format!(
                 "SELECT {} FROM {} ORDER BY (SELECT NULL) OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
                 quoted_cols.join(", "),
-                db_type.quote_identifier(table_name),
+                db_type.quote_table_name(table_name),
                 offset,
                 page_size
             ),
             _ => format!(
                 "SELECT {} FROM {} LIMIT {} OFFSET {}",
                 quoted_cols.join(", "),
-                db_type.quote_identifier(table_name),
+                db_type.quote_table_name(table_name),
                 page_size,
                 offset
             ),
         };
 
-        let mut result: Vec<Vec<String>> = Vec::new();
+        self.fetch_values(&query, columns.len()).await
+    }
+}
 
-        match self {
-            DbConnection::MySQL(pool) => {
-                let rows = sqlx::query(&query).fetch_all(pool).await?;
-                for row in rows {
-                    let mut row_data: Vec<String> = Vec::new();
-                    for i in 0..columns.len() {
-                        let val: Option<String> = sqlx::Row::try_get(&row, i).ok();
-                        row_data.push(val.unwrap_or_else(|| "NULL".to_string()));
-                    }
-                    result.push(row_data);
-                }
-            }
-            DbConnection::PostgreSQL(pool) => {
-                let rows = sqlx::query(&query).fetch_all(pool).await?;
-                for row in rows {
-                    let mut row_data: Vec<String> = Vec::new();
-                    for i in 0..columns.len() {
-                        let val: Option<String> = sqlx::Row::try_get(&row, i).ok();
-                        row_data.push(val.unwrap_or_else(|| "NULL".to_string()));
-                    }
-                    result.push(row_data);
-                }
+/// Default number of rows fetched per chunk when comparing table data
+pub const DEFAULT_CHUNK_SIZE: usize = 1000;
+
+/// Progress of a table data comparison
+#[derive(Debug, Clone, Default)]
+pub struct CompareProgress {
+    pub table_name: String,
+    /// Rows of source and target checked so far
+    pub rows_read: u64,
+    /// Rows in source and target together, counted before the comparison starts
+    pub total_rows: u64,
+    /// Rows transferred from the databases; less than `rows_read` in checksum mode
+    pub rows_fetched: u64,
+    pub differences: usize,
+}
+
+impl CompareProgress {
+    /// Fraction of rows checked, between 0.0 and 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.total_rows == 0 {
+            1.0
+        } else {
+            (self.rows_read as f64 / self.total_rows as f64).min(1.0)
+        }
+    }
+}
+
+/// Per-table settings of a data comparison
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompareOptions {
+    /// Columns identifying a row. Empty: the primary key, else the first
+    /// unique index on non-nullable columns. The columns must be unique and
+    /// non-null on both sides.
+    #[serde(default)]
+    pub key_columns: Vec<String>,
+    /// SQL condition restricting the compared rows, applied on the source
+    /// and, unless `target_filter` is set, as written on the target. Empty:
+    /// all rows.
+    #[serde(default)]
+    pub filter: String,
+    /// Condition restricting the target rows, for a `filter` naming columns
+    /// or tables that are named differently in the target. Empty: `filter`.
+    #[serde(default)]
+    pub target_filter: String,
+    /// Column name patterns to compare (`*` and `?` wildcards). Empty: all columns.
+    #[serde(default)]
+    pub include_columns: Vec<String>,
+    /// Column name patterns to ignore when comparing and updating rows
+    #[serde(default)]
+    pub exclude_columns: Vec<String>,
+    /// Name of the table in the target. Empty: the source name.
+    #[serde(default)]
+    pub target_table: String,
+    /// Target names of the source columns named differently in the target
+    #[serde(default)]
+    pub column_map: BTreeMap<String, String>,
+    /// Column that only grows as rows are added or changed, such as an
+    /// `updated_at` timestamp or an auto-increment id. Empty: none.
+    #[serde(default)]
+    pub watermark_column: String,
+    /// Watermark of the last sync: only rows whose watermark column is at
+    /// or beyond it are compared. Read from the watermark store, not saved
+    /// with the settings.
+    #[serde(skip)]
+    pub watermark: Option<String>,
+}
+
+impl CompareOptions {
+    /// Name of source table `table` in the target
+    pub fn target_table<'a>(&'a self, table: &'a str) -> &'a str {
+        if self.target_table.is_empty() {
+            table
+        } else {
+            &self.target_table
+        }
+    }
+
+    /// Name of source column `column` in the target
+    pub fn target_column<'a>(&'a self, column: &'a str) -> &'a str {
+        self.column_map.get(column).map(String::as_str).unwrap_or(column)
+    }
+
+    /// Whether only rows at or beyond a synced watermark are compared
+    pub fn is_incremental(&self) -> bool {
+        !self.watermark_column.is_empty() && self.watermark.is_some()
+    }
+
+    /// Row filter of the target side
+    pub fn target_filter(&self) -> &str {
+        if self.target_filter.trim().is_empty() {
+            &self.filter
+        } else {
+            &self.target_filter
+        }
+    }
+
+    /// Condition restricting the compared rows of one side to `filter`,
+    /// whose watermark column is named `watermark_column`
+    fn row_filter(&self, filter: &str, watermark_column: &str, db_type: DbType) -> Option<String> {
+        let mut conditions = Vec::new();
+        if !filter.trim().is_empty() {
+            conditions.push(filter.trim().to_string());
+        }
+        if let (true, Some(watermark)) = (self.is_incremental(), &self.watermark) {
+            // Rows at the watermark itself are compared again, in case more
+            // were written with the same value after the last sync
+            conditions.push(format!(
+                "{} >= {}",
+                db_type.quote_identifier(watermark_column),
+                watermark_literal(watermark, db_type)
+            ));
+        }
+        match conditions.len() {
+            0 => None,
+            1 => conditions.pop(),
+            _ => Some(conditions.iter().map(|c| format!("({})", c)).collect::<Vec<_>>().join(" AND ")),
+        }
+    }
+}
+
+/// SQL literal of a stored watermark: integers as numbers, anything else,
+/// such as a timestamp, as text the database converts to the column type
+fn watermark_literal(watermark: &str, db_type: DbType) -> String {
+    match watermark.parse::<i64>() {
+        Ok(number) => number.to_string(),
+        Err(_) => Value::Text(watermark.to_string()).to_sql_literal(db_type),
+    }
+}
+
+/// Whether a column name matches a pattern with `*` (any run of characters)
+/// and `?` (one character) wildcards, ignoring ASCII case
+pub fn column_matches(pattern: &str, column: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let column: Vec<char> = column.to_ascii_lowercase().chars().collect();
+
+    // Greedy match, backtracking to the last `*`
+    let (mut p, mut c) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while c < column.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == column[c]) {
+            p += 1;
+            c += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, c));
+            p += 1;
+        } else if let Some((star_p, star_c)) = star {
+            p = star_p + 1;
+            c = star_c + 1;
+            star = Some((star_p, star_c + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+/// One side of a table being compared: its columns and the columns
+/// identifying a row. Columns are named as in the source on both sides.
+#[derive(Debug, Clone)]
+pub struct KeyedTable {
+    pub name: String,
+    pub columns: Vec<String>,
+    /// Names of the columns in this side's table where they differ from
+    /// the source names
+    pub column_names: HashMap<String, String>,
+    /// Empty when the table has no primary key or usable unique index
+    pub key_columns: Vec<String>,
+    /// Key columns holding text (or SQL Server GUIDs), which are ordered by
+    /// their UTF-8 bytes
+    pub text_keys: Vec<bool>,
+    /// Columns checked for equality and written by UPDATEs, always including
+    /// the key. The others are only copied by INSERTs.
+    pub compared_columns: Vec<String>,
+    /// Condition restricting the compared rows
+    pub filter: Option<String>,
+    /// Auto-incrementing column of this side's table on SQL Server (IDENTITY)
+    /// or PostgreSQL (serial or identity), by its source name. Explicit values
+    /// need `IDENTITY_INSERT` on SQL Server and leave the sequence behind on
+    /// PostgreSQL.
+    pub identity_column: Option<String>,
+}
+
+impl KeyedTable {
+    /// Read the columns of `table_name` and pick its row key: the key
+    /// columns of `options` if given, else the primary key, else a unique
+    /// index on non-nullable columns
+    pub async fn load(conn: &DbConnection, table_name: &str, database: &str, options: &CompareOptions) -> Result<Self> {
+        let columns = conn.get_columns(table_name).await?;
+        let key_columns = &options.key_columns;
+        let watermark_column = &options.watermark_column;
+        if !watermark_column.is_empty() && !columns.iter().any(|c| &c.name == watermark_column) {
+            return Err(anyhow::anyhow!("Watermark column {} not found in table {}", watermark_column, table_name));
+        }
+
+        let key_columns = if !key_columns.is_empty() {
+            if let Some(missing) = key_columns.iter().find(|k| !columns.iter().any(|c| &c.name == *k)) {
+                return Err(anyhow::anyhow!("Key column {} not found in table {}", missing, table_name));
             }
-            DbConnection::SQLite(pool) => {
-                let rows = sqlx::query(&query).fetch_all(pool).await?;
-                for row in rows {
-                    let mut row_data: Vec<String> = Vec::new();
-                    for i in 0..columns.len() {
-                        let val: Option<String> = sqlx::Row::try_get(&row, i).ok();
-                        row_data.push(val.unwrap_or_else(|| "NULL".to_string()));
-                    }
-                    result.push(row_data);
-                }
+            key_columns.to_vec()
+        } else {
+            let primary_keys = conn.get_primary_keys(table_name, database).await?;
+            if !primary_keys.is_empty() {
+                primary_keys
+            } else {
+                // NULLs are not equal to each other, so a key must not allow them
+                conn.get_indexes(table_name)
+                    .await?
+                    .into_iter()
+                    .find(|index| {
+                        index.is_unique
+                            && index
+                                .columns
+                                .iter()
+                                .all(|k| columns.iter().any(|c| &c.name == k && !c.nullable))
+                    })
+                    .map(|index| index.columns)
+                    .unwrap_or_default()
             }
-            DbConnection::SQLServer(_) => {
-                // SQL Server needs mutable access
+        };
+
+        if !watermark_column.is_empty() && key_columns.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Table {} has no key; a watermark needs one to match changed rows",
+                table_name
+            ));
+        }
+
+        let text_keys = key_columns
+            .iter()
+            .map(|key| {
+                columns
+                    .iter()
+                    .find(|c| &c.name == key)
+                    .map(|c| sorts_as_text(&c.data_type))
+                    .unwrap_or(false)
+            })
+            .collect();
+
+        let identity_column = identity_column(&columns, conn.db_type()).map(|c| c.name.clone());
+        let columns: Vec<String> = columns.into_iter().map(|c| c.name).collect();
+        let compared_columns = columns
+            .iter()
+            .filter(|c| {
+                key_columns.contains(c)
+                    || ((options.include_columns.is_empty()
+                        || options.include_columns.iter().any(|p| column_matches(p, c)))
+                        && !options.exclude_columns.iter().any(|p| column_matches(p, c)))
+            })
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            name: table_name.to_string(),
+            columns,
+            column_names: HashMap::new(),
+            key_columns,
+            text_keys,
+            compared_columns,
+            filter: options.row_filter(&options.filter, watermark_column, conn.db_type()),
+            identity_column,
+        })
+    }
+
+    /// Load the source table and its counterpart in the target, named as
+    /// `options` maps them. Source columns missing from the target are left
+    /// out, unless they are compared: then loading fails.
+    pub async fn load_pair(
+        source: &DbConnection,
+        target: &DbConnection,
+        table_name: &str,
+        database: &str,
+        options: &CompareOptions,
+    ) -> Result<(Self, Self)> {
+        let mut source_table = Self::load(source, table_name, database, options).await?;
+
+        let target_name = options.target_table(table_name);
+        let target_column_info = target.get_columns(target_name).await?;
+        let target_columns: Vec<String> = target_column_info.iter().map(|c| c.name.clone()).collect();
+        if target_columns.is_empty() {
+            return Err(anyhow::anyhow!("Table {} not found in target", target_name));
+        }
+
+        let unmapped = unmapped_columns(&source_table.columns, &target_columns, options);
+        let missing: Vec<&str> = unmapped
+            .source
+            .iter()
+            .filter(|c| source_table.compared_columns.contains(c))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Columns of {} missing in target table {}: {}. Map or exclude them.",
+                table_name,
+                target_name,
+                missing.join(", ")
+            ));
+        }
+        source_table.columns.retain(|c| !unmapped.source.contains(c));
+
+        // The source filter is reused on the target, where mapped columns have other names
+        if options.target_filter.trim().is_empty() {
+            let words: Vec<&str> = options
+                .filter
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .collect();
+            let renamed: Vec<&str> = options
+                .column_map
+                .iter()
+                .filter(|(source, target)| source != target && words.iter().any(|w| w.eq_ignore_ascii_case(source)))
+                .map(|(source, _)| source.as_str())
+                .collect();
+            if !renamed.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Filter of {} names columns renamed in target table {}: {}. Set a target filter.",
+                    table_name,
+                    target_name,
+                    renamed.join(", ")
+                ));
             }
         }
 
-        Ok(result)
+        let mut target_table = source_table.clone();
+        target_table.name = target_name.to_string();
+        target_table.filter = options.row_filter(
+            options.target_filter(),
+            options.target_column(&options.watermark_column),
+            target.db_type(),
+        );
+        target_table.column_names = source_table
+            .columns
+            .iter()
+            .filter_map(|c| {
+                let name = options.target_column(c);
+                (name != c).then(|| (c.clone(), name.to_string()))
+            })
+            .collect();
+        target_table.identity_column = identity_column(&target_column_info, target.db_type()).and_then(|identity| {
+            source_table.columns.iter().find(|c| options.target_column(c) == identity.name).cloned()
+        });
+
+        Ok((source_table, target_table))
+    }
+
+    /// Whether rows can be matched by key; keyless tables are compared as
+    /// multisets of rows
+    pub fn has_key(&self) -> bool {
+        !self.key_columns.is_empty()
+    }
+
+    /// Quoted name of `column` in this side's table
+    pub(super) fn quote_column(&self, column: &str, db_type: DbType) -> String {
+        db_type.quote_identifier(self.column_names.get(column).map(String::as_str).unwrap_or(column))
+    }
+
+    /// Key expressions to order and filter by
+    pub(super) fn key_exprs(&self, db_type: DbType) -> Vec<String> {
+        self.key_columns
+            .iter()
+            .zip(&self.text_keys)
+            .map(|(pk, is_text)| binary_sort_expr(&self.quote_column(pk, db_type), *is_text, db_type))
+            .collect()
+    }
+
+    /// Whether two rows agree in every compared column
+    pub(super) fn rows_equal(&self, a: &HashMap<String, Value>, b: &HashMap<String, Value>) -> bool {
+        self.compared_columns.iter().all(|c| a.get(c) == b.get(c))
+    }
+
+    /// The compared columns of a row
+    pub(super) fn compared_values(&self, row: &HashMap<String, Value>) -> HashMap<String, Value> {
+        self.compared_columns
+            .iter()
+            .filter_map(|c| row.get(c).map(|v| (c.clone(), v.clone())))
+            .collect()
+    }
+
+    /// Key values of a row, in key column order
+    pub(super) fn key_of(&self, row: &HashMap<String, Value>) -> Vec<Value> {
+        self.key_columns
+            .iter()
+            .map(|pk| row.get(pk).cloned().unwrap_or(Value::Null))
+            .collect()
     }
 }
 
-/// Build a unique key from primary key values
-fn build_pk_key(row: &HashMap<String, String>, primary_keys: &[String]) -> String {
-    primary_keys
+/// The IDENTITY column of a SQL Server table, or the serial or identity
+/// column of a PostgreSQL table
+fn identity_column(columns: &[ColumnInfo], db_type: DbType) -> Option<&ColumnInfo> {
+    match db_type {
+        DbType::SQLServer | DbType::PostgreSQL => columns.iter().find(|c| is_auto_increment(c, db_type)),
+        _ => None,
+    }
+}
+
+/// Wrap an INSERT into `table` in `SET IDENTITY_INSERT` when it writes the
+/// table's SQL Server IDENTITY column. Kept in the same statement, as the
+/// setting does not outlive the batch it is set in.
+pub(super) fn with_identity_insert(db_type: DbType, table: &KeyedTable, insert: String) -> String {
+    match &table.identity_column {
+        Some(column) if db_type == DbType::SQLServer && table.columns.contains(column) => {
+            let name = db_type.quote_table_name(&table.name);
+            format!("SET IDENTITY_INSERT {} ON;\n{}\nSET IDENTITY_INSERT {} OFF;", name, insert, name)
+        }
+        _ => insert,
+    }
+}
+
+/// Range of row keys: after `after` (exclusive) up to `until` (inclusive).
+/// A missing bound is unbounded.
+#[derive(Debug, Clone, Default)]
+pub struct KeyRange {
+    pub after: Option<Vec<Value>>,
+    pub until: Option<Vec<Value>>,
+}
+
+/// Condition matching keys of `table` greater than `values`:
+/// (k1 > v1) OR (k1 = v1 AND k2 > v2) OR ...
+fn keys_after(table: &KeyedTable, values: &[Value], db_type: DbType) -> String {
+    let key_exprs = table.key_exprs(db_type);
+    // Literals are ordered like the key expressions they are compared to
+    let literals: Vec<String> = values
         .iter()
-        .map(|pk| row.get(pk).map(|v| v.as_str()).unwrap_or(""))
-        .collect::<Vec<_>>()
-        .join("|")
+        .zip(&table.text_keys)
+        .map(|(value, is_text)| {
+            let literal = match (value, db_type) {
+                // SQL Server writes GUIDs as uppercase text, as the key column does
+                (Value::Uuid(_), DbType::SQLServer) => {
+                    format!("CAST({} AS uniqueidentifier)", value.to_sql_literal(db_type))
+                }
+                _ => value.to_sql_literal(db_type),
+            };
+            binary_sort_expr(&literal, *is_text, db_type)
+        })
+        .collect();
+    let branches: Vec<String> = (0..key_exprs.len().min(literals.len()))
+        .map(|i| {
+            let mut terms: Vec<String> = (0..i)
+                .map(|j| format!("{} = {}", key_exprs[j], literals[j]))
+                .collect();
+            terms.push(format!("{} > {}", key_exprs[i], literals[i]));
+            format!("({})", terms.join(" AND "))
+        })
+        .collect();
+    format!("({})", branches.join(" OR "))
+}
+
+/// WHERE clause restricting a query to the filter of `table` and `range`,
+/// empty for the whole table
+pub(super) fn range_filter(table: &KeyedTable, range: &KeyRange, db_type: DbType) -> String {
+    let mut conditions = Vec::new();
+    if let Some(filter) = &table.filter {
+        conditions.push(format!("({})", filter));
+    }
+    if let Some(after) = &range.after {
+        conditions.push(keys_after(table, after, db_type));
+    }
+    if let Some(until) = &range.until {
+        conditions.push(format!("NOT {}", keys_after(table, until, db_type)));
+    }
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    }
+}
+
+/// Reads a key range of one side of a comparison chunk by chunk, in key order
+pub(super) struct ChunkReader<'a> {
+    conn: &'a DbConnection,
+    table: &'a KeyedTable,
+    range: KeyRange,
+    chunk_size: usize,
+    buffer: VecDeque<HashMap<String, Value>>,
+    exhausted: bool,
+}
+
+impl<'a> ChunkReader<'a> {
+    pub(super) fn new(conn: &'a DbConnection, table: &'a KeyedTable, range: KeyRange, chunk_size: usize) -> Self {
+        Self {
+            conn,
+            table,
+            range,
+            chunk_size: chunk_size.max(1),
+            buffer: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    /// Make sure the next row is buffered. Returns the number of rows fetched.
+    async fn fill(&mut self) -> Result<usize> {
+        if !self.buffer.is_empty() || self.exhausted {
+            return Ok(0);
+        }
+        let rows = self.conn.get_table_chunk(self.table, &self.range, self.chunk_size).await?;
+        self.exhausted = rows.len() < self.chunk_size;
+        if let Some(last) = rows.last() {
+            self.range.after = Some(self.table.key_of(last));
+        }
+        let fetched = rows.len();
+        self.buffer.extend(rows);
+        Ok(fetched)
+    }
+}
+
+/// Compare row keys the way the databases order them: text keys by their
+/// UTF-8 bytes (see `binary_sort_expr`), numbers by value
+fn compare_keys(a: &[Value], b: &[Value], text_keys: &[bool]) -> Ordering {
+    for ((a, b), is_text) in a.iter().zip(b).zip(text_keys) {
+        let ordering = match (a, b) {
+            (Value::Int(x), Value::Int(y)) => x.cmp(y),
+            (Value::UInt(x), Value::UInt(y)) => x.cmp(y),
+            (Value::Bytes(x), Value::Bytes(y)) => x.cmp(y),
+            // Engines differ in the case they give GUIDs
+            (Value::Uuid(x), Value::Uuid(y)) => x.to_ascii_lowercase().cmp(&y.to_ascii_lowercase()),
+            _ if *is_text => a.to_string().as_bytes().cmp(b.to_string().as_bytes()),
+            _ => match (a.to_string().parse::<i128>(), b.to_string().parse::<i128>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                _ => match (a.to_string().parse::<f64>(), b.to_string().parse::<f64>()) {
+                    (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+                    _ => a.to_string().as_bytes().cmp(b.to_string().as_bytes()),
+                },
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Merge join two readers over the same key range, appending the differences
+/// to `results`. Progress is reported after every chunk.
+pub(super) async fn merge_compare(
+    mut source_rows: ChunkReader<'_>,
+    mut target_rows: ChunkReader<'_>,
+    target_db_type: DbType,
+    results: &mut Vec<DataDiffResult>,
+    progress: &mut CompareProgress,
+    on_progress: &mut (impl FnMut(&CompareProgress) + Send),
+) -> Result<()> {
+    let table = source_rows.table;
+    // Statements are run on the target, under its names
+    let target_table = target_rows.table;
+
+    loop {
+        let fetched = (source_rows.fill().await? + target_rows.fill().await?) as u64;
+        if fetched > 0 {
+            progress.rows_read += fetched;
+            progress.rows_fetched += fetched;
+            progress.differences = results.len();
+            on_progress(progress);
+        }
+
+        let ordering = match (source_rows.buffer.front(), target_rows.buffer.front()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(s), Some(t)) => compare_keys(&table.key_of(s), &table.key_of(t), &table.text_keys),
+        };
+
+        match ordering {
+            // Only in source: insert
+            Ordering::Less => {
+                let source_row = source_rows.buffer.pop_front().unwrap_or_default();
+                let pk = extract_primary_key(&source_row, &table.key_columns);
+                results.push(DataDiffResult {
+                    diff_type: DataDiffType::Insert,
+                    table_name: table.name.clone(),
+                    primary_key: pk,
+                    old_values: None,
+                    sql: generate_insert_sql(target_db_type, target_table, &source_row),
+                    new_values: Some(source_row),
+                });
+            }
+            // Only in target: delete
+            Ordering::Greater => {
+                let target_row = target_rows.buffer.pop_front().unwrap_or_default();
+                let pk = extract_primary_key(&target_row, &table.key_columns);
+                results.push(DataDiffResult {
+                    diff_type: DataDiffType::Delete,
+                    table_name: table.name.clone(),
+                    sql: generate_delete_sql(target_db_type, target_table, &pk),
+                    primary_key: pk,
+                    old_values: Some(target_row),
+                    new_values: None,
+                });
+            }
+            // In both: update if any value differs
+            Ordering::Equal => {
+                let source_row = source_rows.buffer.pop_front().unwrap_or_default();
+                let target_row = target_rows.buffer.pop_front().unwrap_or_default();
+                if !table.rows_equal(&source_row, &target_row) {
+                    results.push(update_diff(target_db_type, table, target_table, source_row, &target_row));
+                }
+            }
+        }
+    }
+
+    Ok(())
 }
 
 /// Compare data between two connections
@@ -272,155 +937,386 @@ pub async fn compare_table_data(
     target: &DbConnection,
     table_name: &str,
     database: &str,
+    options: &CompareOptions,
 ) -> Result<Vec<DataDiffResult>> {
-    let target_db_type = target.db_type();
+    compare_table_data_chunked(source, target, table_name, database, options, DEFAULT_CHUNK_SIZE, |_| {}).await
+}
 
-    // Get primary keys from source
-    let primary_keys = source.get_primary_keys(table_name, database).await?;
-    if primary_keys.is_empty() {
-        return Err(anyhow::anyhow!("Table {} has no primary key", table_name));
-    }
+/// Outcome of comparing one table: both sides as loaded, the rows each
+/// holds within the filter, and the differences found
+#[derive(Debug, Clone)]
+pub struct TableComparison {
+    pub source_table: KeyedTable,
+    pub target_table: KeyedTable,
+    pub source_count: u64,
+    pub target_count: u64,
+    pub diffs: Vec<DataDiffResult>,
+}
 
-    // Get columns from source
-    let columns = source.get_columns(table_name).await?;
-    let column_names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+/// Compare data between two connections with a merge join over both tables
+/// read in key order, `chunk_size` rows at a time, so memory stays
+/// bounded by the chunk size and the differences found.
+/// `on_progress` is called after every chunk.
+pub async fn compare_table_data_chunked(
+    source: &DbConnection,
+    target: &DbConnection,
+    table_name: &str,
+    database: &str,
+    options: &CompareOptions,
+    chunk_size: usize,
+    on_progress: impl FnMut(&CompareProgress) + Send,
+) -> Result<Vec<DataDiffResult>> {
+    let comparison = compare_table_pair(source, target, table_name, database, options, chunk_size, on_progress).await?;
+    Ok(comparison.diffs)
+}
 
-    // Get data from both
-    let source_data = source
-        .get_table_data(table_name, &column_names, &primary_keys)
-        .await?;
-    let target_data = target
-        .get_table_data(table_name, &column_names, &primary_keys)
-        .await?;
+/// Compare data like `compare_table_data_chunked`, also returning the
+/// loaded tables and their row counts
+pub async fn compare_table_pair(
+    source: &DbConnection,
+    target: &DbConnection,
+    table_name: &str,
+    database: &str,
+    options: &CompareOptions,
+    chunk_size: usize,
+    mut on_progress: impl FnMut(&CompareProgress) + Send,
+) -> Result<TableComparison> {
+    let (source_table, target_table) = KeyedTable::load_pair(source, target, table_name, database, options).await?;
+    let source_count = source.count_rows(&source_table).await?;
+    let target_count = target.count_rows(&target_table).await?;
+
+    let mut progress = CompareProgress {
+        table_name: table_name.to_string(),
+        total_rows: source_count + target_count,
+        ..Default::default()
+    };
+    on_progress(&progress);
+
+    if !source_table.has_key() {
+        let diffs = compare_keyless(source, target, &source_table, &target_table, progress, on_progress).await?;
+        return Ok(TableComparison {
+            source_table,
+            target_table,
+            source_count,
+            target_count,
+            diffs,
+        });
+    }
 
     let mut results = Vec::new();
+    merge_compare(
+        ChunkReader::new(source, &source_table, KeyRange::default(), chunk_size),
+        ChunkReader::new(target, &target_table, KeyRange::default(), chunk_size),
+        target.db_type(),
+        &mut results,
+        &mut progress,
+        &mut on_progress,
+    )
+    .await?;
+    if options.is_incremental() {
+        results = resolve_watermark_edges(source, target, &source_table, &target_table, results).await?;
+    }
 
-    // Find inserts and updates
-    for (pk_key, source_row) in &source_data {
-        if let Some(target_row) = target_data.get(pk_key) {
-            // Check for updates
-            if source_row != target_row {
-                let pk = extract_primary_key(source_row, &primary_keys);
-                results.push(DataDiffResult {
-                    diff_type: DataDiffType::Update,
-                    table_name: table_name.to_string(),
-                    primary_key: pk.clone(),
-                    old_values: Some(target_row.clone()),
-                    new_values: Some(source_row.clone()),
-                    sql: generate_update_sql(target_db_type, table_name, source_row, &primary_keys),
-                });
-            }
-        } else {
-            // Insert
-            let pk = extract_primary_key(source_row, &primary_keys);
-            results.push(DataDiffResult {
-                diff_type: DataDiffType::Insert,
-                table_name: table_name.to_string(),
-                primary_key: pk,
-                old_values: None,
-                new_values: Some(source_row.clone()),
-                sql: generate_insert_sql(target_db_type, table_name, source_row, &column_names),
-            });
-        }
+    progress.differences = results.len();
+    on_progress(&progress);
+
+    Ok(TableComparison {
+        source_table,
+        target_table,
+        source_count,
+        target_count,
+        diffs: results,
+    })
+}
+
+/// Compare a table without a key as two multisets of rows. Rows identical
+/// in the compared columns are counted on both sides; every copy the target lacks becomes an INSERT
+/// and every surplus copy a DELETE of a single matching row.
+pub(super) async fn compare_keyless(
+    source: &DbConnection,
+    target: &DbConnection,
+    table: &KeyedTable,
+    target_table: &KeyedTable,
+    mut progress: CompareProgress,
+    mut on_progress: impl FnMut(&CompareProgress) + Send,
+) -> Result<Vec<DataDiffResult>> {
+    let query = |side: &KeyedTable, db_type: DbType| {
+        let cols: Vec<String> = side.columns.iter().map(|c| side.quote_column(c, db_type)).collect();
+        format!(
+            "SELECT {} FROM {}{}",
+            cols.join(", "),
+            db_type.quote_table_name(&side.name),
+            range_filter(side, &KeyRange::default(), db_type)
+        )
+    };
+    let fingerprint = |row: &HashMap<String, Value>| -> String {
+        table
+            .compared_columns
+            .iter()
+            .map(|c| row.get(c).unwrap_or(&Value::Null).fingerprint())
+            .collect::<Vec<_>>()
+            .join("\u{1f}")
+    };
+
+    // Identical rows: source count minus target count, and one copy of the row
+    let mut counts: BTreeMap<String, (i64, HashMap<String, Value>)> = BTreeMap::new();
+    let source_rows = source.fetch_rows(&query(table, source.db_type()), &table.columns).await?;
+    progress.rows_read += source_rows.len() as u64;
+    progress.rows_fetched += source_rows.len() as u64;
+    on_progress(&progress);
+    for row in source_rows {
+        counts.entry(fingerprint(&row)).or_insert((0, row)).0 += 1;
+    }
+
+    let target_rows = target.fetch_rows(&query(target_table, target.db_type()), &table.columns).await?;
+    progress.rows_read += target_rows.len() as u64;
+    progress.rows_fetched += target_rows.len() as u64;
+    on_progress(&progress);
+    for row in target_rows {
+        counts.entry(fingerprint(&row)).or_insert((0, row)).0 -= 1;
     }
 
-    // Find deletes
-    for (pk_key, target_row) in &target_data {
-        if !source_data.contains_key(pk_key) {
-            let pk = extract_primary_key(target_row, &primary_keys);
-            results.push(DataDiffResult {
-                diff_type: DataDiffType::Delete,
-                table_name: table_name.to_string(),
-                primary_key: pk.clone(),
-                old_values: Some(target_row.clone()),
-                new_values: None,
-                sql: generate_delete_sql(target_db_type, table_name, &primary_keys, &pk),
+    let db_type = target.db_type();
+    let mut results = Vec::new();
+    for (count, row) in counts.into_values() {
+        for _ in 0..count.unsigned_abs() {
+            results.push(if count > 0 {
+                DataDiffResult {
+                    diff_type: DataDiffType::Insert,
+                    table_name: table.name.clone(),
+                    primary_key: HashMap::new(),
+                    old_values: None,
+                    sql: generate_insert_sql(db_type, target_table, &row),
+                    new_values: Some(row.clone()),
+                }
+            } else {
+                DataDiffResult {
+                    diff_type: DataDiffType::Delete,
+                    table_name: table.name.clone(),
+                    primary_key: HashMap::new(),
+                    old_values: Some(row.clone()),
+                    sql: generate_delete_one_sql(db_type, target_table, &row)?,
+                    new_values: None,
+                }
             });
         }
     }
 
+    progress.differences = results.len();
+    on_progress(&progress);
+
     Ok(results)
 }
 
-fn extract_primary_key(row: &HashMap<String, String>, primary_keys: &[String]) -> HashMap<String, String> {
+/// Update of the target row `target_row` of `table` to the values of `source_row`
+pub(super) fn update_diff(
+    db_type: DbType,
+    table: &KeyedTable,
+    target_table: &KeyedTable,
+    source_row: HashMap<String, Value>,
+    target_row: &HashMap<String, Value>,
+) -> DataDiffResult {
+    DataDiffResult {
+        diff_type: DataDiffType::Update,
+        table_name: table.name.clone(),
+        primary_key: extract_primary_key(&source_row, &table.key_columns),
+        old_values: Some(table.compared_values(target_row)),
+        sql: generate_update_sql(db_type, target_table, &target_table.compared_values(&source_row)),
+        new_values: Some(source_row),
+    }
+}
+
+fn extract_primary_key(row: &HashMap<String, Value>, primary_keys: &[String]) -> HashMap<String, Value> {
     primary_keys
         .iter()
         .filter_map(|pk| row.get(pk).map(|v| (pk.clone(), v.clone())))
         .collect()
 }
 
-fn generate_insert_sql(
-    db_type: DbType,
-    table_name: &str,
-    row: &HashMap<String, String>,
-    columns: &[String],
-) -> String {
-    let cols: Vec<String> = columns
+fn generate_insert_sql(db_type: DbType, table: &KeyedTable, row: &HashMap<String, Value>) -> String {
+    let cols: Vec<String> = table
+        .columns
         .iter()
         .filter(|c| row.contains_key(*c))
-        .map(|c| db_type.quote_identifier(c))
+        .map(|c| table.quote_column(c, db_type))
         .collect();
 
-    let vals: Vec<String> = columns
+    let vals: Vec<String> = table
+        .columns
         .iter()
         .filter_map(|c| row.get(c))
-        .map(|v| escape_value(v))
+        .map(|v| v.to_sql_literal(db_type))
         .collect();
 
-    format!(
+    let insert = format!(
         "INSERT INTO {} ({}) VALUES ({});",
-        db_type.quote_identifier(table_name),
+        db_type.quote_table_name(&table.name),
         cols.join(", "),
         vals.join(", ")
-    )
+    );
+    with_identity_insert(db_type, table, insert)
 }
 
-fn generate_update_sql(
-    db_type: DbType,
-    table_name: &str,
-    row: &HashMap<String, String>,
-    primary_keys: &[String],
-) -> String {
+fn generate_update_sql(db_type: DbType, table: &KeyedTable, row: &HashMap<String, Value>) -> String {
     let sets: Vec<String> = row
         .iter()
-        .filter(|(k, _)| !primary_keys.contains(k))
-        .map(|(k, v)| format!("{} = {}", db_type.quote_identifier(k), escape_value(v)))
+        .filter(|(k, _)| !table.key_columns.contains(k))
+        .map(|(k, v)| format!("{} = {}", table.quote_column(k, db_type), v.to_sql_literal(db_type)))
         .collect();
 
-    let wheres: Vec<String> = primary_keys
+    let wheres: Vec<String> = table
+        .key_columns
         .iter()
-        .filter_map(|pk| row.get(pk).map(|v| format!("{} = {}", db_type.quote_identifier(pk), escape_value(v))))
+        .filter_map(|pk| row.get(pk).map(|v| format!("{} = {}", table.quote_column(pk, db_type), v.to_sql_literal(db_type))))
         .collect();
 
     format!(
         "UPDATE {} SET {} WHERE {};",
-        db_type.quote_identifier(table_name),
+        db_type.quote_table_name(&table.name),
         sets.join(", "),
         wheres.join(" AND ")
     )
 }
 
-fn generate_delete_sql(
-    db_type: DbType,
-    table_name: &str,
-    primary_keys: &[String],
-    pk_values: &HashMap<String, String>,
-) -> String {
-    let wheres: Vec<String> = primary_keys
+fn generate_delete_sql(db_type: DbType, table: &KeyedTable, pk_values: &HashMap<String, Value>) -> String {
+    let wheres: Vec<String> = table
+        .key_columns
         .iter()
-        .filter_map(|pk| pk_values.get(pk).map(|v| format!("{} = {}", db_type.quote_identifier(pk), escape_value(v))))
+        .filter_map(|pk| pk_values.get(pk).map(|v| format!("{} = {}", table.quote_column(pk, db_type), v.to_sql_literal(db_type))))
         .collect();
 
     format!(
         "DELETE FROM {} WHERE {};",
-        db_type.quote_identifier(table_name),
+        db_type.quote_table_name(&table.name),
         wheres.join(" AND ")
     )
 }
 
-fn escape_value(val: &str) -> String {
-    if val == "NULL" {
-        return "NULL".to_string();
+/// Delete a single row equal to `row` in every compared column. Fails when
+/// no compared column has a value, as the DELETE would match any row.
+fn generate_delete_one_sql(db_type: DbType, table: &KeyedTable, row: &HashMap<String, Value>) -> Result<String> {
+    let wheres: Vec<String> = table
+        .compared_columns
+        .iter()
+        .filter_map(|c| row.get(c).map(|v| (table.quote_column(c, db_type), v)))
+        .map(|(c, v)| match (v, db_type) {
+            (Value::Null, _) => format!("{} IS NULL", c),
+            // PostgreSQL json has no equality operator; its text form is the stored text
+            (Value::Json(_), DbType::PostgreSQL) => format!("{}::text = {}", c, v.to_sql_literal(db_type)),
+            (Value::Json(_), DbType::MySQL) => format!("{} = CAST({} AS JSON)", c, v.to_sql_literal(db_type)),
+            _ => format!("{} = {}", c, v.to_sql_literal(db_type)),
+        })
+        .collect();
+    if wheres.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Cannot delete a row of table {}: it has no compared columns to identify the row by",
+            table.name
+        ));
+    }
+    let wheres = wheres.join(" AND ");
+    let name = db_type.quote_table_name(&table.name);
+
+    Ok(match db_type {
+        DbType::MySQL => format!("DELETE FROM {} WHERE {} LIMIT 1;", name, wheres),
+        DbType::SQLServer => format!("DELETE TOP (1) FROM {} WHERE {};", name, wheres),
+        // No LIMIT on DELETE: pick one physical row by its row address
+        DbType::PostgreSQL => format!(
+            "DELETE FROM {} WHERE ctid IN (SELECT ctid FROM {} WHERE {} LIMIT 1);",
+            name, name, wheres
+        ),
+        DbType::SQLite => format!(
+            "DELETE FROM {} WHERE rowid IN (SELECT rowid FROM {} WHERE {} LIMIT 1);",
+            name, name, wheres
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Vec<Value> {
+        vec![Value::Text(s.to_string())]
+    }
+
+    fn keyless_table(compared_columns: &[&str]) -> KeyedTable {
+        KeyedTable {
+            name: "t".to_string(),
+            columns: vec!["a".to_string(), "b".to_string()],
+            column_names: HashMap::new(),
+            key_columns: Vec::new(),
+            text_keys: Vec::new(),
+            compared_columns: compared_columns.iter().map(|c| c.to_string()).collect(),
+            filter: None,
+            identity_column: None,
+        }
+    }
+
+    #[test]
+    fn text_keys_order_by_utf8_bytes() {
+        // Accented letters sort after ASCII, not next to their base letter
+        assert_eq!(compare_keys(&text("é"), &text("z"), &[true]), Ordering::Greater);
+        assert_eq!(compare_keys(&text("e"), &text("é"), &[true]), Ordering::Less);
+        // Code point order, where UTF-16 code units would put the emoji first
+        assert_eq!(compare_keys(&text("\u{FFFD}"), &text("\u{1F600}"), &[true]), Ordering::Less);
+    }
+
+    #[test]
+    fn guid_keys_order_by_text() {
+        let low = vec![Value::Uuid("0000000a-0000-0000-0000-000000000001".into())];
+        let high = vec![Value::Uuid("00000001-0000-0000-0000-000000000002".into())];
+        // SQL Server would order these by the last byte group first
+        assert_eq!(compare_keys(&low, &high, &[true]), Ordering::Greater);
+        let upper = vec![Value::Uuid("0000000B-0000-0000-0000-000000000001".into())];
+        assert_eq!(compare_keys(&low, &upper, &[true]), Ordering::Less);
+    }
+
+    #[test]
+    fn numeric_keys_order_by_value() {
+        assert_eq!(compare_keys(&[Value::Int(9)], &[Value::Int(10)], &[false]), Ordering::Less);
+        assert_eq!(compare_keys(&text("9"), &text("10"), &[false]), Ordering::Less);
+        assert_eq!(compare_keys(&text("9"), &text("10"), &[true]), Ordering::Greater);
+    }
+
+    #[test]
+    fn delete_one_matches_every_compared_column() {
+        let row = HashMap::from([("a".to_string(), Value::Int(1)), ("b".to_string(), Value::Null)]);
+        let sql = generate_delete_one_sql(DbType::MySQL, &keyless_table(&["a", "b"]), &row).unwrap();
+        assert_eq!(sql, "DELETE FROM `t` WHERE `a` = 1 AND `b` IS NULL LIMIT 1;");
+    }
+
+    #[test]
+    fn delete_one_refuses_an_empty_condition() {
+        let row = HashMap::from([("a".to_string(), Value::Int(1))]);
+        assert!(generate_delete_one_sql(DbType::SQLite, &keyless_table(&[]), &row).is_err());
+        assert!(generate_delete_one_sql(DbType::SQLite, &keyless_table(&["b"]), &row).is_err());
+    }
+
+    #[test]
+    fn target_filter_defaults_to_the_source_filter() {
+        let mut options = CompareOptions {
+            filter: "tenant = 1".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(options.target_filter(), "tenant = 1");
+        options.target_filter = "org = 1".to_string();
+        assert_eq!(options.target_filter(), "org = 1");
+        assert_eq!(
+            options.row_filter(options.target_filter(), "", DbType::SQLite).as_deref(),
+            Some("org = 1")
+        );
+    }
+
+    #[test]
+    fn column_matches_wildcards_ignoring_case() {
+        assert!(column_matches("updated_at", "Updated_At"));
+        assert!(column_matches("*_at", "created_at"));
+        assert!(column_matches("last_seen_*", "last_seen_"));
+        assert!(column_matches("a*b*c", "axxbyybzc"));
+        assert!(column_matches("?d", "id"));
+        assert!(column_matches("*", ""));
+        assert!(!column_matches("*_at", "created_on"));
+        assert!(!column_matches("?d", "uuid"));
+        assert!(!column_matches("id", "ids"));
     }
-    format!("'{}'", val.replace('\'', "''"))
 }
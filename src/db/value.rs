@@ -0,0 +1,373 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::MySqlRow;
+use sqlx::postgres::PgRow;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, TypeInfo, ValueRef};
+use std::fmt;
+use std::str::FromStr;
+
+use super::DbType;
+
+/// A column value, decoded according to its database type.
+///
+/// Dates and times are kept as canonically formatted text, so the same
+/// instant read from different databases compares equal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    /// Exact numeric in decimal notation
+    Decimal(String),
+    Text(String),
+    Bytes(Vec<u8>),
+    /// `YYYY-MM-DD`
+    Date(String),
+    /// `HH:MM:SS[.fraction]`
+    Time(String),
+    /// `YYYY-MM-DD HH:MM:SS[.fraction][+HH:MM]`
+    DateTime(String),
+    Uuid(String),
+    Json(String),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Value::Bool(_) | Value::Int(_) | Value::UInt(_) | Value::Float(_) | Value::Decimal(_)
+        )
+    }
+
+    /// Normalized decimal notation of a number, or of text holding one.
+    /// Booleans count as 1 and 0.
+    fn as_number(&self) -> Option<String> {
+        match self {
+            Value::Bool(v) => Some(if *v { "1" } else { "0" }.to_string()),
+            Value::Int(v) => Some(v.to_string()),
+            Value::UInt(v) => Some(v.to_string()),
+            Value::Float(v) if v.is_finite() => normalize_decimal(&v.to_string()),
+            Value::Decimal(s) | Value::Text(s) => normalize_decimal(s),
+            _ => None,
+        }
+    }
+
+    /// Text identifying the value, the same for values that compare equal
+    /// across types; used to group identical rows
+    pub(super) fn fingerprint(&self) -> String {
+        match self {
+            Value::Null => "\0".to_string(),
+            Value::Bytes(v) => format!("x{}", hex(v)),
+            Value::Json(s) => match serde_json::from_str::<serde_json::Value>(s) {
+                Ok(json) => format!("'{}", json),
+                Err(_) => format!("'{}", s),
+            },
+            Value::Uuid(s) => format!("'{}", s.to_ascii_lowercase()),
+            _ => match self.as_number().filter(|_| self.is_numeric()) {
+                Some(number) => format!("#{}", number),
+                None => format!("'{}", self),
+            },
+        }
+    }
+
+    /// Render the value as a SQL literal for `db_type`
+    pub fn to_sql_literal(&self, db_type: DbType) -> String {
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::Bool(v) => match (db_type, v) {
+                (DbType::PostgreSQL, true) => "TRUE".to_string(),
+                (DbType::PostgreSQL, false) => "FALSE".to_string(),
+                (_, true) => "1".to_string(),
+                (_, false) => "0".to_string(),
+            },
+            Value::Int(v) => v.to_string(),
+            Value::UInt(v) => v.to_string(),
+            Value::Float(v) if v.is_finite() => v.to_string(),
+            Value::Float(v) => quote_text(&v.to_string(), db_type),
+            Value::Decimal(s) => s.clone(),
+            Value::Bytes(v) => match db_type {
+                DbType::MySQL | DbType::SQLite => format!("X'{}'", hex(v)),
+                DbType::PostgreSQL => format!("'\\x{}'::bytea", hex(v)),
+                DbType::SQLServer => format!("0x{}", hex(v)),
+            },
+            Value::Text(s)
+            | Value::Date(s)
+            | Value::Time(s)
+            | Value::DateTime(s)
+            | Value::Uuid(s)
+            | Value::Json(s) => quote_text(s, db_type),
+        }
+    }
+
+    /// Render the value as a field of PostgreSQL's `COPY` text format
+    pub(super) fn to_copy_text(&self) -> String {
+        match self {
+            Value::Null => "\\N".to_string(),
+            Value::Bool(v) => if *v { "t" } else { "f" }.to_string(),
+            // bytea's `\x` prefix, with the backslash escaped for COPY
+            Value::Bytes(v) => format!("\\\\x{}", hex(v)),
+            _ => {
+                let text = self.to_string();
+                let mut escaped = String::with_capacity(text.len());
+                for ch in text.chars() {
+                    match ch {
+                        '\\' => escaped.push_str("\\\\"),
+                        '\n' => escaped.push_str("\\n"),
+                        '\r' => escaped.push_str("\\r"),
+                        '\t' => escaped.push_str("\\t"),
+                        _ => escaped.push(ch),
+                    }
+                }
+                escaped
+            }
+        }
+    }
+}
+
+impl PartialEq for Value {
+    /// Values are equal when they denote the same data: NULL only equals
+    /// NULL, numbers compare by value across types, JSON by its parsed form
+    /// and UUIDs ignoring case
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Null, _) | (_, Value::Null) => false,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            (Value::Bytes(a), Value::Text(b)) | (Value::Text(b), Value::Bytes(a)) => a == b.as_bytes(),
+            (Value::Bytes(_), _) | (_, Value::Bytes(_)) => false,
+            (Value::Float(a), Value::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Value::Json(a), Value::Json(b) | Value::Text(b)) | (Value::Text(b), Value::Json(a)) => {
+                match (serde_json::from_str::<serde_json::Value>(a), serde_json::from_str::<serde_json::Value>(b)) {
+                    (Ok(a), Ok(b)) => a == b,
+                    _ => a == b,
+                }
+            }
+            (Value::Uuid(a), b) | (b, Value::Uuid(a)) => a.eq_ignore_ascii_case(&b.to_string()),
+            _ if self.is_numeric() || other.is_numeric() => match (self.as_number(), other.as_number()) {
+                (Some(a), Some(b)) => a == b,
+                _ => self.to_string() == other.to_string(),
+            },
+            _ => self.to_string() == other.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("NULL"),
+            Value::Bool(v) => write!(f, "{}", v),
+            Value::Int(v) => write!(f, "{}", v),
+            Value::UInt(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Bytes(v) => write!(f, "0x{}", hex(v)),
+            Value::Decimal(s)
+            | Value::Text(s)
+            | Value::Date(s)
+            | Value::Time(s)
+            | Value::DateTime(s)
+            | Value::Uuid(s)
+            | Value::Json(s) => f.write_str(s),
+        }
+    }
+}
+
+fn quote_text(text: &str, db_type: DbType) -> String {
+    let escaped = text.replace('\'', "''");
+    match db_type {
+        // Backslash is an escape character in MySQL string literals by default
+        DbType::MySQL => format!("'{}'", escaped.replace('\\', "\\\\")),
+        DbType::SQLServer => format!("N'{}'", escaped),
+        DbType::PostgreSQL | DbType::SQLite => format!("'{}'", escaped),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Canonical form of a plain decimal number (`-0012.50` -> `-12.5`),
+/// `None` if `text` is not one
+fn normalize_decimal(text: &str) -> Option<String> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+    if int_part.is_empty() && frac_part.is_empty()
+        || !int_part.chars().chain(frac_part.chars()).all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let int_part = int_part.trim_start_matches('0');
+    let frac_part = frac_part.trim_end_matches('0');
+    let mut normalized = String::new();
+    if negative && !(int_part.is_empty() && frac_part.is_empty()) {
+        normalized.push('-');
+    }
+    normalized.push_str(if int_part.is_empty() { "0" } else { int_part });
+    if !frac_part.is_empty() {
+        normalized.push('.');
+        normalized.push_str(frac_part);
+    }
+    Some(normalized)
+}
+
+fn parse_or_text<T: FromStr>(text: &str, wrap: fn(T) -> Value) -> Value {
+    text.parse().map(wrap).unwrap_or_else(|_| Value::Text(text.to_string()))
+}
+
+fn date_value(text: &str) -> Value {
+    match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        Ok(date) => Value::Date(date.to_string()),
+        Err(_) => Value::Date(text.to_string()),
+    }
+}
+
+fn time_value(text: &str) -> Value {
+    match NaiveTime::parse_from_str(text, "%H:%M:%S%.f") {
+        Ok(time) => Value::Time(time.to_string()),
+        Err(_) => Value::Time(text.to_string()),
+    }
+}
+
+fn datetime_value(text: &str) -> Value {
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f") {
+        return Value::DateTime(datetime.to_string());
+    }
+    match DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f%#z") {
+        Ok(datetime) => Value::DateTime(datetime.format("%Y-%m-%d %H:%M:%S%.f%:z").to_string()),
+        Err(_) => Value::DateTime(text.to_string()),
+    }
+}
+
+/// Decode column `index` of a MySQL row read with the text protocol
+pub(super) fn decode_mysql(row: &MySqlRow, index: usize) -> Result<Value> {
+    let raw = row.try_get_raw(index)?;
+    if raw.is_null() {
+        return Ok(Value::Null);
+    }
+    let type_info = raw.type_info().into_owned();
+    let bytes: &[u8] = row.try_get_unchecked(index)?;
+    let text = String::from_utf8_lossy(bytes);
+
+    Ok(match type_info.name() {
+        "BOOLEAN" | "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "BIGINT" | "YEAR" => {
+            parse_or_text(&text, Value::Int)
+        }
+        name if name.ends_with(" UNSIGNED") => parse_or_text(&text, Value::UInt),
+        "FLOAT" | "DOUBLE" => parse_or_text(&text, Value::Float),
+        "DECIMAL" => Value::Decimal(text.into_owned()),
+        "DATE" => date_value(&text),
+        "TIME" => time_value(&text),
+        "DATETIME" | "TIMESTAMP" => datetime_value(&text),
+        "JSON" => Value::Json(text.into_owned()),
+        "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" | "BIT" | "GEOMETRY" => {
+            Value::Bytes(bytes.to_vec())
+        }
+        _ => Value::Text(text.into_owned()),
+    })
+}
+
+/// Decode column `index` of a PostgreSQL row read with the simple query
+/// protocol, which returns every value in its text form
+pub(super) fn decode_postgres(row: &PgRow, index: usize) -> Result<Value> {
+    let raw = row.try_get_raw(index)?;
+    if raw.is_null() {
+        return Ok(Value::Null);
+    }
+    let type_info = raw.type_info().into_owned();
+    let text: &str = row.try_get_unchecked(index)?;
+
+    Ok(match type_info.name() {
+        "BOOL" => Value::Bool(text == "t"),
+        "INT2" | "INT4" | "INT8" | "OID" => parse_or_text(text, Value::Int),
+        "FLOAT4" | "FLOAT8" => parse_or_text(text, Value::Float),
+        "NUMERIC" => Value::Decimal(text.to_string()),
+        "BYTEA" => text
+            .strip_prefix("\\x")
+            .and_then(|digits| {
+                (0..digits.len())
+                    .step_by(2)
+                    .map(|i| digits.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+                    .collect::<Option<Vec<u8>>>()
+            })
+            .map(Value::Bytes)
+            .unwrap_or_else(|| Value::Text(text.to_string())),
+        "DATE" => date_value(text),
+        "TIME" => time_value(text),
+        "TIMETZ" => Value::Time(text.to_string()),
+        "TIMESTAMP" | "TIMESTAMPTZ" => datetime_value(text),
+        "UUID" => Value::Uuid(text.to_string()),
+        "JSON" | "JSONB" => Value::Json(text.to_string()),
+        _ => Value::Text(text.to_string()),
+    })
+}
+
+/// Decode column `index` of a SQLite row by the storage class of the value
+pub(super) fn decode_sqlite(row: &SqliteRow, index: usize) -> Result<Value> {
+    let raw = row.try_get_raw(index)?;
+    if raw.is_null() {
+        return Ok(Value::Null);
+    }
+    let type_info = raw.type_info().into_owned();
+
+    Ok(match type_info.name() {
+        "INTEGER" => Value::Int(row.try_get_unchecked(index)?),
+        "REAL" => Value::Float(row.try_get_unchecked(index)?),
+        "BLOB" => Value::Bytes(row.try_get_unchecked(index)?),
+        _ => Value::Text(row.try_get_unchecked(index)?),
+    })
+}
+
+/// Decode a SQL Server cell
+pub(super) fn decode_mssql(cell: tiberius::ColumnData<'static>) -> Value {
+    use tiberius::time::chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+    use tiberius::{ColumnData, FromSql};
+
+    let value = match &cell {
+        ColumnData::U8(v) => v.map(|v| Value::Int(v as i64)),
+        ColumnData::I16(v) => v.map(|v| Value::Int(v as i64)),
+        ColumnData::I32(v) => v.map(|v| Value::Int(v as i64)),
+        ColumnData::I64(v) => v.map(Value::Int),
+        // Through text, so REAL values keep their short form
+        ColumnData::F32(v) => v.map(|v| parse_or_text(&v.to_string(), Value::Float)),
+        ColumnData::F64(v) => v.map(Value::Float),
+        ColumnData::Bit(v) => v.map(Value::Bool),
+        ColumnData::String(v) => v.as_ref().map(|v| Value::Text(v.to_string())),
+        ColumnData::Guid(v) => v.map(|v| Value::Uuid(v.to_string())),
+        ColumnData::Binary(v) => v.as_ref().map(|v| Value::Bytes(v.to_vec())),
+        ColumnData::Numeric(v) => v.map(|v| Value::Decimal(v.to_string())),
+        ColumnData::Xml(v) => v.as_ref().map(|v| Value::Text(v.to_string())),
+        ColumnData::Date(_) => NaiveDate::from_sql(&cell)
+            .ok()
+            .flatten()
+            .map(|v| Value::Date(v.to_string())),
+        ColumnData::Time(_) => NaiveTime::from_sql(&cell)
+            .ok()
+            .flatten()
+            .map(|v| Value::Time(v.to_string())),
+        ColumnData::DateTimeOffset(_) => DateTime::<FixedOffset>::from_sql(&cell)
+            .ok()
+            .flatten()
+            .map(|v| Value::DateTime(v.format("%Y-%m-%d %H:%M:%S%.f%:z").to_string())),
+        ColumnData::DateTime(_) | ColumnData::SmallDateTime(_) | ColumnData::DateTime2(_) => {
+            NaiveDateTime::from_sql(&cell)
+                .ok()
+                .flatten()
+                .map(|v| Value::DateTime(v.to_string()))
+        }
+    };
+    value.unwrap_or(Value::Null)
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::connection::config_dir;
+use super::sync::{range_filter, update_diff};
+use super::{CompareOptions, DataDiffResult, DataDiffType, DbConnection, KeyRange, KeyedTable, Value};
+
+/// Watermark a table was last synced up to, for a source/target connection pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedWatermark {
+    pub source: String,
+    pub target: String,
+    pub table: String,
+    pub column: String,
+    /// Highest value of the watermark column in the synced source rows
+    pub value: String,
+}
+
+/// Watermark store for the progress of incremental syncs
+pub struct WatermarkStore {
+    watermarks: Vec<SavedWatermark>,
+    file_path: PathBuf,
+}
+
+impl WatermarkStore {
+    /// Create new watermark store
+    pub fn new() -> Result<Self> {
+        let file_path = config_dir()?.join("watermarks.json");
+        let watermarks = if file_path.exists() {
+            let content = std::fs::read_to_string(&file_path)?;
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { watermarks, file_path })
+    }
+
+    /// Saved watermarks of every table of a connection pair, by table name
+    pub fn table_watermarks(&self, source: &str, target: &str) -> HashMap<String, SavedWatermark> {
+        self.watermarks
+            .iter()
+            .filter(|w| w.source == source && w.target == target)
+            .map(|w| (w.table.clone(), w.clone()))
+            .collect()
+    }
+
+    /// Save the watermark a table was synced up to
+    pub fn save(&mut self, watermark: SavedWatermark) -> Result<()> {
+        self.watermarks
+            .retain(|w| !(w.source == watermark.source && w.target == watermark.target && w.table == watermark.table));
+        self.watermarks.push(watermark);
+        self.persist()
+    }
+
+    /// Forget the watermark of a table, so its next sync compares all rows
+    pub fn remove(&mut self, source: &str, target: &str, table: &str) -> Result<()> {
+        self.watermarks
+            .retain(|w| !(w.source == source && w.target == target && w.table == table));
+        self.persist()
+    }
+
+    /// Persist watermarks to file
+    fn persist(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.watermarks)?;
+        std::fs::write(&self.file_path, content)?;
+        Ok(())
+    }
+}
+
+/// Set the watermark of `options` from `saved` if it was taken on the same column
+pub fn apply_watermark(options: &mut CompareOptions, saved: Option<&SavedWatermark>) {
+    options.watermark = saved
+        .filter(|w| !options.watermark_column.is_empty() && w.column == options.watermark_column)
+        .map(|w| w.value.clone());
+}
+
+/// Highest watermark among the source rows a comparison with `options`
+/// covers, `None` without a watermark column or matching rows.
+///
+/// Read it before comparing: rows written while the comparison runs then
+/// lie beyond it and are compared again by the next sync.
+pub async fn read_watermark(
+    source: &DbConnection,
+    table_name: &str,
+    database: &str,
+    options: &CompareOptions,
+) -> Result<Option<String>> {
+    if options.watermark_column.is_empty() {
+        return Ok(None);
+    }
+    let table = KeyedTable::load(source, table_name, database, options).await?;
+    let db_type = source.db_type();
+    let query = format!(
+        "SELECT MAX({}) FROM {}{}",
+        db_type.quote_identifier(&options.watermark_column),
+        db_type.quote_table_name(&table.name),
+        range_filter(&table, &KeyRange::default(), db_type)
+    );
+    let value = source.fetch_values(&query, 1).await?.into_iter().next().and_then(|row| row.into_iter().next());
+    Ok(value.filter(|v| !v.is_null()).map(|v| v.to_string()))
+}
+
+/// Key of a row as text, for looking rows up across sides
+fn key_fingerprint(table: &KeyedTable, row: &HashMap<String, Value>) -> Vec<String> {
+    table.key_of(row).iter().map(Value::fingerprint).collect()
+}
+
+/// Settle the differences of an incremental comparison at the watermark.
+///
+/// A row changed on one side since the last sync lies beyond the watermark
+/// there but may lie before it on the other, so it shows up as an insert or
+/// a delete. Look such rows up by key on the other side: a match turns the
+/// difference into an update, or drops it if the rows agree.
+pub(super) async fn resolve_watermark_edges(
+    source: &DbConnection,
+    target: &DbConnection,
+    table: &KeyedTable,
+    target_table: &KeyedTable,
+    results: Vec<DataDiffResult>,
+) -> Result<Vec<DataDiffResult>> {
+    let keys_of = |diff_type: DataDiffType| -> Vec<Vec<Value>> {
+        results
+            .iter()
+            .filter(|d| d.diff_type == diff_type)
+            .filter_map(|d| d.new_values.as_ref().or(d.old_values.as_ref()))
+            .map(|row| table.key_of(row))
+            .collect()
+    };
+    let by_key = |rows: Vec<HashMap<String, Value>>| -> HashMap<Vec<String>, HashMap<String, Value>> {
+        rows.into_iter().map(|row| (key_fingerprint(table, &row), row)).collect()
+    };
+    let target_rows = by_key(target.get_rows_by_key(target_table, &keys_of(DataDiffType::Insert)).await?);
+    let source_rows = by_key(source.get_rows_by_key(table, &keys_of(DataDiffType::Delete)).await?);
+
+    let db_type = target.db_type();
+    let mut resolved = Vec::with_capacity(results.len());
+    for diff in results {
+        let pair = match (&diff.diff_type, &diff.new_values, &diff.old_values) {
+            (DataDiffType::Insert, Some(source_row), _) => target_rows
+                .get(&key_fingerprint(table, source_row))
+                .map(|target_row| (source_row.clone(), target_row)),
+            (DataDiffType::Delete, _, Some(target_row)) => source_rows
+                .get(&key_fingerprint(table, target_row))
+                .map(|source_row| (source_row.clone(), target_row)),
+            _ => None,
+        };
+        match pair {
+            Some((source_row, target_row)) if table.rows_equal(&source_row, target_row) => {}
+            Some((source_row, target_row)) => {
+                resolved.push(update_diff(db_type, table, target_table, source_row, target_row))
+            }
+            None => resolved.push(diff),
+        }
+    }
+    Ok(resolved)
+}
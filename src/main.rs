@@ -1,6 +1,8 @@
+mod cli;
 mod db;
 mod ui;
 
+use std::collections::HashMap;
 use std::io;
 
 use anyhow::Result;
@@ -15,28 +17,54 @@ use ratatui::{
     Terminal,
 };
 
-use db::{ConnectionStore, DbConnection, SavedConnection};
+use db::{ConnectionStore, DbConnection, SavedConnection, SettingsStore, WatermarkStore};
 use ui::{
     draw_tabs, Tab,
     ConnectionFormState, draw_connection_form,
     SchemaDiffState, draw_schema_diff,
-    DataSyncState, draw_data_sync,
+    DataSyncState, OptionField, draw_data_sync,
     TableBrowserState, draw_table_browser,
-    Spinner, draw_spinner,
+    Spinner, draw_spinner, draw_progress_bar,
 };
 
 /// Background task result
 enum TaskResult {
     ConnectionTest(Result<(), String>),
     SchemaCompare(Result<Vec<db::DiffResult>, String>),
+    ApplySchema {
+        indexes: Vec<usize>,
+        result: Result<Vec<db::StatementResult>, String>,
+    },
     LoadTables(Result<Vec<String>, String>),
-    CompareData(Result<Vec<db::DataDiffResult>, String>),
+    CompareData {
+        table: String,
+        result: Result<Vec<db::DataDiffResult>, String>,
+        /// Source watermark read before comparing, if the table has a watermark column
+        watermark: Option<String>,
+    },
+    CompareAll(Result<Vec<db::TableDataInfo>, String>),
+    CheckMapping {
+        table: String,
+        result: Result<db::UnmappedColumns, String>,
+    },
+    ApplyData {
+        table: String,
+        result: Result<db::BatchResult, String>,
+        recompare: Result<Vec<db::DataDiffResult>, String>,
+        /// Source watermark read before re-comparing
+        watermark: Option<String>,
+    },
     LoadTableData {
         columns: Vec<String>,
-        rows: Vec<Vec<String>>,
+        rows: Vec<Vec<db::Value>>,
         total_count: usize,
     },
     LoadTableDataError(String),
+    CopyTable(Result<db::CopyProgress, String>),
+    /// Intermediate progress message; the task keeps running
+    Progress(String),
+    /// Intermediate progress of a table copy, shown as a progress bar
+    CopyProgress(db::CopyProgress),
 }
 
 /// Application state
@@ -44,6 +72,8 @@ struct App {
     running: bool,
     current_tab: Tab,
     connection_store: ConnectionStore,
+    settings_store: SettingsStore,
+    watermark_store: WatermarkStore,
 
     // Tab states
     connection_state: ConnectionFormState,
@@ -57,6 +87,8 @@ struct App {
 
     // Spinner for async operations
     spinner: Spinner,
+    /// Progress of a running table copy, drawn as a bar instead of the spinner
+    copy_progress: Option<db::CopyProgress>,
 
     // Background task receiver
     task_rx: Option<tokio::sync::mpsc::Receiver<TaskResult>>,
@@ -65,22 +97,32 @@ struct App {
 impl App {
     fn new() -> Result<Self> {
         let connection_store = ConnectionStore::new()?;
+        let settings_store = SettingsStore::new()?;
+        let watermark_store = WatermarkStore::new()?;
         let saved = connection_store.get_all().to_vec();
 
         let mut connection_state = ConnectionFormState::default();
         connection_state.saved_connections = saved;
 
+        let data_sync_state = DataSyncState {
+            ignored_columns: settings_store.ignored_columns().to_vec(),
+            ..Default::default()
+        };
+
         Ok(Self {
             running: true,
             current_tab: Tab::Connection,
             connection_store,
+            settings_store,
+            watermark_store,
             connection_state,
             schema_diff_state: SchemaDiffState::default(),
-            data_sync_state: DataSyncState::default(),
+            data_sync_state,
             table_browser_state: TableBrowserState::default(),
             source_connection: None,
             target_connection: None,
             spinner: Spinner::default(),
+            copy_progress: None,
             task_rx: None,
         })
     }
@@ -88,10 +130,66 @@ impl App {
     fn refresh_connections(&mut self) {
         self.connection_state.saved_connections = self.connection_store.get_all().to_vec();
     }
+
+    /// Load the saved compare settings of the current source/target pair
+    fn load_compare_settings(&mut self) {
+        let state = &mut self.data_sync_state;
+        state.options = self.settings_store.table_options(&state.source_name, &state.target_name);
+        state.watermarks = self.watermark_store.table_watermarks(&state.source_name, &state.target_name);
+    }
+
+    /// Save `watermark` as the point `table` is synced up to when a
+    /// comparison starting at its previous watermark found no differences.
+    /// Returns a note for the status line.
+    fn record_watermark(&mut self, table: &str, diffs: &[db::DataDiffResult], watermark: Option<String>) -> String {
+        let state = &mut self.data_sync_state;
+        let column = state.table_options(table).watermark_column;
+        let Some(value) = watermark.filter(|_| diffs.is_empty() && !column.is_empty()) else {
+            return String::new();
+        };
+        let saved = db::SavedWatermark {
+            source: state.source_name.clone(),
+            target: state.target_name.clone(),
+            table: table.to_string(),
+            column,
+            value,
+        };
+        let note = format!(" | synced up to {} = {}", saved.column, saved.value);
+        match self.watermark_store.save(saved.clone()) {
+            Ok(()) => {
+                state.watermarks.insert(table.to_string(), saved);
+                note
+            }
+            Err(e) => format!(" | failed to save watermark: {}", e),
+        }
+    }
+
+    /// Whether a confirmation or input dialog is waiting for an answer
+    fn confirm_pending(&self) -> bool {
+        match self.current_tab {
+            Tab::SchemaDiff => self.schema_diff_state.confirm_apply,
+            Tab::DataSync => {
+                let state = &self.data_sync_state;
+                state.confirm_apply || state.confirm_copy || state.input.is_some()
+            }
+            _ => false,
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Headless mode when a command is given
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::parse_args(&args) {
+        Ok(Some(cli_args)) => std::process::exit(cli::run(cli_args).await),
+        Ok(None) => {}
+        Err(e) => {
+            cli::print_usage(Some(&e.to_string()));
+            std::process::exit(cli::EXIT_ERROR);
+        }
+    }
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -136,9 +234,16 @@ async fn run_app<B: ratatui::backend::Backend>(
         // Check for background task results
         if let Some(ref mut rx) = app.task_rx {
             match rx.try_recv() {
+                Ok(TaskResult::Progress(message)) => {
+                    app.spinner.message = message;
+                }
+                Ok(TaskResult::CopyProgress(progress)) => {
+                    app.copy_progress = Some(progress);
+                }
                 Ok(result) => {
                     handle_task_result(app, result);
                     app.spinner.stop();
+                    app.copy_progress = None;
                     app.task_rx = None;
                 }
                 Err(tokio::sync::mpsc::error::TryRecvError::Empty) => {
@@ -146,6 +251,7 @@ async fn run_app<B: ratatui::backend::Backend>(
                 }
                 Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => {
                     app.spinner.stop();
+                    app.copy_progress = None;
                     app.task_rx = None;
                 }
             }
@@ -187,8 +293,10 @@ async fn run_app<B: ratatui::backend::Backend>(
                 Tab::TableBrowser => draw_table_browser(f, main_area, &mut app.table_browser_state),
             }
 
-            // Draw spinner at the bottom when active
-            if app.spinner.active {
+            // Draw spinner at the bottom when active, or the progress of a copy
+            if let (true, Some(progress)) = (app.spinner.active, &app.copy_progress) {
+                draw_progress_bar(f, chunks[2], progress.fraction(), &copy_message(progress));
+            } else if app.spinner.active {
                 draw_spinner(f, chunks[2], &app.spinner);
             }
         })?;
@@ -218,8 +326,8 @@ async fn run_app<B: ratatui::backend::Backend>(
                     }
                 }
 
-                // Global: Esc to quit (when not editing)
-                if key.code == KeyCode::Esc {
+                // Global: Esc to quit (when not editing or confirming)
+                if key.code == KeyCode::Esc && !app.confirm_pending() {
                     app.running = false;
                     continue;
                 }
@@ -272,7 +380,7 @@ fn handle_task_result(app: &mut App, result: TaskResult) {
             match res {
                 Ok(diffs) => {
                     let count = diffs.len();
-                    app.schema_diff_state.diffs = diffs;
+                    app.schema_diff_state.set_diffs(diffs);
                     app.schema_diff_state.status = format!("Found {} differences", count);
                 }
                 Err(e) => {
@@ -281,6 +389,25 @@ fn handle_task_result(app: &mut App, result: TaskResult) {
             }
             app.schema_diff_state.loading = false;
         }
+        TaskResult::ApplySchema { indexes, result } => {
+            match result {
+                Ok(results) => {
+                    let applied = results.iter().filter(|r| r.is_applied()).count();
+                    app.schema_diff_state.set_apply_results(&indexes, &results);
+                    app.schema_diff_state.status = match results.iter().find_map(|r| match &r.status {
+                        db::StatementStatus::Failed(e) => Some(e.clone()),
+                        _ => None,
+                    }) {
+                        Some(e) => format!("Applied {}/{} statements, failed: {}", applied, results.len(), e),
+                        None => format!("Applied {}/{} statements", applied, results.len()),
+                    };
+                }
+                Err(e) => {
+                    app.schema_diff_state.status = format!("Error: {}", e);
+                }
+            }
+            app.schema_diff_state.loading = false;
+        }
         TaskResult::LoadTables(res) => {
             match res {
                 Ok(tables) => {
@@ -288,6 +415,7 @@ fn handle_task_result(app: &mut App, result: TaskResult) {
                     match app.current_tab {
                         Tab::DataSync => {
                             app.data_sync_state.tables = tables;
+                            app.data_sync_state.summary.clear();
                             app.data_sync_state.status = format!("Loaded {} tables", count);
                         }
                         Tab::TableBrowser => {
@@ -310,18 +438,83 @@ fn handle_task_result(app: &mut App, result: TaskResult) {
                 }
             }
         }
-        TaskResult::CompareData(res) => {
-            match res {
+        TaskResult::CompareData { table, result, watermark } => {
+            match result {
                 Ok(diffs) => {
+                    let note = app.record_watermark(&table, &diffs, watermark);
                     let count = diffs.len();
-                    app.data_sync_state.diffs = diffs;
-                    app.data_sync_state.status = format!("Found {} differences", count);
+                    app.data_sync_state.set_diffs(diffs);
+                    app.data_sync_state.status = format!("Found {} differences{}", count, note);
                 }
                 Err(e) => {
                     app.data_sync_state.status = format!("Error: {}", e);
                 }
             }
         }
+        TaskResult::CompareAll(res) => {
+            app.data_sync_state.status = match res {
+                Ok(summary) => {
+                    let total = |count: fn(&db::TableDataInfo) -> i64| summary.iter().map(count).sum::<i64>();
+                    let status = format!(
+                        "Compared {} tables: {} with differences ({} inserts, {} updates, {} deletes), {} failed",
+                        summary.len(),
+                        summary.iter().filter(|info| info.difference_count() > 0).count(),
+                        total(|info| info.insert_count),
+                        total(|info| info.update_count),
+                        total(|info| info.delete_count),
+                        summary.iter().filter(|info| info.error.is_some()).count()
+                    );
+                    app.data_sync_state.set_summary(summary);
+                    status
+                }
+                Err(e) => format!("Error: {}", e),
+            };
+        }
+        TaskResult::CheckMapping { table, result } => {
+            app.data_sync_state.status = match result {
+                Ok(unmapped) if unmapped.is_empty() => format!("{}: all columns are mapped", table),
+                Ok(unmapped) => {
+                    let list = |columns: &[String]| {
+                        if columns.is_empty() { "none".to_string() } else { columns.join(", ") }
+                    };
+                    format!(
+                        "{}: unmapped source columns: {} | unmapped target columns: {}",
+                        table,
+                        list(&unmapped.source),
+                        list(&unmapped.target)
+                    )
+                }
+                Err(e) => format!("Error: {}", e),
+            };
+        }
+        TaskResult::ApplyData { table, result, recompare, watermark } => {
+            let applied_status = match result {
+                Ok(batches) if batches.is_complete() => format!("Applied {} rows", batches.rows),
+                Ok(batches) => {
+                    let failed = batches.results.iter().find_map(|r| match &r.status {
+                        db::StatementStatus::Failed(e) => Some(e.as_str()),
+                        _ => None,
+                    });
+                    format!(
+                        "Applied {}/{} rows, stopped at a failed batch: {}",
+                        batches.rows_applied,
+                        batches.rows,
+                        failed.unwrap_or("unknown error")
+                    )
+                }
+                Err(e) => format!("Apply error: {}", e),
+            };
+            app.data_sync_state.status = match recompare {
+                Ok(diffs) => {
+                    let note = app.record_watermark(&table, &diffs, watermark);
+                    let count = diffs.len();
+                    app.data_sync_state.set_diffs(diffs);
+                    format!("{} | {} differences remain{}", applied_status, count, note)
+                }
+                Err(e) => format!("{} | Re-compare error: {}", applied_status, e),
+            };
+            app.data_sync_state.loading = false;
+        }
         TaskResult::LoadTableData { columns, rows, total_count } => {
             // Reset column scroll if columns changed (new table)
             if app.table_browser_state.columns != columns {
@@ -341,9 +534,33 @@ fn handle_task_result(app: &mut App, result: TaskResult) {
         TaskResult::LoadTableDataError(e) => {
             app.table_browser_state.status = format!("Error: {}", e);
         }
+        TaskResult::CopyTable(res) => {
+            app.data_sync_state.status = match res {
+                Ok(progress) => format!("Copied {}", copy_message(&progress)),
+                Err(e) => format!("Copy error: {}", e),
+            };
+        }
+        TaskResult::Progress(message) => {
+            app.spinner.message = message;
+        }
+        TaskResult::CopyProgress(progress) => {
+            app.copy_progress = Some(progress);
+        }
     }
 }
 
+/// Rows copied so far and the copy rate
+fn copy_message(progress: &db::CopyProgress) -> String {
+    format!(
+        "{}: {}/{} rows in {:.1}s ({:.0} rows/s)",
+        progress.table_name,
+        progress.rows_copied,
+        progress.total_rows,
+        progress.elapsed.as_secs_f64(),
+        progress.rows_per_second()
+    )
+}
+
 async fn handle_tab_input(app: &mut App, key: KeyEvent) {
     match app.current_tab {
         Tab::Connection => handle_connection_input(app, key).await,
@@ -473,6 +690,7 @@ async fn handle_connection_input(app: &mut App, key: KeyEvent) {
                 });
                 app.schema_diff_state.source_name = name.clone();
                 app.data_sync_state.source_name = name.clone();
+                app.load_compare_settings();
                 app.connection_state.set_status(&format!("'{}' set as source", name), false);
             }
         }
@@ -489,6 +707,7 @@ async fn handle_connection_input(app: &mut App, key: KeyEvent) {
                 });
                 app.schema_diff_state.target_name = name.clone();
                 app.data_sync_state.target_name = name.clone();
+                app.load_compare_settings();
                 app.table_browser_state.connection_name = name.clone();
                 app.connection_state.set_status(&format!("'{}' set as target", name), false);
             }
@@ -510,6 +729,7 @@ async fn handle_connection_input(app: &mut App, key: KeyEvent) {
                 4 => state.user.push(c),
                 5 => state.password.push(c),
                 6 => state.database.push(c),
+                7 => state.schemas.push(c),
                 _ => {}
             }
         }
@@ -530,6 +750,7 @@ async fn handle_connection_input(app: &mut App, key: KeyEvent) {
                 4 => { state.user.pop(); }
                 5 => { state.password.pop(); }
                 6 => { state.database.pop(); }
+                7 => { state.schemas.pop(); }
                 _ => {}
             }
         }
@@ -540,11 +761,46 @@ async fn handle_connection_input(app: &mut App, key: KeyEvent) {
 
 async fn handle_schema_diff_input(app: &mut App, key: KeyEvent) {
     let state = &mut app.schema_diff_state;
+    let has_ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+    // Confirmation dialog captures all input
+    if state.confirm_apply {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                state.confirm_apply = false;
+                apply_schema_diffs(app).await;
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                state.confirm_apply = false;
+                state.status = "Apply cancelled".to_string();
+            }
+            _ => {}
+        }
+        return;
+    }
 
     match key.code {
         KeyCode::Up => state.prev(),
         KeyCode::Down => state.next(),
 
+        // Space: mark/unmark diff for applying
+        KeyCode::Char(' ') => state.toggle_mark(),
+
+        // Ctrl+A: mark/unmark all diffs
+        KeyCode::Char('a') if has_ctrl => state.toggle_mark_all(),
+
+        // Ctrl+E: apply marked diffs (or the selected one) after confirmation
+        KeyCode::Char('e') if has_ctrl => {
+            if state.indexes_to_apply().is_empty() {
+                state.status = "Nothing to apply".to_string();
+            } else {
+                state.confirm_apply = true;
+            }
+        }
+
+        // Ctrl+S: export marked diffs (or all) as a migration script
+        KeyCode::Char('s') if has_ctrl => export_migration_script(app),
+
         // Enter: compare schemas
         KeyCode::Enter => {
             compare_schemas(app).await;
@@ -564,8 +820,11 @@ async fn compare_schemas(app: &mut App) {
     app.schema_diff_state.status = "Comparing schemas...".to_string();
     app.schema_diff_state.loading = true;
 
-    let source_config = app.source_connection.as_ref().unwrap().config.clone();
-    let target_config = app.target_connection.as_ref().unwrap().config.clone();
+    let source = app.source_connection.as_ref().unwrap();
+    let target = app.target_connection.as_ref().unwrap();
+    let options = app.settings_store.table_options(&source.name, &target.name);
+    let source_config = source.config.clone();
+    let target_config = target.config.clone();
 
     let (tx, rx) = tokio::sync::mpsc::channel(1);
     app.task_rx = Some(rx);
@@ -582,6 +841,8 @@ async fn compare_schemas(app: &mut App) {
             let target_schema = target_conn.get_schema(&target_config.database).await
                 .map_err(|e| e.to_string())?;
 
+            // Compare under the target names of mapped tables and columns
+            let source_schema = db::map_schema(&source_schema, &options);
             let diffs = db::compare_schemas(&source_schema, &target_schema, target_config.db_type);
             Ok(diffs)
         }.await;
@@ -590,10 +851,143 @@ async fn compare_schemas(app: &mut App) {
     });
 }
 
+fn export_migration_script(app: &mut App) {
+    let state = &mut app.schema_diff_state;
+    let Some(target) = app.target_connection.as_ref() else {
+        state.status = "Set target connection first".to_string();
+        return;
+    };
+    if state.diffs.is_empty() {
+        state.status = "Nothing to export".to_string();
+        return;
+    }
+
+    let diffs: Vec<db::DiffResult> = if state.marked.is_empty() {
+        state.diffs.clone()
+    } else {
+        state.marked.iter().filter_map(|i| state.diffs.get(*i).cloned()).collect()
+    };
+
+    let path = format!("migration_{}_to_{}.sql", state.source_name, state.target_name);
+    let script = db::migration_script(&diffs, target.config.db_type);
+    state.status = match std::fs::write(&path, script) {
+        Ok(()) => format!("Exported {} changes to {}", diffs.len(), path),
+        Err(e) => format!("Export failed: {}", e),
+    };
+}
+
+async fn apply_schema_diffs(app: &mut App) {
+    if app.target_connection.is_none() {
+        app.schema_diff_state.status = "Set target connection first".to_string();
+        return;
+    }
+
+    let indexes = app.schema_diff_state.indexes_to_apply();
+    let statements: Vec<String> = indexes
+        .iter()
+        .filter_map(|i| app.schema_diff_state.diffs.get(*i))
+        .map(|d| d.sql.clone())
+        .collect();
+
+    app.spinner.start(&format!("Applying {} changes...", statements.len()));
+    app.schema_diff_state.status = "Applying changes...".to_string();
+    app.schema_diff_state.loading = true;
+
+    let target_config = app.target_connection.as_ref().unwrap().config.clone();
+
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    app.task_rx = Some(rx);
+
+    tokio::spawn(async move {
+        let result = async {
+            let target_conn = DbConnection::connect(&target_config).await
+                .map_err(|e| e.to_string())?;
+
+            let results = target_conn.execute_migration(&statements).await
+                .map_err(|e| e.to_string())?;
+            Ok(results)
+        }.await;
+
+        let _ = tx.send(TaskResult::ApplySchema { indexes, result }).await;
+    });
+}
+
 async fn handle_data_sync_input(app: &mut App, key: KeyEvent) {
     let state = &mut app.data_sync_state;
     let has_ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
 
+    // Confirmation dialog captures all input
+    if state.confirm_apply {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                state.confirm_apply = false;
+                apply_data_diffs(app).await;
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                state.confirm_apply = false;
+                state.status = "Apply cancelled".to_string();
+            }
+            _ => {}
+        }
+        return;
+    }
+    if state.confirm_copy {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                state.confirm_copy = false;
+                copy_table(app).await;
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                state.confirm_copy = false;
+                state.status = "Copy cancelled".to_string();
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    // Input dialog captures all input
+    if let Some((_, value)) = &mut state.input {
+        match key.code {
+            KeyCode::Enter => {
+                let saved = match state.finish_input() {
+                    Some(OptionField::IgnoredColumns) => {
+                        app.settings_store.save_ignored_columns(state.ignored_columns.clone())
+                    }
+                    // Kept for the session only, like the batch size
+                    Some(OptionField::RowsPerStatement) => Ok(()),
+                    // A new watermark column starts over with all rows
+                    Some(OptionField::WatermarkColumn) => {
+                        let table = state.selected_table().cloned().unwrap_or_default();
+                        app.settings_store
+                            .save_table_options(&state.source_name, &state.target_name, &table, &state.table_options(&table))
+                            .and_then(|_| app.watermark_store.remove(&state.source_name, &state.target_name, &table))
+                    }
+                    Some(_) => {
+                        let table = state.selected_table().cloned().unwrap_or_default();
+                        app.settings_store.save_table_options(
+                            &state.source_name,
+                            &state.target_name,
+                            &table,
+                            &state.table_options(&table),
+                        )
+                    }
+                    None => Ok(()),
+                };
+                if let Err(e) = saved {
+                    state.status = format!("Failed to save settings: {}", e);
+                }
+            }
+            KeyCode::Esc => state.input = None,
+            KeyCode::Backspace => {
+                value.pop();
+            }
+            KeyCode::Char(c) if !has_ctrl => value.push(c),
+            _ => {}
+        }
+        return;
+    }
+
     match key.code {
         KeyCode::Tab => state.toggle_focus(),
         KeyCode::Up => {
@@ -616,6 +1010,69 @@ async fn handle_data_sync_input(app: &mut App, key: KeyEvent) {
             load_data_sync_tables(app).await;
         }
 
+        // Ctrl+D: compare all tables, parents before the tables referencing them
+        KeyCode::Char('d') if has_ctrl => compare_all_tables(app).await,
+
+        // Ctrl+K: toggle checksum comparison
+        KeyCode::Char('k') if has_ctrl => {
+            state.checksum_mode = !state.checksum_mode;
+            state.status = if state.checksum_mode {
+                "Checksum mode: only key ranges with differing checksums are fetched".to_string()
+            } else {
+                "Full mode: all rows are fetched and compared".to_string()
+            };
+        }
+
+        // Ctrl+W: cycle how row changes are written: per row, multi-row inserts, upserts
+        KeyCode::Char('w') if has_ctrl => state.cycle_sql_mode(),
+
+        // Ctrl+B: set the rows per multi-row statement
+        KeyCode::Char('b') if has_ctrl => state.start_input(OptionField::RowsPerStatement),
+
+        // Ctrl+U: set the key columns of the selected table
+        KeyCode::Char('u') if has_ctrl => state.start_input(OptionField::KeyColumns),
+
+        // Ctrl+F: set the row filter of the selected table
+        KeyCode::Char('f') if has_ctrl => state.start_input(OptionField::Filter),
+
+        // Ctrl+V: set the row filter of the selected table's target, if named differently
+        KeyCode::Char('v') if has_ctrl => state.start_input(OptionField::TargetFilter),
+
+        // Ctrl+O / Ctrl+X: set the compared / excluded columns of the selected table
+        KeyCode::Char('o') if has_ctrl => state.start_input(OptionField::IncludeColumns),
+        KeyCode::Char('x') if has_ctrl => state.start_input(OptionField::ExcludeColumns),
+
+        // Ctrl+G: set the column patterns ignored in all tables
+        KeyCode::Char('g') if has_ctrl => state.start_input(OptionField::IgnoredColumns),
+
+        // Ctrl+T / Ctrl+N: set the target table / column names of the selected table
+        KeyCode::Char('t') if has_ctrl => state.start_input(OptionField::TargetTable),
+        KeyCode::Char('n') if has_ctrl => state.start_input(OptionField::ColumnMap),
+
+        // Ctrl+P: set the watermark column of the selected table for incremental syncs
+        KeyCode::Char('p') if has_ctrl => state.start_input(OptionField::WatermarkColumn),
+
+        // Ctrl+R: report the columns the mapping of the selected table leaves unmatched
+        KeyCode::Char('r') if has_ctrl => check_table_mapping(app).await,
+
+        // Ctrl+Y: copy all rows of the selected table into its empty target table after confirmation
+        KeyCode::Char('y') if has_ctrl && state.selected_table().is_some() => state.confirm_copy = true,
+
+        // Space on diff list: mark/unmark diff for applying
+        KeyCode::Char(' ') if !state.focus_left => state.toggle_mark(),
+
+        // Ctrl+A: mark/unmark all diffs
+        KeyCode::Char('a') if has_ctrl => state.toggle_mark_all(),
+
+        // Ctrl+E: apply marked diffs (or the selected one) after confirmation
+        KeyCode::Char('e') if has_ctrl => {
+            if state.diffs_to_apply().is_empty() {
+                state.status = "Nothing to apply".to_string();
+            } else {
+                state.confirm_apply = true;
+            }
+        }
+
         // Enter on table list: compare table data
         KeyCode::Enter => {
             if app.data_sync_state.focus_left {
@@ -667,6 +1124,192 @@ async fn compare_table_data(app: &mut App) {
 
     let source_config = app.source_connection.as_ref().unwrap().config.clone();
     let target_config = app.target_connection.as_ref().unwrap().config.clone();
+    let checksum_mode = app.data_sync_state.checksum_mode;
+    let options = app.data_sync_state.compare_options(&table_name);
+
+    // Room for progress messages; they are dropped rather than awaited when full
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+    app.task_rx = Some(rx);
+
+    tokio::spawn(async move {
+        let progress_tx = tx.clone();
+        let on_progress = move |progress: &db::CompareProgress| {
+            let _ = progress_tx.try_send(TaskResult::Progress(format!(
+                "Comparing {}: {}/{} rows ({:.0}%), {} fetched, {} differences",
+                progress.table_name,
+                progress.rows_read,
+                progress.total_rows,
+                progress.fraction() * 100.0,
+                progress.rows_fetched,
+                progress.differences
+            )));
+        };
+
+        let mut watermark = None;
+        let result = async {
+            let source_conn = DbConnection::connect(&source_config).await.map_err(|e| e.to_string())?;
+            let target_conn = DbConnection::connect(&target_config).await.map_err(|e| e.to_string())?;
+            watermark = db::read_watermark(&source_conn, &table_name, &source_config.database, &options)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let diffs = if checksum_mode {
+                db::compare_table_checksums(
+                    &source_conn,
+                    &target_conn,
+                    &table_name,
+                    &source_config.database,
+                    &options,
+                    db::DEFAULT_CHUNK_SIZE,
+                    on_progress,
+                ).await
+            } else {
+                db::compare_table_data_chunked(
+                    &source_conn,
+                    &target_conn,
+                    &table_name,
+                    &source_config.database,
+                    &options,
+                    db::DEFAULT_CHUNK_SIZE,
+                    on_progress,
+                ).await
+            }.map_err(|e| e.to_string())?;
+
+            Ok(diffs)
+        }.await;
+
+        let _ = tx.send(TaskResult::CompareData { table: table_name, result, watermark }).await;
+    });
+}
+
+async fn compare_all_tables(app: &mut App) {
+    if app.source_connection.is_none() || app.target_connection.is_none() {
+        app.data_sync_state.status = "Set source and target connections first".to_string();
+        return;
+    }
+
+    app.spinner.start("Comparing all tables...");
+    app.data_sync_state.status = "Comparing all tables...".to_string();
+
+    let source_config = app.source_connection.as_ref().unwrap().config.clone();
+    let target_config = app.target_connection.as_ref().unwrap().config.clone();
+    let checksum_mode = app.data_sync_state.checksum_mode;
+    let saved_options = app.data_sync_state.options.clone();
+    let ignored_columns = app.data_sync_state.ignored_columns.clone();
+    let watermarks = app.data_sync_state.watermarks.clone();
+
+    // Room for progress messages; they are dropped rather than awaited when full
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+    app.task_rx = Some(rx);
+
+    tokio::spawn(async move {
+        let result = async {
+            let source_conn = DbConnection::connect(&source_config).await?;
+            let target_conn = DbConnection::connect(&target_config).await?;
+            let tables = source_conn.get_tables_in_dependency_order().await?;
+            let options: HashMap<String, db::CompareOptions> = tables
+                .iter()
+                .map(|table| {
+                    let mut options = saved_options.get(table).cloned().unwrap_or_default();
+                    options.exclude_columns.extend(ignored_columns.iter().cloned());
+                    db::apply_watermark(&mut options, watermarks.get(table));
+                    (table.clone(), options)
+                })
+                .collect();
+
+            let progress_tx = tx.clone();
+            let total = tables.len();
+            let mut done = 0;
+            let on_table = move |info: &db::TableDataInfo| {
+                done += 1;
+                let _ = progress_tx.try_send(TaskResult::Progress(format!(
+                    "Compared {}/{} tables, last: {} ({} differences)",
+                    done,
+                    total,
+                    info.table_name,
+                    info.difference_count()
+                )));
+            };
+
+            let summary = db::compare_tables(
+                &source_conn,
+                &target_conn,
+                &tables,
+                &source_config.database,
+                &options,
+                checksum_mode,
+                false,
+                db::DEFAULT_TABLE_CONCURRENCY,
+                on_table,
+            ).await;
+            anyhow::Ok(summary.into_iter().map(|(info, _)| info).collect())
+        }.await.map_err(|e| e.to_string());
+
+        let _ = tx.send(TaskResult::CompareAll(result)).await;
+    });
+}
+
+async fn copy_table(app: &mut App) {
+    if app.source_connection.is_none() || app.target_connection.is_none() {
+        app.data_sync_state.status = "Set source and target connections first".to_string();
+        return;
+    }
+
+    let Some(table_name) = app.data_sync_state.selected_table().cloned() else {
+        return;
+    };
+
+    app.spinner.start(&format!("Copying table {}...", table_name));
+    app.data_sync_state.status = format!("Copying table {}...", table_name);
+
+    let source_config = app.source_connection.as_ref().unwrap().config.clone();
+    let target_config = app.target_connection.as_ref().unwrap().config.clone();
+    let options = app.data_sync_state.compare_options(&table_name);
+
+    // Room for progress updates; they are dropped rather than awaited when full
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+    app.task_rx = Some(rx);
+
+    tokio::spawn(async move {
+        let progress_tx = tx.clone();
+        let on_progress = move |progress: &db::CopyProgress| {
+            let _ = progress_tx.try_send(TaskResult::CopyProgress(progress.clone()));
+        };
+
+        let result = async {
+            let source_conn = DbConnection::connect(&source_config).await?;
+            let target_conn = DbConnection::connect(&target_config).await?;
+            db::copy_table(
+                &source_conn,
+                &target_conn,
+                &table_name,
+                &source_config.database,
+                &options,
+                db::DEFAULT_COPY_BATCH_SIZE,
+                on_progress,
+            ).await
+        }.await.map_err(|e| e.to_string());
+
+        let _ = tx.send(TaskResult::CopyTable(result)).await;
+    });
+}
+
+async fn check_table_mapping(app: &mut App) {
+    if app.source_connection.is_none() || app.target_connection.is_none() {
+        app.data_sync_state.status = "Set source and target connections first".to_string();
+        return;
+    }
+
+    let Some(table) = app.data_sync_state.selected_table().cloned() else {
+        return;
+    };
+
+    app.spinner.start(&format!("Checking column mapping of {}...", table));
+    app.data_sync_state.status = format!("Checking column mapping of {}...", table);
+
+    let source_config = app.source_connection.as_ref().unwrap().config.clone();
+    let target_config = app.target_connection.as_ref().unwrap().config.clone();
+    let options = app.data_sync_state.table_options(&table);
 
     let (tx, rx) = tokio::sync::mpsc::channel(1);
     app.task_rx = Some(rx);
@@ -675,18 +1318,102 @@ async fn compare_table_data(app: &mut App) {
         let result = async {
             let source_conn = DbConnection::connect(&source_config).await.map_err(|e| e.to_string())?;
             let target_conn = DbConnection::connect(&target_config).await.map_err(|e| e.to_string())?;
+            db::check_mapping(&source_conn, &target_conn, &table, &options)
+                .await
+                .map_err(|e| e.to_string())
+        }.await;
+
+        let _ = tx.send(TaskResult::CheckMapping { table, result }).await;
+    });
+}
+
+async fn apply_data_diffs(app: &mut App) {
+    if app.source_connection.is_none() || app.target_connection.is_none() {
+        app.data_sync_state.status = "Set source and target connections first".to_string();
+        return;
+    }
+
+    let checksum_mode = app.data_sync_state.checksum_mode;
+    let diffs = app.data_sync_state.diffs_to_apply();
+    let table_name = match diffs.first() {
+        Some(diff) => diff.table_name.clone(),
+        None => return,
+    };
+    let diffs: Vec<db::DataDiffResult> = diffs.into_iter().cloned().collect();
+    let batch_size = app.data_sync_state.batch_size;
+    let sql_options = app.data_sync_state.sql_options;
+    let options = app.data_sync_state.compare_options(&table_name);
+
+    app.spinner.start(&format!("Applying {} row changes to {}...", diffs.len(), table_name));
+    app.data_sync_state.status = format!("Applying changes to {}...", table_name);
+    app.data_sync_state.loading = true;
+
+    let source_config = app.source_connection.as_ref().unwrap().config.clone();
+    let target_config = app.target_connection.as_ref().unwrap().config.clone();
+
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    app.task_rx = Some(rx);
 
-            let diffs = db::compare_table_data(
+    tokio::spawn(async move {
+        let connected = async {
+            let source_conn = DbConnection::connect(&source_config).await?;
+            let target_conn = DbConnection::connect(&target_config).await?;
+            // Grouped statements are written against the target table and its names
+            let (_, target_table) = db::KeyedTable::load_pair(
                 &source_conn,
                 &target_conn,
                 &table_name,
                 &source_config.database,
-            ).await.map_err(|e| e.to_string())?;
+                &options,
+            ).await?;
+            let diffs: Vec<&db::DataDiffResult> = diffs.iter().collect();
+            let statements = db::data_statements(&diffs, &target_table, target_config.db_type, &sql_options);
+            anyhow::Ok((source_conn, target_conn, statements))
+        }.await;
+        let (source_conn, target_conn, statements) = match connected {
+            Ok(connected) => connected,
+            Err(e) => {
+                let _ = tx.send(TaskResult::ApplyData {
+                    table: table_name,
+                    result: Err(e.to_string()),
+                    recompare: Err("not run".to_string()),
+                    watermark: None,
+                }).await;
+                return;
+            }
+        };
 
-            Ok(diffs)
+        let result = target_conn.execute_batches(&statements, batch_size).await
+            .map_err(|e| e.to_string());
+
+        // Re-compare so the diff list reflects what is still out of sync;
+        // if nothing is, the table is synced up to the watermark read first
+        let watermark = db::read_watermark(&source_conn, &table_name, &source_config.database, &options)
+            .await
+            .unwrap_or(None);
+        let recompare = async {
+            if checksum_mode {
+                db::compare_table_checksums(
+                    &source_conn,
+                    &target_conn,
+                    &table_name,
+                    &source_config.database,
+                    &options,
+                    db::DEFAULT_CHUNK_SIZE,
+                    |_| {},
+                ).await
+            } else {
+                db::compare_table_data(
+                    &source_conn,
+                    &target_conn,
+                    &table_name,
+                    &source_config.database,
+                    &options,
+                ).await
+            }.map_err(|e| e.to_string())
         }.await;
 
-        let _ = tx.send(TaskResult::CompareData(result)).await;
+        let _ = tx.send(TaskResult::ApplyData { table: table_name, result, recompare, watermark }).await;
     });
 }
 
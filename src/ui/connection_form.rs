@@ -23,6 +23,8 @@ pub struct ConnectionFormState {
     pub password: String,
     pub database: String,
     pub file_path: String,
+    /// PostgreSQL schemas, comma-separated
+    pub schemas: String,
 
     // Current focused field
     pub focused_field: usize,
@@ -47,6 +49,7 @@ impl Default for ConnectionFormState {
             password: String::new(),
             database: String::new(),
             file_path: String::new(),
+            schemas: String::new(),
             focused_field: 0,
             editing: false,
             status: "Set source (F5) and target (F6) before using other features".to_string(),
@@ -58,8 +61,9 @@ impl Default for ConnectionFormState {
 impl ConnectionFormState {
     pub fn field_count(&self) -> usize {
         match self.db_type {
-            DbType::SQLite => 3, // name, db_type, file_path
-            _ => 7,             // name, db_type, host, port, user, password, database
+            DbType::SQLite => 3,     // name, db_type, file_path
+            DbType::PostgreSQL => 8, // ..., database, schemas
+            _ => 7,                 // name, db_type, host, port, user, password, database
         }
     }
 
@@ -104,6 +108,15 @@ impl ConnectionFormState {
             } else {
                 Some(self.file_path.clone().into())
             },
+            schemas: if self.db_type == DbType::PostgreSQL {
+                self.schemas
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            } else {
+                Vec::new()
+            },
         }
     }
 
@@ -121,6 +134,7 @@ impl ConnectionFormState {
             .as_ref()
             .map(|p| p.display().to_string())
             .unwrap_or_default();
+        self.schemas = conn.config.schemas.join(", ");
     }
 
     pub fn clear(&mut self) {
@@ -132,6 +146,7 @@ impl ConnectionFormState {
         self.password.clear();
         self.database.clear();
         self.file_path.clear();
+        self.schemas.clear();
         self.focused_field = 0;
     }
 
@@ -210,6 +225,7 @@ pub fn draw_connection_form(
             Constraint::Length(2), // user
             Constraint::Length(2), // password
             Constraint::Length(2), // database
+            Constraint::Length(2), // schemas
             Constraint::Length(2), // status
             Constraint::Min(0),    // spacer
         ])
@@ -281,6 +297,17 @@ pub fn draw_connection_form(
             let db_p = Paragraph::new(format!("Database: {}", state.database))
                 .style(field_style(6, state.focused_field));
             f.render_widget(db_p, form_chunks[7]);
+
+            if state.db_type == DbType::PostgreSQL {
+                let schemas = if state.schemas.is_empty() && state.focused_field != 7 {
+                    "public (comma-separated, first one unqualified)"
+                } else {
+                    &state.schemas
+                };
+                let schemas_p = Paragraph::new(format!("Schemas: {}", schemas))
+                    .style(field_style(7, state.focused_field));
+                f.render_widget(schemas_p, form_chunks[8]);
+            }
         }
     }
 
@@ -291,7 +318,7 @@ pub fn draw_connection_form(
         Style::default().fg(Color::Green)
     };
     let status_p = Paragraph::new(state.status.clone()).style(status_style);
-    f.render_widget(status_p, form_chunks[8]);
+    f.render_widget(status_p, form_chunks[9]);
 
     // Hints at bottom of page
     let hints = Paragraph::new(
@@ -4,8 +4,105 @@ use ratatui::{
     widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
     Frame,
 };
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use crate::db::{DataDiffResult, DataDiffType};
+use super::{draw_confirm, draw_input};
+use crate::db::{
+    apply_watermark, parse_column_map, CompareOptions, DataDiffResult, DataDiffType, SavedWatermark, SqlMode, SqlOptions,
+    TableDataInfo, DEFAULT_BATCH_SIZE,
+};
+
+/// Compare setting edited in the input dialog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionField {
+    KeyColumns,
+    Filter,
+    TargetFilter,
+    IncludeColumns,
+    ExcludeColumns,
+    TargetTable,
+    ColumnMap,
+    WatermarkColumn,
+    /// Column patterns ignored in every table
+    IgnoredColumns,
+    /// Rows per multi-row INSERT or upsert
+    RowsPerStatement,
+}
+
+impl OptionField {
+    pub fn title(&self) -> &'static str {
+        match self {
+            OptionField::KeyColumns => "Key Columns",
+            OptionField::Filter => "Row Filter",
+            OptionField::TargetFilter => "Target Row Filter",
+            OptionField::IncludeColumns => "Compared Columns",
+            OptionField::ExcludeColumns => "Excluded Columns",
+            OptionField::TargetTable => "Target Table",
+            OptionField::ColumnMap => "Column Mapping",
+            OptionField::WatermarkColumn => "Watermark Column",
+            OptionField::IgnoredColumns => "Columns Ignored in All Tables",
+            OptionField::RowsPerStatement => "Rows per Statement",
+        }
+    }
+
+    pub fn hint(&self) -> &'static str {
+        match self {
+            OptionField::KeyColumns => {
+                "Comma-separated unique, non-null columns identifying a row. Empty: primary key or unique index"
+            }
+            OptionField::Filter => {
+                "SQL condition applied on source and target, e.g. tenant_id = 42. Empty: all rows"
+            }
+            OptionField::TargetFilter => {
+                "SQL condition for the target when the row filter names columns differently there. Empty: the row filter"
+            }
+            OptionField::IncludeColumns => {
+                "Comma-separated columns to compare, * and ? wildcards allowed. Empty: all columns"
+            }
+            OptionField::ExcludeColumns => {
+                "Comma-separated columns to ignore when comparing and updating, e.g. updated_at, last_seen_*"
+            }
+            OptionField::TargetTable => {
+                "Name of the table in the target. Empty: the source name"
+            }
+            OptionField::ColumnMap => {
+                "Comma-separated source=target pairs for columns named differently in the target, e.g. email=mail"
+            }
+            OptionField::WatermarkColumn => {
+                "Column that grows with every insert or change, e.g. updated_at. Saving resets the synced watermark. Empty: all rows"
+            }
+            OptionField::IgnoredColumns => {
+                "Comma-separated column patterns ignored in every table, e.g. *_at"
+            }
+            OptionField::RowsPerStatement => {
+                "Rows written by one multi-row INSERT or upsert when applying"
+            }
+        }
+    }
+
+    /// Whether the setting applies to all tables rather than the selected one
+    pub fn is_global(&self) -> bool {
+        matches!(self, OptionField::IgnoredColumns | OptionField::RowsPerStatement)
+    }
+}
+
+/// Split a comma-separated list, dropping empty entries
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// Column mapping as `source→target` pairs
+fn mapping_list(column_map: &BTreeMap<String, String>) -> String {
+    column_map
+        .iter()
+        .map(|(source, target)| format!("{}→{}", source, target))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
 /// Data sync view state
 pub struct DataSyncState {
@@ -20,6 +117,28 @@ pub struct DataSyncState {
     pub status: String,
     pub loading: bool,
     pub focus_left: bool, // true = table list, false = diff list
+    /// Diffs marked for applying
+    pub marked: BTreeSet<usize>,
+    /// Apply confirmation dialog is open
+    pub confirm_apply: bool,
+    /// Copy confirmation dialog is open
+    pub confirm_copy: bool,
+    /// Number of statements executed per transaction when applying
+    pub batch_size: usize,
+    /// Compare server-side checksums of key ranges before fetching rows
+    pub checksum_mode: bool,
+    /// How row changes are written when applying
+    pub sql_options: SqlOptions,
+    /// Compare settings by table name
+    pub options: HashMap<String, CompareOptions>,
+    /// Column patterns ignored in every table
+    pub ignored_columns: Vec<String>,
+    /// Setting being edited and the text entered so far
+    pub input: Option<(OptionField, String)>,
+    /// Difference counts of the last comparison of all tables, by table name
+    pub summary: HashMap<String, TableDataInfo>,
+    /// Watermarks the tables were last synced up to, by table name
+    pub watermarks: HashMap<String, SavedWatermark>,
 }
 
 impl Default for DataSyncState {
@@ -36,11 +155,192 @@ impl Default for DataSyncState {
             status: "Press [Ctrl+L] to load tables".to_string(),
             loading: false,
             focus_left: true,
+            marked: BTreeSet::new(),
+            confirm_apply: false,
+            confirm_copy: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+            checksum_mode: false,
+            sql_options: SqlOptions::default(),
+            options: HashMap::new(),
+            ignored_columns: Vec::new(),
+            input: None,
+            summary: HashMap::new(),
+            watermarks: HashMap::new(),
         }
     }
 }
 
 impl DataSyncState {
+    pub fn set_diffs(&mut self, diffs: Vec<DataDiffResult>) {
+        self.diffs = diffs;
+        self.selected_diff_index = 0;
+        self.marked.clear();
+    }
+
+    /// Show the summary of a comparison of all tables, listing the tables
+    /// in the order they were compared
+    pub fn set_summary(&mut self, summary: Vec<TableDataInfo>) {
+        self.tables = summary.iter().map(|info| info.table_name.clone()).collect();
+        self.selected_table_index = 0;
+        self.summary = summary.into_iter().map(|info| (info.table_name.clone(), info)).collect();
+    }
+
+    pub fn selected_table(&self) -> Option<&String> {
+        self.tables.get(self.selected_table_index)
+    }
+
+    /// Compare settings of `table`, the defaults if none were set
+    pub fn table_options(&self, table: &str) -> CompareOptions {
+        self.options.get(table).cloned().unwrap_or_default()
+    }
+
+    /// Settings to compare `table` with: its own plus the globally ignored
+    /// columns, starting at its synced watermark
+    pub fn compare_options(&self, table: &str) -> CompareOptions {
+        let mut options = self.table_options(table);
+        options.exclude_columns.extend(self.ignored_columns.iter().cloned());
+        apply_watermark(&mut options, self.watermarks.get(table));
+        options
+    }
+
+    /// Open the input dialog for `field`
+    pub fn start_input(&mut self, field: OptionField) {
+        let table = self.selected_table().cloned().unwrap_or_default();
+        if table.is_empty() && !field.is_global() {
+            return;
+        }
+        let options = self.table_options(&table);
+        let value = match field {
+            OptionField::KeyColumns => options.key_columns.join(", "),
+            OptionField::Filter => options.filter,
+            OptionField::TargetFilter => options.target_filter,
+            OptionField::IncludeColumns => options.include_columns.join(", "),
+            OptionField::ExcludeColumns => options.exclude_columns.join(", "),
+            OptionField::TargetTable => options.target_table,
+            OptionField::ColumnMap => options
+                .column_map
+                .iter()
+                .map(|(source, target)| format!("{}={}", source, target))
+                .collect::<Vec<_>>()
+                .join(", "),
+            OptionField::WatermarkColumn => options.watermark_column,
+            OptionField::IgnoredColumns => self.ignored_columns.join(", "),
+            OptionField::RowsPerStatement => self.sql_options.rows_per_statement.to_string(),
+        };
+        self.input = Some((field, value));
+    }
+
+    /// Store the entered value and close the dialog. Returns the field that
+    /// changed; unless it is global, it belongs to the selected table.
+    pub fn finish_input(&mut self) -> Option<OptionField> {
+        let (field, value) = self.input.take()?;
+        let table = self.selected_table().cloned().unwrap_or_default();
+        if table.is_empty() && !field.is_global() {
+            return None;
+        }
+
+        self.status = match field {
+            OptionField::KeyColumns => {
+                let options = self.options.entry(table.clone()).or_default();
+                options.key_columns = split_list(&value);
+                if options.key_columns.is_empty() {
+                    format!("{}: key is the primary key or a unique index", table)
+                } else {
+                    format!("{}: key is {}", table, options.key_columns.join(", "))
+                }
+            }
+            OptionField::Filter => {
+                let options = self.options.entry(table.clone()).or_default();
+                options.filter = value.trim().to_string();
+                if options.filter.is_empty() {
+                    format!("{}: comparing all rows", table)
+                } else {
+                    format!("{}: comparing rows where {}", table, options.filter)
+                }
+            }
+            OptionField::TargetFilter => {
+                let options = self.options.entry(table.clone()).or_default();
+                options.target_filter = value.trim().to_string();
+                if options.target_filter.is_empty() {
+                    format!("{}: target rows filtered like the source", table)
+                } else {
+                    format!("{}: comparing target rows where {}", table, options.target_filter)
+                }
+            }
+            OptionField::IncludeColumns => {
+                let options = self.options.entry(table.clone()).or_default();
+                options.include_columns = split_list(&value);
+                if options.include_columns.is_empty() {
+                    format!("{}: comparing all columns", table)
+                } else {
+                    format!("{}: comparing only {}", table, options.include_columns.join(", "))
+                }
+            }
+            OptionField::ExcludeColumns => {
+                let options = self.options.entry(table.clone()).or_default();
+                options.exclude_columns = split_list(&value);
+                if options.exclude_columns.is_empty() {
+                    format!("{}: no columns excluded", table)
+                } else {
+                    format!("{}: ignoring {}", table, options.exclude_columns.join(", "))
+                }
+            }
+            OptionField::TargetTable => {
+                let options = self.options.entry(table.clone()).or_default();
+                options.target_table = value.trim().to_string();
+                format!("{}: target table is {}", table, options.target_table(&table))
+            }
+            OptionField::ColumnMap => {
+                let column_map = match parse_column_map(&value) {
+                    Ok(column_map) => column_map,
+                    Err(e) => {
+                        // Keep the dialog open to correct the entry
+                        self.status = e.to_string();
+                        self.input = Some((field, value));
+                        return None;
+                    }
+                };
+                let options = self.options.entry(table.clone()).or_default();
+                options.column_map = column_map;
+                if options.column_map.is_empty() {
+                    format!("{}: columns have the same names in the target", table)
+                } else {
+                    format!("{}: mapping columns {}", table, mapping_list(&options.column_map))
+                }
+            }
+            OptionField::WatermarkColumn => {
+                let options = self.options.entry(table.clone()).or_default();
+                options.watermark_column = value.trim().to_string();
+                self.watermarks.remove(&table);
+                if options.watermark_column.is_empty() {
+                    format!("{}: comparing all rows", table)
+                } else {
+                    format!("{}: syncing incrementally by {}, starting with all rows", table, options.watermark_column)
+                }
+            }
+            OptionField::IgnoredColumns => {
+                self.ignored_columns = split_list(&value);
+                if self.ignored_columns.is_empty() {
+                    "No columns ignored in all tables".to_string()
+                } else {
+                    format!("Ignoring {} in all tables", self.ignored_columns.join(", "))
+                }
+            }
+            OptionField::RowsPerStatement => match value.trim().parse::<usize>() {
+                Ok(rows) if rows > 0 => {
+                    self.sql_options.rows_per_statement = rows;
+                    format!("Writing up to {} rows per statement", rows)
+                }
+                _ => {
+                    self.status = "Rows per statement must be a positive number".to_string();
+                    self.input = Some((field, value));
+                    return None;
+                }
+            },
+        };
+        Some(field)
+    }
+
     pub fn next_table(&mut self) {
         if !self.tables.is_empty() {
             self.selected_table_index = (self.selected_table_index + 1) % self.tables.len();
@@ -70,6 +370,42 @@ impl DataSyncState {
     pub fn toggle_focus(&mut self) {
         self.focus_left = !self.focus_left;
     }
+
+    pub fn toggle_mark(&mut self) {
+        if self.selected_diff_index < self.diffs.len() && !self.marked.remove(&self.selected_diff_index) {
+            self.marked.insert(self.selected_diff_index);
+        }
+    }
+
+    pub fn toggle_mark_all(&mut self) {
+        if self.marked.len() == self.diffs.len() {
+            self.marked.clear();
+        } else {
+            self.marked = (0..self.diffs.len()).collect();
+        }
+    }
+
+    /// Switch to the next way of writing row changes
+    pub fn cycle_sql_mode(&mut self) {
+        self.sql_options.mode = self.sql_options.mode.next();
+        self.status = match self.sql_options.mode {
+            SqlMode::PerRow => "Applying one statement per row".to_string(),
+            mode => format!(
+                "Applying as {}s of up to {} rows",
+                mode.label(),
+                self.sql_options.rows_per_statement
+            ),
+        };
+    }
+
+    /// Diffs to apply: the marked ones, or the selected one if none are marked
+    pub fn diffs_to_apply(&self) -> Vec<&DataDiffResult> {
+        if self.marked.is_empty() {
+            self.diffs.get(self.selected_diff_index).into_iter().collect()
+        } else {
+            self.marked.iter().filter_map(|i| self.diffs.get(*i)).collect()
+        }
+    }
 }
 
 /// Draw data sync view
@@ -77,7 +413,7 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // Header
+            Constraint::Length(5), // Header
             Constraint::Min(10),   // Main content
             Constraint::Length(5), // SQL Preview
             Constraint::Length(1), // Status
@@ -85,10 +421,50 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
         .split(area);
 
     // Header
+    let options = state
+        .selected_table()
+        .map(|t| state.table_options(t))
+        .unwrap_or_default();
+    let list_or = |list: &[String], empty: &str| {
+        if list.is_empty() { empty.to_string() } else { list.join(", ") }
+    };
+    let watermark = match state.selected_table().map(|t| state.compare_options(t)) {
+        Some(CompareOptions { watermark_column, watermark: Some(value), .. }) => {
+            format!("{} >= {}", watermark_column, value)
+        }
+        Some(options) if !options.watermark_column.is_empty() => format!("{} (not synced yet)", options.watermark_column),
+        _ => "none".to_string(),
+    };
+    let last_compare = match state.selected_table().and_then(|t| state.summary.get(t)) {
+        Some(TableDataInfo { error: Some(e), .. }) => format!("error: {}", e),
+        Some(info) => format!(
+            "{} source rows, {} target rows, {} inserts, {} updates, {} deletes",
+            info.source_count, info.target_count, info.insert_count, info.update_count, info.delete_count
+        ),
+        None => "none".to_string(),
+    };
     let header = Paragraph::new(format!(
-        "Source: {}  ->  Target: {}",
+        "Source: {}  ->  Target: {}  |  Compare: {}  |  Write: {}\nKey: {}  |  Filter: {}  |  Columns: {}  |  Excluded: {}  |  Ignored everywhere: {}\nTarget table: {}  |  Mapped columns: {}  |  Watermark: {}  |  Last compare all: {}",
         if state.source_name.is_empty() { "<not set>" } else { &state.source_name },
         if state.target_name.is_empty() { "<not set>" } else { &state.target_name },
+        if state.checksum_mode { "checksum" } else { "full" },
+        match state.sql_options.mode {
+            SqlMode::PerRow => SqlMode::PerRow.label().to_string(),
+            mode => format!("{}, {} rows", mode.label(), state.sql_options.rows_per_statement),
+        },
+        list_or(&options.key_columns, "auto"),
+        match (options.filter.is_empty(), options.target_filter.is_empty()) {
+            (true, true) => "none".to_string(),
+            (_, true) => options.filter.clone(),
+            _ => format!("{} / target: {}", options.filter, options.target_filter),
+        },
+        list_or(&options.include_columns, "all"),
+        list_or(&options.exclude_columns, "none"),
+        list_or(&state.ignored_columns, "none"),
+        if options.target_table.is_empty() { "same name" } else { &options.target_table },
+        if options.column_map.is_empty() { "none".to_string() } else { mapping_list(&options.column_map) },
+        watermark,
+        last_compare,
     ))
     .block(Block::default().borders(Borders::ALL).title("Data Sync"));
     f.render_widget(header, chunks[0]);
@@ -110,7 +486,19 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
             } else {
                 Style::default()
             };
-            Row::new(vec![Cell::from(name.clone())]).style(style)
+            let summary = match state.summary.get(name) {
+                None => Cell::from(""),
+                Some(info) if info.error.is_some() => Cell::from("error").style(Style::default().fg(Color::Red)),
+                Some(info) if info.difference_count() == 0 => {
+                    Cell::from("in sync").style(Style::default().fg(Color::Green))
+                }
+                Some(info) => Cell::from(format!(
+                    "+{} ~{} -{}",
+                    info.insert_count, info.update_count, info.delete_count
+                ))
+                .style(Style::default().fg(Color::Yellow)),
+            };
+            Row::new(vec![Cell::from(name.clone()), summary]).style(style)
         })
         .collect();
 
@@ -123,7 +511,7 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
         (Style::default().fg(Color::DarkGray), Style::default())
     };
 
-    let table_list = Table::new(table_rows, [Constraint::Min(10)])
+    let table_list = Table::new(table_rows, [Constraint::Min(10), Constraint::Length(16)])
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -136,7 +524,7 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
     f.render_stateful_widget(table_list, main_chunks[0], &mut state.table_list_state);
 
     // Diff list
-    let diff_header_cells = ["Type", "Primary Key", "Changes"]
+    let diff_header_cells = ["", "Type", "Key", "Changes"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
     let diff_header = Row::new(diff_header_cells).height(1);
@@ -152,26 +540,33 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
                 DataDiffType::Delete => (Style::default().fg(Color::Red), "DELETE"),
             };
 
-            let pk_str: String = diff
-                .primary_key
-                .iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect::<Vec<_>>()
-                .join(", ");
+            let keyless = diff.primary_key.is_empty();
+            let pk_str: String = if keyless {
+                "<no key>".to_string()
+            } else {
+                diff.primary_key
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
 
             let change_str = match diff.diff_type {
+                DataDiffType::Insert if keyless => "Missing copy of row".to_string(),
+                DataDiffType::Delete if keyless => "Surplus copy of row".to_string(),
                 DataDiffType::Insert => "New row".to_string(),
                 DataDiffType::Delete => "Remove row".to_string(),
                 DataDiffType::Update => {
+                    // Only the compared columns are in the old values
                     let changed: Vec<String> = diff
-                        .new_values
+                        .old_values
                         .as_ref()
-                        .map(|nv| {
-                            nv.keys()
+                        .map(|ov| {
+                            ov.keys()
                                 .filter(|k| {
-                                    diff.old_values
+                                    diff.new_values
                                         .as_ref()
-                                        .map(|ov| ov.get(*k) != nv.get(*k))
+                                        .map(|nv| nv.get(*k) != ov.get(*k))
                                         .unwrap_or(true)
                                 })
                                 .cloned()
@@ -192,7 +587,10 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
                 Style::default()
             };
 
+            let mark = if state.marked.contains(&i) { "[x]" } else { "[ ]" };
+
             Row::new(vec![
+                Cell::from(mark),
                 Cell::from(type_str).style(type_style),
                 Cell::from(pk_str),
                 Cell::from(change_str),
@@ -213,6 +611,7 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
     let diff_table = Table::new(
         diff_rows,
         [
+            Constraint::Length(3),
             Constraint::Length(8),
             Constraint::Length(20),
             Constraint::Min(20),
@@ -222,7 +621,11 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .title(format!("Data Differences ({})", state.diffs.len()))
+            .title(format!(
+                "Data Differences ({}, {} marked)",
+                state.diffs.len(),
+                state.marked.len()
+            ))
             .title_style(diff_title_style)
             .border_style(diff_border_style),
     );
@@ -244,9 +647,35 @@ pub fn draw_data_sync(f: &mut Frame, area: Rect, state: &mut DataSyncState) {
 
     // Status / hints
     let status = Paragraph::new(format!(
-        "{} | [Ctrl+L]Load [Enter]Compare [Tab]Focus [↑↓]Navigate [Esc]Quit",
+        "{} | [Ctrl+L]Load [Enter]Compare [Ctrl+D]Compare all [Space]Mark [Ctrl+A]Mark all [Ctrl+E]Apply [Ctrl+K]Checksum [Ctrl+W]Write mode [Ctrl+B]Rows/stmt [Ctrl+U]Key [Ctrl+F]Filter [Ctrl+V]Target filter [Ctrl+O]Only [Ctrl+X]Exclude [Ctrl+G]Ignore [Ctrl+T]Target table [Ctrl+N]Map columns [Ctrl+P]Watermark [Ctrl+R]Check mapping [Ctrl+Y]Copy table [Tab]Focus [↑↓]Navigate [Esc]Quit",
         state.status
     ))
     .style(Style::default().fg(Color::Cyan));
     f.render_widget(status, chunks[3]);
+
+    if state.confirm_apply {
+        let message = format!(
+            "Apply {} row change(s) to target '{}'?",
+            state.diffs_to_apply().len(),
+            if state.target_name.is_empty() { "<not set>" } else { &state.target_name },
+        );
+        draw_confirm(f, area, "Confirm Apply", &message);
+    }
+
+    if let (true, Some(table)) = (state.confirm_copy, state.selected_table()) {
+        let message = format!(
+            "Copy all rows of {} into its empty table in target '{}'?",
+            table,
+            if state.target_name.is_empty() { "<not set>" } else { &state.target_name },
+        );
+        draw_confirm(f, area, "Confirm Copy", &message);
+    }
+
+    if let Some((field, value)) = &state.input {
+        let title = match state.selected_table() {
+            Some(table) if !field.is_global() => format!("{}: {}", field.title(), table),
+            _ => field.title().to_string(),
+        };
+        draw_input(f, area, &title, field.hint(), value);
+    }
 }
@@ -14,7 +14,7 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Tabs},
+    widgets::{Block, Borders, Clear, Paragraph, Tabs},
     Frame,
 };
 
@@ -101,3 +101,37 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         ])
         .split(popup_layout[1])[1]
 }
+
+/// Draw a yes/no confirmation dialog over `area`
+pub fn draw_confirm(f: &mut Frame, area: Rect, title: &str, message: &str) {
+    let popup = centered_rect(50, 30, area);
+
+    let dialog = Paragraph::new(format!("{}\n\n[y]Confirm  [n]Cancel", message))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title.to_string())
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .style(Style::default().fg(Color::White));
+
+    f.render_widget(Clear, popup);
+    f.render_widget(dialog, popup);
+}
+
+/// Draw a single-line text input dialog over `area`
+pub fn draw_input(f: &mut Frame, area: Rect, title: &str, hint: &str, value: &str) {
+    let popup = centered_rect(60, 30, area);
+
+    let dialog = Paragraph::new(format!("{}\n\n> {}_\n\n[Enter]Save  [Esc]Cancel", hint, value))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title.to_string())
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .style(Style::default().fg(Color::White));
+
+    f.render_widget(Clear, popup);
+    f.render_widget(dialog, popup);
+}
@@ -1,11 +1,14 @@
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    text::Line,
     widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
     Frame,
 };
+use std::collections::BTreeSet;
 
-use crate::db::DiffResult;
+use super::draw_confirm;
+use crate::db::{DiffResult, StatementResult, StatementStatus};
 
 /// Schema diff view state
 pub struct SchemaDiffState {
@@ -16,6 +19,12 @@ pub struct SchemaDiffState {
     pub table_state: TableState,
     pub status: String,
     pub loading: bool,
+    /// Diffs marked for applying
+    pub marked: BTreeSet<usize>,
+    /// Apply confirmation dialog is open
+    pub confirm_apply: bool,
+    /// Execution status of each diff after the last apply
+    pub apply_status: Vec<Option<StatementStatus>>,
 }
 
 impl Default for SchemaDiffState {
@@ -28,11 +37,21 @@ impl Default for SchemaDiffState {
             table_state: TableState::default(),
             status: "Press [Enter] to compare schemas".to_string(),
             loading: false,
+            marked: BTreeSet::new(),
+            confirm_apply: false,
+            apply_status: Vec::new(),
         }
     }
 }
 
 impl SchemaDiffState {
+    pub fn set_diffs(&mut self, diffs: Vec<DiffResult>) {
+        self.apply_status = vec![None; diffs.len()];
+        self.diffs = diffs;
+        self.selected_index = 0;
+        self.marked.clear();
+    }
+
     pub fn next(&mut self) {
         if !self.diffs.is_empty() {
             self.selected_index = (self.selected_index + 1) % self.diffs.len();
@@ -44,16 +63,60 @@ impl SchemaDiffState {
             self.selected_index = (self.selected_index + self.diffs.len() - 1) % self.diffs.len();
         }
     }
+
+    pub fn toggle_mark(&mut self) {
+        if self.selected_index < self.diffs.len() && !self.marked.remove(&self.selected_index) {
+            self.marked.insert(self.selected_index);
+        }
+    }
+
+    pub fn toggle_mark_all(&mut self) {
+        if self.marked.len() == self.diffs.len() {
+            self.marked.clear();
+        } else {
+            self.marked = (0..self.diffs.len()).collect();
+        }
+    }
+
+    /// Indexes of diffs to apply: the marked ones, or the selected one if none are marked
+    pub fn indexes_to_apply(&self) -> Vec<usize> {
+        if self.marked.is_empty() {
+            if self.selected_index < self.diffs.len() {
+                vec![self.selected_index]
+            } else {
+                Vec::new()
+            }
+        } else {
+            self.marked.iter().copied().collect()
+        }
+    }
+
+    /// Record results of applying the diffs at `indexes`
+    pub fn set_apply_results(&mut self, indexes: &[usize], results: &[StatementResult]) {
+        for (index, result) in indexes.iter().zip(results) {
+            if let Some(slot) = self.apply_status.get_mut(*index) {
+                *slot = Some(result.status.clone());
+            }
+        }
+        self.marked.clear();
+    }
 }
 
 /// Draw schema diff view
 pub fn draw_schema_diff(f: &mut Frame, area: Rect, state: &mut SchemaDiffState) {
+    // Changed routines and triggers show their definition diff beside the SQL
+    let body_diff = state
+        .diffs
+        .get(state.selected_index)
+        .map(|d| d.body_diff.clone())
+        .filter(|d| !d.is_empty());
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3), // Header
             Constraint::Min(10),   // Table
-            Constraint::Length(6), // SQL Preview
+            Constraint::Length(if body_diff.is_some() { 12 } else { 6 }), // SQL Preview
             Constraint::Length(1), // Status
         ])
         .split(area);
@@ -68,7 +131,7 @@ pub fn draw_schema_diff(f: &mut Frame, area: Rect, state: &mut SchemaDiffState)
     f.render_widget(header, chunks[0]);
 
     // Diff table
-    let header_cells = ["Type", "Table", "Details"]
+    let header_cells = ["", "Type", "Table", "Details", "Result"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
     let header_row = Row::new(header_cells).height(1);
@@ -90,10 +153,22 @@ pub fn draw_schema_diff(f: &mut Frame, area: Rect, state: &mut SchemaDiffState)
                 Style::default()
             };
 
+            let mark = if state.marked.contains(&i) { "[x]" } else { "[ ]" };
+
+            let (result_style, result_str) = match state.apply_status.get(i).cloned().flatten() {
+                Some(StatementStatus::Applied) => (Style::default().fg(Color::Green), "OK"),
+                Some(StatementStatus::Failed(_)) => (Style::default().fg(Color::Red), "FAILED"),
+                Some(StatementStatus::RolledBack) => (Style::default().fg(Color::Yellow), "ROLLED BACK"),
+                Some(StatementStatus::Skipped) => (Style::default().fg(Color::DarkGray), "SKIPPED"),
+                None => (Style::default(), ""),
+            };
+
             Row::new(vec![
+                Cell::from(mark),
                 Cell::from(type_str).style(type_style),
                 Cell::from(diff.table_name.clone()),
                 Cell::from(diff.detail.clone()),
+                Cell::from(result_str).style(result_style),
             ])
             .style(style)
         })
@@ -102,37 +177,75 @@ pub fn draw_schema_diff(f: &mut Frame, area: Rect, state: &mut SchemaDiffState)
     let table = Table::new(
         rows,
         [
+            Constraint::Length(3),
             Constraint::Length(10),
             Constraint::Length(20),
             Constraint::Min(30),
+            Constraint::Length(12),
         ],
     )
     .header(header_row)
     .block(Block::default().borders(Borders::ALL).title(format!(
-        "Differences ({})",
-        state.diffs.len()
+        "Differences ({}, {} marked)",
+        state.diffs.len(),
+        state.marked.len()
     )));
 
     state.table_state.select(Some(state.selected_index));
     f.render_stateful_widget(table, chunks[1], &mut state.table_state);
 
-    // SQL Preview
+    // SQL Preview, followed by the error if the statement failed
     let sql = state
         .diffs
         .get(state.selected_index)
-        .map(|d| d.sql.clone())
+        .map(|d| match state.apply_status.get(state.selected_index) {
+            Some(Some(StatementStatus::Failed(e))) => format!("{}\n-- Error: {}", d.sql, e),
+            _ => d.sql.clone(),
+        })
         .unwrap_or_else(|| "No difference selected".to_string());
 
     let sql_preview = Paragraph::new(sql)
         .block(Block::default().borders(Borders::ALL).title("SQL Preview"))
         .style(Style::default().fg(Color::Cyan));
-    f.render_widget(sql_preview, chunks[2]);
+    match body_diff {
+        Some(body_diff) => {
+            let preview_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(chunks[2]);
+            let lines: Vec<Line> = body_diff
+                .lines()
+                .map(|line| {
+                    let color = match line.chars().next() {
+                        Some('+') => Color::Green,
+                        Some('-') => Color::Red,
+                        _ => Color::Gray,
+                    };
+                    Line::styled(line.to_string(), Style::default().fg(color))
+                })
+                .collect();
+            let diff_view = Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title("Definition Diff (target -> source)"));
+            f.render_widget(diff_view, preview_chunks[0]);
+            f.render_widget(sql_preview, preview_chunks[1]);
+        }
+        None => f.render_widget(sql_preview, chunks[2]),
+    }
 
     // Status / hints
     let status = Paragraph::new(format!(
-        "{} | [Enter]Compare [↑↓]Navigate [Esc]Quit",
+        "{} | [Enter]Compare [Space]Mark [Ctrl+A]Mark all [Ctrl+E]Apply [Ctrl+S]Export [↑↓]Navigate [Esc]Quit",
         state.status
     ))
     .style(Style::default().fg(Color::Cyan));
     f.render_widget(status, chunks[3]);
+
+    if state.confirm_apply {
+        let message = format!(
+            "Apply {} change(s) to target '{}'?",
+            state.indexes_to_apply().len(),
+            if state.target_name.is_empty() { "<not set>" } else { &state.target_name },
+        );
+        draw_confirm(f, area, "Confirm Apply", &message);
+    }
 }
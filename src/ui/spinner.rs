@@ -155,19 +155,15 @@ pub fn draw_spinner(f: &mut Frame, area: Rect, spinner: &Spinner) {
     f.render_widget(paragraph, area);
 }
 
-/// Draw a progress bar with percentage
+/// Draw a progress bar with percentage, followed by `message`.
+/// The bar takes the width the percentage and message leave free.
 pub fn draw_progress_bar(f: &mut Frame, area: Rect, progress: f64, message: &str) {
-    let width = area.width.saturating_sub(2) as usize;
+    let label = format!(" {:.0}% {}", progress * 100.0, message);
+    let width = (area.width as usize).saturating_sub(2 + label.chars().count());
     let filled = ((progress * width as f64) as usize).min(width);
     let empty = width.saturating_sub(filled);
 
-    let bar = format!(
-        "[{}{}] {:.0}% {}",
-        "█".repeat(filled),
-        "░".repeat(empty),
-        progress * 100.0,
-        message
-    );
+    let bar = format!("[{}{}]{}", "█".repeat(filled), "░".repeat(empty), label);
 
     let paragraph = Paragraph::new(bar).style(Style::default().fg(Color::Cyan));
     f.render_widget(paragraph, area);
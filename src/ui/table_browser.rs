@@ -5,6 +5,8 @@ use ratatui::{
     Frame,
 };
 
+use crate::db::Value;
+
 /// Table browser view state
 pub struct TableBrowserState {
     pub connection_name: String,
@@ -12,7 +14,7 @@ pub struct TableBrowserState {
     pub selected_table_index: usize,
     pub table_list_state: TableState,
     pub columns: Vec<String>,
-    pub rows: Vec<Vec<String>>,
+    pub rows: Vec<Vec<Value>>,
     pub selected_row_index: usize,
     pub data_table_state: TableState,
     pub page: usize,
@@ -215,7 +217,13 @@ pub fn draw_table_browser(f: &mut Frame, area: Rect, state: &mut TableBrowserSta
             let row_start = start_col.min(row.len());
             let cells: Vec<Cell> = row[row_start..row_end]
                 .iter()
-                .map(|c| Cell::from(c.clone()))
+                .map(|c| {
+                    if c.is_null() {
+                        Cell::from("NULL").style(Style::default().fg(Color::DarkGray))
+                    } else {
+                        Cell::from(c.to_string())
+                    }
+                })
                 .collect();
             Row::new(cells).style(style)
         })